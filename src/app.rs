@@ -1,9 +1,16 @@
 use std::collections::HashMap;
+use crate::batch::{BatchConfig, BatchReport, BatchRun};
+use crate::validation::{Diagnostic, ModelEntity};
+use crate::import::{self, Sheet};
+use crate::export;
+use crate::conformance::{self, ConformanceReport, DeviationKind};
+use crate::persistence::{self, Format, LoadError, LoadedModel, LoadedSession, Session};
 use crate::execution::{Execution, TransactionInstanceId};
 use crate::model::{CPAct, Model, SubjectId, TransactionId};
 use crate::windows::EguiWindows;
 use std::future::Future;
 use std::sync::mpsc::{channel, Receiver, Sender};
+use strum_macros::EnumIter;
 
 #[cfg(not(target_arch = "wasm32"))]
 fn execute<F: Future<Output = ()> + Send + 'static>(f: F) {
@@ -23,14 +30,59 @@ pub struct InitiateTransactionModalContext {
     pub initiated_transaction_id_o: Option<TransactionId>,
     pub requested_product: String,
     pub addressee_id_o: Option<SubjectId>,
+    #[serde(skip)]
+    pub error_o: Option<String>,
+}
+
+/// Order of the items shown on an agenda.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize, EnumIter)]
+pub enum AgendaSort {
+    #[default]
+    Timestamp,
+    Transaction,
+    Fact,
+}
+
+impl std::fmt::Display for AgendaSort {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AgendaSort::Timestamp => write!(f, "Timestamp"),
+            AgendaSort::Transaction => write!(f, "Transaction"),
+            AgendaSort::Fact => write!(f, "Fact"),
+        }
+    }
+}
+
+/// Which items of an agenda are shown.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize, EnumIter)]
+pub enum AgendaFilter {
+    #[default]
+    All,
+    Actionable, // at least one of the next acts can be performed now
+    Blocked,    // all the next acts are impeded by wait links
+}
+
+impl std::fmt::Display for AgendaFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AgendaFilter::All => write!(f, "All"),
+            AgendaFilter::Actionable => write!(f, "Only actionable"),
+            AgendaFilter::Blocked => write!(f, "Only blocked"),
+        }
+    }
 }
 
 #[derive(Default, serde::Deserialize, serde::Serialize)]
 #[serde(default)] // if we add new fields, give them default values when deserializing old state
 pub struct SubjectContext {
     pub focused_subject_id_o: Option<SubjectId>,
+    pub inbox: bool, // the agendas of all subjects are shown instead of the focused subject's pane
+    pub agenda_sort: AgendaSort,
+    pub agenda_filter: AgendaFilter,
     #[serde(skip)]
     pub selected_next_act: HashMap<(SubjectId, TransactionInstanceId), CPAct>,
+    #[serde(skip)]
+    pub error_o: Option<String>,
 }
 
 impl SubjectContext {
@@ -52,6 +104,100 @@ pub struct CPWorldContext {
     pub performer_filter: String,
 }
 
+#[derive(Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)] // if we add new fields, give them default values when deserializing old state
+pub struct TransactionsInstancesContext {
+    pub transaction_filter_o: Option<TransactionId>,
+    pub subject_filter_o: Option<SubjectId>, // initiator, executor or awaited performer
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)] // if we add new fields, give them default values when deserializing old state
+pub struct ClockContext {
+    pub advance_minutes: i64,
+    #[serde(skip)]
+    pub start_text: String,
+}
+
+impl Default for ClockContext {
+    fn default() -> Self {
+        Self {
+            advance_minutes: 60,
+            start_text: String::new(),
+        }
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)] // if we add new fields, give them default values when deserializing old state
+pub struct TimelineContext {
+    pub by_subject: bool, // lanes of subjects instead of transaction instances
+    pub pixels_per_hour: f32,
+}
+
+impl Default for TimelineContext {
+    fn default() -> Self {
+        Self {
+            by_subject: false,
+            pixels_per_hour: 120.0,
+        }
+    }
+}
+
+#[derive(Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)] // if we add new fields, give them default values when deserializing old state
+pub struct BatchContext {
+    pub transaction_id_o: Option<TransactionId>,
+    pub config: BatchConfig,
+    #[serde(skip)]
+    pub report_o: Option<Result<BatchReport, String>>,
+    #[serde(skip)]
+    pub run_o: Option<BatchRun>, // in progress, advanced with every frame
+}
+
+#[derive(Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)] // if we add new fields, give them default values when deserializing old state
+pub struct OcdEditorContext {
+    pub link_mode: bool, // dragging creates links instead of moving the shapes
+    #[serde(skip)]
+    pub selected_o: Option<ModelEntity>,
+    #[serde(skip)]
+    pub error_o: Option<String>,
+}
+
+#[derive(Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)] // if we add new fields, give them default values when deserializing old state
+pub struct PsdViewContext {
+    pub root_o: Option<TransactionInstanceId>, // the tree of transaction instances shown, all if none
+}
+
+#[derive(Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)] // if we add new fields, give them default values when deserializing old state
+pub struct ConformanceContext {
+    #[serde(skip)]
+    pub report_o: Option<(String, ConformanceReport)>, // file name of the log and its report
+    #[serde(skip)]
+    pub kind_filter_o: Option<DeviationKind>,
+}
+
+/// The diagnostics of the model, shown on every frame, so the model is validated again only once its generation (see
+/// [`AppContext::model_changed`]) has changed.
+#[derive(Default)]
+pub struct DiagnosticsCache {
+    model_generation_o: Option<u64>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl DiagnosticsCache {
+    pub fn get(&mut self, model: &Model, model_generation: u64) -> &[Diagnostic] {
+        if self.model_generation_o != Some(model_generation) {
+            self.diagnostics = model.validate();
+            self.model_generation_o = Some(model_generation);
+        }
+        &self.diagnostics
+    }
+}
+
 #[derive(Default, serde::Deserialize, serde::Serialize)]
 #[serde(default)] // if we add new fields, give them default values when deserializing old state
 pub struct AppContext {
@@ -60,7 +206,100 @@ pub struct AppContext {
     pub initiate_transaction_modal_context: InitiateTransactionModalContext,
     pub subject_context: SubjectContext,
     pub c_p_world_context: CPWorldContext,
+    pub transactions_instances_context: TransactionsInstancesContext,
+    pub clock_context: ClockContext,
+    pub timeline_context: TimelineContext,
+    pub run_policies: bool,
+    #[serde(skip)]
+    pub policies_paused_at_o: Option<usize>, // the number of steps when undo or redo paused the automated subjects
+    pub batch_context: BatchContext,
+    pub conformance_context: ConformanceContext,
+    pub ocd_editor_context: OcdEditorContext,
+    pub psd_view_context: PsdViewContext,
     pub hi_transaction_instance_id_o: Option<TransactionInstanceId>, // highlighted
+    pub hi_entity_o: Option<ModelEntity>, // highlighted in the model editors
+    #[serde(skip)]
+    pub model_generation: u64, // counts the changes of the model
+    #[serde(skip)]
+    pub diagnostics_cache: DiagnosticsCache,
+}
+
+impl AppContext {
+    /// To be called by everything that changes the model: the editors, loading and importing.
+    pub fn model_changed(&mut self) {
+        self.model_generation += 1;
+    }
+
+    /// Replaces the model and its execution, forgetting only what refers to them: the selections, highlights and
+    /// results; the settings of the clock, the batch runs, the views and their filters are kept.
+    pub fn load_session(&mut self, session: Session) {
+        self.model = session.model;
+        self.execution = session.execution;
+        self.model_changed();
+        self.initiate_transaction_modal_context = Default::default();
+        if self.subject_context.focused_subject_id_o.as_ref().is_some_and(|subject_id| self.model.find_subject(subject_id).is_none()) {
+            self.subject_context.focused_subject_id_o = None;
+        }
+        self.subject_context.selected_next_act.clear();
+        self.subject_context.error_o = None;
+        self.batch_context.transaction_id_o = None;
+        self.batch_context.report_o = None;
+        self.batch_context.run_o = None;
+        self.conformance_context.report_o = None;
+        self.ocd_editor_context.selected_o = None;
+        self.ocd_editor_context.error_o = None;
+        self.psd_view_context.root_o = None;
+        self.hi_transaction_instance_id_o = None;
+        self.hi_entity_o = None;
+        self.policies_paused_at_o = None;
+    }
+}
+
+#[derive(Clone, Copy)]
+enum FileKind {
+    Model,   // .dms
+    Session, // .dmss: model and execution
+    Sheet,   // .csv: imported into the model, see `import`
+    EventLog, // .xes or .csv: checked against the model, see `conformance`
+}
+
+impl std::fmt::Display for FileKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FileKind::Model => write!(f, "model"),
+            FileKind::Session => write!(f, "session"),
+            FileKind::Sheet => write!(f, "sheets"),
+            FileKind::EventLog => write!(f, "event log"),
+        }
+    }
+}
+
+impl FileKind {
+    fn extension(&self) -> &'static str {
+        match self {
+            FileKind::Model => "dms",
+            FileKind::Session => "dmss",
+            FileKind::Sheet => "csv",
+            FileKind::EventLog => "xes",
+        }
+    }
+
+    fn filter(&self) -> &'static str {
+        match self {
+            FileKind::Model => "*.dms",
+            FileKind::Session => "*.dmss",
+            FileKind::Sheet => "*.csv",
+            FileKind::EventLog => "*.xes",
+        }
+    }
+}
+
+type FileText = (FileKind, String, String); // kind, file name, contents
+
+enum LoadOutcome {
+    Failed(LoadError),
+    Repaired(Vec<String>),
+    Imported(Vec<String>),
 }
 
 /// We derive Deserialize/Serialize so we can persist app state on shutdown.
@@ -68,17 +307,20 @@ pub struct AppContext {
 #[serde(default)] // if we add new fields, give them default values when deserializing old state
 pub struct DemosimApp {
     #[serde(skip)]
-    model_text_channel: (Sender<String>, Receiver<String>),
+    file_text_channel: (Sender<FileText>, Receiver<FileText>),
     egui_windows: EguiWindows,
     app_context: AppContext,
+    #[serde(skip)]
+    load_outcome_o: Option<LoadOutcome>,
 }
 
 impl Default for DemosimApp {
     fn default() -> Self {
         Self {
-            model_text_channel: channel(),
+            file_text_channel: channel(),
             egui_windows: EguiWindows::default(),
             app_context: AppContext::default(),
+            load_outcome_o: None,
         }
     }
 }
@@ -92,21 +334,256 @@ impl DemosimApp {
         // Load previous app state (if any).
         // Note that you must enable the `persistence` feature for this to work.
         if let Some(storage) = cc.storage {
-            return eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default();
+            let mut app: Self = eframe::get_value(storage, eframe::APP_KEY).unwrap_or_default();
+            let AppContext { model, execution, .. } = &mut app.app_context;
+            execution.restore(model);
+            return app;
         }
 
         Default::default()
     }
 
-    fn try_load_model(&mut self) {
-        if let Ok(model_text) = self.model_text_channel.1.try_recv() {
-            let model: Model = ron::from_str(&model_text).unwrap();
-            self.app_context.model = model;
+    fn try_load_file(&mut self) {
+        if let Ok((file_kind, file_name, text)) = self.file_text_channel.1.try_recv() {
+            // On failure, the current model (and execution) is kept
+            let format = Format::from_file_name(&file_name);
+            let repairs_r = match file_kind {
+                FileKind::Model => persistence::read_model(&text, format).map(|LoadedModel { model, repairs, .. }| {
+                    self.app_context.model = model;
+                    self.app_context.model_changed();
+                    repairs
+                }),
+                FileKind::Session => persistence::read_session(&text, format).map(|LoadedSession { session, repairs }| {
+                    self.app_context.load_session(session);
+                    repairs
+                }),
+                FileKind::Sheet => {
+                    self.import_sheet(&file_name, &text);
+                    return;
+                }
+                FileKind::EventLog => {
+                    self.check_event_log(file_name, &text);
+                    return;
+                }
+            };
+            self.load_outcome_o = match repairs_r {
+                Ok(repairs) => (!repairs.is_empty()).then_some(LoadOutcome::Repaired(repairs)),
+                Err(error) => Some(LoadOutcome::Failed(error)),
+            };
+        }
+    }
+
+    fn load_file(&self, ctx: &egui::Context, file_kind: FileKind) {
+        let sender = self.file_text_channel.0.clone();
+        let task = rfd::AsyncFileDialog::new()
+            .set_title(format!("Load {}", file_kind))
+            .add_filter("DEMOsim", &[file_kind.filter()])
+            .add_filter("JSON", &["*.json"])
+            .pick_file();
+        let ctx = ctx.clone();
+        execute(async move {
+            let file = task.await;
+            if let Some(file) = file {
+                let text = file.read().await;
+                let _ = sender.send((file_kind, file.file_name(), String::from_utf8_lossy(&text).to_string()));
+                ctx.request_repaint();
+            }
+        });
+    }
+
+    // Sheets of several files are imported together, the Transaction Product Tables first
+    fn import_sheets(&self, ctx: &egui::Context) {
+        let sender = self.file_text_channel.0.clone();
+        let task = rfd::AsyncFileDialog::new()
+            .set_title("Import Transaction Product Table and links")
+            .add_filter("CSV", &[FileKind::Sheet.filter()])
+            .pick_files();
+        let ctx = ctx.clone();
+        execute(async move {
+            let Some(files) = task.await else { return };
+            let mut sheets = Vec::new();
+            for file in files {
+                let text = String::from_utf8_lossy(&file.read().await).to_string();
+                sheets.push((Sheet::detect(&text) != Some(Sheet::TransactionProductTable), file.file_name(), text));
+            }
+            sheets.sort_by_key(|(is_links, _, _)| *is_links);
+            for (_, file_name, text) in sheets {
+                let _ = sender.send((FileKind::Sheet, file_name, text));
+            }
+            ctx.request_repaint();
+        });
+    }
+
+    fn import_sheet(&mut self, file_name: &str, text: &str) {
+        let model = &mut self.app_context.model;
+        let mut messages = Vec::new();
+        let summary_o = match Sheet::detect(text) {
+            Some(Sheet::TransactionProductTable) => Some(import::import_transaction_product_table(model, text)),
+            Some(Sheet::Links) => Some(import::import_links(model, text)),
+            None => None,
+        };
+        match summary_o {
+            Some(summary) => {
+                self.app_context.model_changed();
+                messages.push(format!("{}: {}", file_name, summary));
+                messages.extend(summary.errors.iter().map(|error| format!("{}, {}", file_name, error)));
+            }
+            None => messages.push(format!("{}: neither a Transaction Product Table nor a links sheet, nothing imported", file_name)),
+        }
+        // Files imported together are reported together
+        if let Some(LoadOutcome::Imported(previous)) = &mut self.load_outcome_o {
+            previous.append(&mut messages);
+        } else {
+            self.load_outcome_o = Some(LoadOutcome::Imported(messages));
+        }
+    }
+
+    fn load_event_log(&self, ctx: &egui::Context) {
+        let sender = self.file_text_channel.0.clone();
+        let task = rfd::AsyncFileDialog::new()
+            .set_title(format!("Check {}", FileKind::EventLog))
+            .add_filter("XES", &[FileKind::EventLog.filter()])
+            .add_filter("CSV", &[FileKind::Sheet.filter()])
+            .pick_file();
+        let ctx = ctx.clone();
+        execute(async move {
+            let file = task.await;
+            if let Some(file) = file {
+                let text = file.read().await;
+                let _ = sender.send((FileKind::EventLog, file.file_name(), String::from_utf8_lossy(&text).to_string()));
+                ctx.request_repaint();
+            }
+        });
+    }
+
+    // A log that cannot be read is reported like a file that cannot be loaded
+    fn check_event_log(&mut self, file_name: String, text: &str) {
+        let log_r = if file_name.to_lowercase().ends_with(".csv") { conformance::read_csv_log(text) } else { conformance::read_xes_log(text) };
+        match log_r {
+            Ok(log) => {
+                let report = conformance::check_conformance(&self.app_context.model, &log);
+                self.app_context.conformance_context = ConformanceContext { report_o: Some((file_name, report)), kind_filter_o: None };
+                self.egui_windows.conformance = true;
+            }
+            Err(error) => self.load_outcome_o = Some(LoadOutcome::Failed(error)),
+        }
+    }
+
+    // The document is written in JSON if the chosen file name ends with .json
+    fn save_file<F>(&self, file_kind: FileKind, write: F) where F: Fn(Format) -> String {
+        let task = rfd::AsyncFileDialog::new()
+            .set_title(format!("Save {}", file_kind))
+            .add_filter("DEMOsim", &[file_kind.filter()])
+            .add_filter("JSON", &["*.json"])
+            .set_file_name(format!("{}.{}", self.app_context.model.name, file_kind.extension()))
+            .save_file();
+        let ron_text = write(Format::Ron);
+        let json_text = write(Format::Json);
+        execute(async move {
+            let file = task.await;
+            if let Some(file) = file {
+                let text = match Format::from_file_name(&file.file_name()) {
+                    Format::Ron => ron_text,
+                    Format::Json => json_text,
+                };
+                _ = file.write(text.as_bytes()).await;
+            }
+        });
+    }
+
+    fn export_file(&self, title: &str, extension: &str, text: String) {
+        let task = rfd::AsyncFileDialog::new()
+            .set_title(title)
+            .add_filter(extension.to_uppercase(), &[format!("*.{}", extension)])
+            .set_file_name(format!("{}.{}", self.app_context.model.name, extension))
+            .save_file();
+        execute(async move {
+            let file = task.await;
+            if let Some(file) = file {
+                _ = file.write(text.as_bytes()).await;
+            }
+        });
+    }
+
+    // A sequence diagram is of the tree of a transaction instance started on its own, chosen in the submenu
+    fn sequence_diagram_menu(&self, ui: &mut egui::Ui, title: &str, extension: &str, write: fn(&Model, &Execution, &TransactionInstanceId) -> String) {
+        let AppContext { model, execution, .. } = &self.app_context;
+        let roots: Vec<_> = execution.transactions_instances.iter().filter(|t_i| t_i.parent_transaction_instance_id.is_none()).collect();
+        ui.add_enabled_ui(!roots.is_empty(), |ui| {
+            ui.menu_button(title, |ui| {
+                for root in roots {
+                    let t_id = model.find_transaction(&root.transaction_id).map(|t| t.t_id.clone()).unwrap_or_default();
+                    if ui.button(format!("{}: {}", t_id, root.product_instance)).on_hover_text("The instance with all its subtransactions").clicked() {
+                        self.export_file("Export sequence diagram", extension, write(model, execution, &root.id));
+                        ui.close_menu();
+                    }
+                }
+            });
+        });
+    }
+
+    fn load_outcome_ui(&mut self, ctx: &egui::Context) {
+        let Some(load_outcome) = &self.load_outcome_o else { return };
+        let mut close = false;
+        egui::Window::new("Loading")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                match load_outcome {
+                    LoadOutcome::Failed(error) => {
+                        ui.colored_label(egui::Color32::RED, "The file could not be loaded, the current model and execution are kept.");
+                        ui.add_space(5.0);
+                        ui.label(error.to_string());
+                    }
+                    LoadOutcome::Repaired(repairs) => {
+                        ui.label("The file contained broken references and was repaired:");
+                        ui.add_space(5.0);
+                        for repair in repairs {
+                            ui.label(format!("• {}", repair));
+                        }
+                    }
+                    LoadOutcome::Imported(messages) => {
+                        egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                            for message in messages {
+                                ui.label(format!("• {}", message));
+                            }
+                        });
+                    }
+                }
+                ui.add_space(10.0);
+                if ui.button("OK").clicked() {
+                    close = true;
+                }
+            });
+        if close {
+            self.load_outcome_o = None;
         }
     }
 
 }
 
+// Runs of a batch performed per frame, so that the GUI stays responsive during long batches
+const BATCH_RUNS_PER_FRAME: usize = 20;
+
+impl DemosimApp {
+    fn advance_batch(&mut self, ctx: &egui::Context) {
+        let batch_context = &mut self.app_context.batch_context;
+        let Some(run) = &mut batch_context.run_o else { return };
+        match run.advance(BATCH_RUNS_PER_FRAME) {
+            Err(e) => {
+                batch_context.report_o = Some(Err(e.to_string()));
+                batch_context.run_o = None;
+            }
+            Ok(()) if run.is_finished() => {
+                batch_context.report_o = Some(Ok(run.report()));
+                batch_context.run_o = None;
+            }
+            Ok(()) => ctx.request_repaint(),
+        }
+    }
+}
+
 impl eframe::App for DemosimApp {
     /// Called by the frame work to save state before shutdown.
     fn save(&mut self, storage: &mut dyn eframe::Storage) {
@@ -115,7 +592,18 @@ impl eframe::App for DemosimApp {
 
     /// Called each time the UI needs repainting, which may be many times per second.
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        self.try_load_model();
+        self.try_load_file();
+        self.load_outcome_ui(ctx);
+        let AppContext { model, execution, run_policies, policies_paused_at_o, .. } = &mut self.app_context;
+        // Undone automated steps would be performed again right away, so after undo and redo the automated subjects
+        // wait for the next step of the user
+        if policies_paused_at_o.is_some_and(|steps| steps != execution.events().len()) {
+            *policies_paused_at_o = None;
+        }
+        if *run_policies && policies_paused_at_o.is_none() {
+            execution.run_policies(model, &mut rand::thread_rng());
+        }
+        self.advance_batch(ctx);
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
             egui::menu::bar(ui, |ui| {
                 ui.menu_button("File", |ui| {
@@ -124,37 +612,68 @@ impl eframe::App for DemosimApp {
                         ui.close_menu();
                     }
                     if ui.button("📂 Load model...").clicked() {
-                        let sender = self.model_text_channel.0.clone();
-                        let task = rfd::AsyncFileDialog::new()
-                            .set_title("Load model")
-                            .add_filter("DEMOsim", &["*.dms"])
-                            .pick_file();
-                        let ctx = ui.ctx().clone();
-                        execute(async move {
-                            let file = task.await;
-                            if let Some(file) = file {
-                                let text = file.read().await;
-                                let _ = sender.send(String::from_utf8_lossy(&text).to_string());
-                                ctx.request_repaint();
-                            }
-                        });
+                        self.load_file(ui.ctx(), FileKind::Model);
                         ui.close_menu();
                     }
                     if ui.button("💾 Save model...").clicked() {
-                        let task = rfd::AsyncFileDialog::new()
-                            .set_title("Save model")
-                            .add_filter("DEMOsim", &["*.dms"])
-                            .set_file_name(format!("{}.dms", self.app_context.model.name))
-                            .save_file();
-                        let model_text = ron::ser::to_string_pretty(&self.app_context.model, ron::ser::PrettyConfig::default()).unwrap();
-                        execute(async move {
-                            let file = task.await;
-                            if let Some(file) = file {
-                                _ = file.write(model_text.as_bytes()).await;
-                            }
-                        });
+                        self.save_file(FileKind::Model, |format| persistence::write_model(&self.app_context.model, format));
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    if ui.button("📂 Load session...").on_hover_text("Model together with its execution").clicked() {
+                        self.load_file(ui.ctx(), FileKind::Session);
+                        ui.close_menu();
+                    }
+                    if ui.button("💾 Save session...").on_hover_text("Model together with its execution").clicked() {
+                        let session = Session { model: self.app_context.model.clone(), execution: self.app_context.execution.clone() };
+                        self.save_file(FileKind::Session, |format| persistence::write_session(&session, format));
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    if ui.button("📥 Import CSV...").on_hover_text("Transaction Product Table and links sheets, added to the model").clicked() {
+                        self.import_sheets(ui.ctx());
+                        ui.close_menu();
+                    }
+                    if ui.button("🔍 Check event log...").on_hover_text("Replay an XES or CSV log of real acts against the model and report the deviations").clicked() {
+                        self.load_event_log(ui.ctx());
                         ui.close_menu();
                     }
+                    ui.menu_button("Export", |ui| {
+                        if ui.button("OCD as DOT...").on_hover_text("Organization Construction Diagram for Graphviz").clicked() {
+                            self.export_file("Export OCD", "dot", export::ocd::ocd_to_dot(&self.app_context.model));
+                            ui.close_menu();
+                        }
+                        if ui.button("OCD as SVG...").on_hover_text("Organization Construction Diagram").clicked() {
+                            self.export_file("Export OCD", "svg", export::ocd::ocd_to_svg(&self.app_context.model));
+                            ui.close_menu();
+                        }
+                        ui.separator();
+                        if ui.button("PSD as DOT...").on_hover_text("Process Structure Diagram for Graphviz").clicked() {
+                            self.export_file("Export PSD", "dot", export::psd::psd_to_dot(&self.app_context.model));
+                            ui.close_menu();
+                        }
+                        if ui.button("PSD as SVG...").on_hover_text("Process Structure Diagram").clicked() {
+                            self.export_file("Export PSD", "svg", export::psd::psd_to_svg(&self.app_context.model));
+                            ui.close_menu();
+                        }
+                        if ui.button("PSD as Mermaid...").on_hover_text("Process Structure Diagram as a Mermaid flowchart").clicked() {
+                            self.export_file("Export PSD", "mmd", export::psd::psd_to_mermaid(&self.app_context.model));
+                            ui.close_menu();
+                        }
+                        ui.separator();
+                        if ui.button("Event log as XES...").on_hover_text("C/P world of the execution for process mining").clicked() {
+                            self.export_file("Export event log", "xes", export::event_log::to_xes(&self.app_context.model, &self.app_context.execution));
+                            ui.close_menu();
+                        }
+                        if ui.button("Event log as CSV...").on_hover_text("C/P world of the execution for process mining").clicked() {
+                            self.export_file("Export event log", "csv", export::event_log::to_csv(&self.app_context.model, &self.app_context.execution));
+                            ui.close_menu();
+                        }
+                        ui.separator();
+                        self.sequence_diagram_menu(ui, "Sequence diagram as Mermaid", "mmd", export::sequence::sequence_to_mermaid);
+                        self.sequence_diagram_menu(ui, "Sequence diagram as PlantUML", "puml", export::sequence::sequence_to_plantuml);
+                    });
+                    ui.separator();
                     // NOTE: no File->Quit on web pages!
                     let is_web = cfg!(target_arch = "wasm32");
                     if !is_web {
@@ -181,6 +700,14 @@ impl eframe::App for DemosimApp {
                 if ui.button("Transactions").clicked() {
                     self.egui_windows.transactions = true;
                 }
+                if ui.button("OCD Editor").clicked() {
+                    self.egui_windows.ocd_editor = true;
+                }
+                let problems = self.app_context.diagnostics_cache.get(&self.app_context.model, self.app_context.model_generation).len();
+                let diagnostics_text = if problems == 0 { "Diagnostics".to_string() } else { format!("Diagnostics ({})", problems) };
+                if ui.button(diagnostics_text).clicked() {
+                    self.egui_windows.diagnostics = true;
+                }
                 ui.add_space(10.0);
                 ui.separator();
                 ui.add_space(10.0);
@@ -192,6 +719,9 @@ impl eframe::App for DemosimApp {
                 if ui.button("ADT").clicked() {
                     self.egui_windows.adt = true;
                 }
+                if ui.button("Policies").clicked() {
+                    self.egui_windows.policies = true;
+                }
                 ui.add_space(10.0);
                 ui.separator();
                 ui.add_space(10.0);
@@ -206,12 +736,51 @@ impl eframe::App for DemosimApp {
                 if ui.button("Coord/Prod World").clicked() {
                     self.egui_windows.c_p_world = true;
                 }
+                if ui.button("Live PSD").clicked() {
+                    self.egui_windows.psd_view = true;
+                }
+                if ui.button("Timeline").clicked() {
+                    self.egui_windows.timeline = true;
+                }
+                if ui.button("Clock").clicked() {
+                    self.egui_windows.clock = true;
+                }
+                if ui.button("Batch Simulation").clicked() {
+                    self.egui_windows.batch = true;
+                }
+                if ui.button("Conformance").clicked() {
+                    self.egui_windows.conformance = true;
+                }
+                ui.add_space(10.0);
+                ui.horizontal(|ui| {
+                    let AppContext { model, execution, run_policies, policies_paused_at_o, .. } = &mut self.app_context;
+                    let undo_text = execution.last_event().map(|event| event.describe(model, execution)).unwrap_or_default();
+                    if ui.add_enabled(execution.can_undo(), egui::Button::new("⟲ Undo"))
+                        .on_hover_text(undo_text)
+                        .clicked() {
+                        execution.undo(model);
+                        if *run_policies {
+                            *policies_paused_at_o = Some(execution.events().len());
+                        }
+                    }
+                    let redo_text = execution.next_redo_event().map(|event| event.describe(model, execution)).unwrap_or_default();
+                    if ui.add_enabled(execution.can_redo(), egui::Button::new("⟳ Redo"))
+                        .on_hover_text(redo_text)
+                        .clicked() {
+                        execution.redo(model);
+                        if *run_policies {
+                            *policies_paused_at_o = Some(execution.events().len());
+                        }
+                    }
+                });
             });
 
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.vertical_centered(|ui| {
                 ui.style_mut().override_text_style = Some(egui::TextStyle::Heading);
-                ui.add(egui::TextEdit::singleline(&mut self.app_context.model.name));
+                if ui.add(egui::TextEdit::singleline(&mut self.app_context.model.name)).changed() {
+                    self.app_context.model_changed();
+                }
             });
             // ui.heading(self.model.name.clone());
             ui.with_layout(egui::Layout::bottom_up(egui::Align::LEFT), |ui| {
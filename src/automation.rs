@@ -0,0 +1,151 @@
+//! Automated subjects: their agenda items are handled according to their [`Policy`](crate::model::Policy) and
+//! the subtransactions they are obliged to initiate are requested on their own.
+
+use chrono::Duration;
+use rand::Rng;
+use crate::batch::ActDuration;
+use crate::execution::{Execution, TransactionInstance, TransactionInstanceId};
+use crate::model::{ActorRoleId, CAct, CPAct, Model, Policy, PolicyRule, SubjectId};
+
+// Guards against policies that never settle, e.g. one automated subject always rejecting what another one keeps declaring
+const MAX_AUTOMATED_STEPS: usize = 1000;
+
+enum AutomatedStep {
+    Act(TransactionInstanceId, SubjectId, CPAct),
+    Initiate(TransactionInstance),
+}
+
+impl Execution {
+    /// Lets the automated subjects act until there is nothing more they can do. Returns the number of steps made.
+    /// The steps are regular steps of the execution, so they can be undone one by one.
+    pub fn run_policies<R: Rng>(&mut self, model: &Model, rng: &mut R) -> usize {
+        self.run_automated(model, rng, &[], None)
+    }
+
+    /// Like [`Execution::run_policies`], but each act takes its duration of simulated time and, if a `fallback` policy
+    /// is given, it is followed by subjects that are not automated and for items the policy of an automated subject
+    /// decides nothing for.
+    pub(crate) fn run_automated<R: Rng>(&mut self, model: &Model, rng: &mut R, durations: &[ActDuration], fallback: Option<&Policy>) -> usize {
+        let mut steps = 0;
+        // Decisions about items that have left the agendas are of no use any more
+        let agendas = &self.agendas;
+        self.automated_decisions.retain(|(transaction_instance_id, subject_id, fact), _| {
+            agendas.iter().any(|(subject_id1, agenda_item)| subject_id1 == subject_id && agenda_item.transaction_instance_id == *transaction_instance_id && agenda_item.fact == *fact)
+        });
+        // Items whose step failed are not retried until some other step succeeds
+        let mut blocked: Vec<(TransactionInstanceId, SubjectId)> = Vec::new();
+        while steps < MAX_AUTOMATED_STEPS {
+            let Some(step) = self.next_automated_step(model, rng, &blocked, fallback) else { break };
+            let (transaction_id, act) = match &step {
+                AutomatedStep::Act(transaction_instance_id, _, act) => (self.get_transaction_instance(transaction_instance_id).transaction_id.clone(), act.clone()),
+                AutomatedStep::Initiate(transaction_instance) => (transaction_instance.transaction_id.clone(), CPAct::CAct(CAct::Request)),
+            };
+            // The act takes its time only when it succeeds, a failed attempt does not delay anything
+            let duration = durations.iter().find(|duration| duration.applies_to(&transaction_id, &act))
+                .map_or_else(Duration::zero, |duration| Duration::minutes(duration.sample_minutes(rng)));
+            let res = match step {
+                AutomatedStep::Act(transaction_instance_id, subject_id, act) => {
+                    let res = self.process_new_fact_after(model, transaction_instance_id.clone(), subject_id.clone(), act.to_fact(), duration);
+                    if res.is_err() { blocked.push((transaction_instance_id, subject_id)); }
+                    res
+                }
+                AutomatedStep::Initiate(transaction_instance) => {
+                    let key = (transaction_instance.parent_transaction_instance_id.clone().unwrap_or_else(|| transaction_instance.id.clone()), transaction_instance.initiator_id.clone());
+                    let res = self.add_transaction_instance_after(model, transaction_instance, duration);
+                    if res.is_err() { blocked.push(key); }
+                    res
+                }
+            };
+            if res.is_ok() {
+                steps += 1;
+                blocked.clear();
+            }
+        }
+        steps
+    }
+
+    fn next_automated_step<'a, R: Rng>(&mut self, model: &'a Model, rng: &mut R, blocked: &[(TransactionInstanceId, SubjectId)], fallback: Option<&'a Policy>) -> Option<AutomatedStep> {
+        // Subjects in the order of the model, so that seeded runs are reproducible
+        for subject in &model.subjects {
+            let Some(policy) = model.policies.get(&subject.id).filter(|policy| policy.automated).or(fallback) else { continue };
+            for agenda_item in self.agenda_for(&subject.id) {
+                if blocked.contains(&(agenda_item.transaction_instance_id.clone(), subject.id.clone())) {
+                    continue;
+                }
+                let Some(transaction_instance) = self.find_transaction_instance(&agenda_item.transaction_instance_id) else { continue };
+                // Subtransactions first: acts waiting for them would be impeded otherwise
+                if let Some(initiation) = self.required_subtransaction(model, transaction_instance, &subject.id) {
+                    return Some(initiation);
+                }
+                let Some(transaction) = model.find_transaction(&transaction_instance.transaction_id) else { continue };
+                let transaction_instance = transaction_instance.clone();
+                let available_acts = self.available_acts(&transaction_instance, &subject.id);
+                let key = (transaction_instance.id.clone(), subject.id.clone(), agenda_item.fact.clone());
+                let decided = match self.automated_decisions.get(&key) {
+                    Some(decided) => decided.clone(),
+                    None => {
+                        // Without a decision from its own rules, the subject follows the fallback policy
+                        let rules_of = |policy: &'a Policy| -> Vec<&'a PolicyRule> {
+                            policy.get_rules_for(&transaction_instance.transaction_id, &agenda_item.fact).into_iter()
+                                .filter(|rule| available_acts.contains(&rule.act))
+                                .collect()
+                        };
+                        let decided = decide(&rules_of(policy), rng)
+                            .or_else(|| fallback.and_then(|fallback| decide(&rules_of(fallback), rng)));
+                        self.automated_decisions.insert(key, decided.clone());
+                        decided
+                    }
+                };
+                // A decided act held back by a wait link is performed once the wait is over
+                let Some(act) = decided else { continue };
+                if self.get_act_impediments(model, transaction, &self.impediments_context(&transaction_instance), &act).is_some() {
+                    continue;
+                }
+                return Some(AutomatedStep::Act(transaction_instance.id.clone(), subject.id.clone(), act));
+            }
+        }
+        None
+    }
+
+    // A subtransaction the subject has to initiate within the instance to satisfy the minimal multiplicity of an initiation
+    fn required_subtransaction(&self, model: &Model, parent: &TransactionInstance, subject_id: &SubjectId) -> Option<AutomatedStep> {
+        let parent_transaction = model.find_transaction(&parent.transaction_id)?;
+        let startable = self.startable_subtransactions(model, parent, subject_id).ok()?;
+        parent_transaction.initiations.iter()
+            .filter(|initiation| initiation.initiated_c_act == CAct::Request)
+            .filter_map(|initiation| startable.iter().find(|t| t.id == initiation.initiated_transaction_id).map(|t| (initiation, *t)))
+            .find(|(initiation, transaction)| {
+                self.get_instances_of_transaction(&transaction.id, &Some(parent.id.clone()))
+                    .is_ok_and(|instances| instances.len() < initiation.multiplicity.min as usize)
+            })
+            .and_then(|(_, transaction)| {
+                let executor_id = first_authorised_subject(model, &transaction.executor_id)?;
+                let product_instance = format!("{} / {}", parent.product_instance, transaction.product);
+                Some(AutomatedStep::Initiate(TransactionInstance::new(Some(parent.id.clone()), transaction.id.clone(), product_instance, subject_id.clone(), executor_id)))
+            })
+    }
+
+}
+
+// The act decided by the rules for an agenda item: a single number is drawn and the rules take their shares of the
+// hundred in order, none being decided when the number falls beyond them
+fn decide<R: Rng>(rules: &[&PolicyRule], rng: &mut R) -> Option<CPAct> {
+    if rules.is_empty() {
+        return None;
+    }
+    let drawn = rng.gen_range(0..100u32);
+    let mut share_end = 0u32;
+    rules.iter()
+        .find(|rule| {
+            share_end += rule.probability_percent as u32;
+            drawn < share_end
+        })
+        .map(|rule| rule.act.clone())
+}
+
+// Whom an automated subject addresses when there is a choice: the first authorised one, for reproducibility
+pub(crate) fn first_authorised_subject(model: &Model, actor_role_id: &ActorRoleId) -> Option<SubjectId> {
+    model.subjects.iter()
+        .find(|s| model.adt.is_authorised(actor_role_id, &s.id))
+        .map(|s| s.id.clone())
+}
@@ -0,0 +1,294 @@
+//! Batch (Monte Carlo) simulation: a model is executed many times with all subjects acting on their own, their
+//! decisions drawn according to their policies and the acts taking randomly long, and the outcomes are summarised.
+//! Runs are seeded, so the same configuration always gives the same report.
+
+use chrono::{DateTime, Utc};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use crate::automation::first_authorised_subject;
+use crate::execution::{CPWorldItem, Execution, ExecutionError, TransactionInstance};
+use crate::model::{CAct, CFact, CPAct, CPFact, Model, Policy, SubjectId, TransactionId};
+
+/// How long performing an act takes, in minutes, drawn uniformly from `min_minutes..=max_minutes`.
+#[derive(serde::Deserialize, serde::Serialize, PartialEq, Eq, Debug, Clone)]
+pub struct ActDuration {
+    pub transaction_id: Option<TransactionId>, // None for any transaction
+    pub act: CPAct,
+    pub min_minutes: i64,
+    pub max_minutes: i64,
+}
+
+impl Default for ActDuration {
+    fn default() -> Self {
+        Self {
+            transaction_id: None,
+            act: CPAct::PAct,
+            min_minutes: 60,
+            max_minutes: 60,
+        }
+    }
+}
+
+impl ActDuration {
+    pub fn applies_to(&self, transaction_id: &TransactionId, act: &CPAct) -> bool {
+        self.act == *act && (self.transaction_id.is_none() || self.transaction_id.as_ref() == Some(transaction_id))
+    }
+
+    pub fn sample_minutes<R: Rng>(&self, rng: &mut R) -> i64 {
+        if self.max_minutes <= self.min_minutes {
+            self.min_minutes.max(0)
+        } else {
+            rng.gen_range(self.min_minutes..=self.max_minutes).max(0)
+        }
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, PartialEq, Eq, Debug, Clone)]
+#[serde(default)]
+pub struct BatchConfig {
+    pub runs: usize,
+    pub seed: u64,
+    pub initiator_id: Option<SubjectId>, // the first authorised subject if None
+    pub executor_id: Option<SubjectId>,  // the first authorised subject if None
+    pub product_instance: String,
+    /// The first matching duration applies; acts without one take no time.
+    pub durations: Vec<ActDuration>,
+}
+
+impl Default for BatchConfig {
+    fn default() -> Self {
+        Self {
+            runs: 100,
+            seed: 0,
+            initiator_id: None,
+            executor_id: None,
+            product_instance: "batch".to_string(),
+            durations: Vec::new(),
+        }
+    }
+}
+
+/// Descriptive statistics of a sample.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Summary {
+    pub count: usize,
+    pub mean: f64,
+    pub std_dev: f64,
+    pub min: f64,
+    pub median: f64,
+    pub p90: f64,
+    pub max: f64,
+}
+
+impl Summary {
+    /// None for an empty sample.
+    pub fn from_samples(samples: &[f64]) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        let mut sorted = samples.to_vec();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        let count = sorted.len();
+        let mean = sorted.iter().sum::<f64>() / count as f64;
+        let variance = sorted.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / count as f64;
+        // Nearest-rank percentile
+        let percentile = |p: f64| sorted[((p * count as f64).ceil() as usize).clamp(1, count) - 1];
+        Some(Self {
+            count,
+            mean,
+            std_dev: variance.sqrt(),
+            min: sorted[0],
+            median: percentile(0.5),
+            p90: percentile(0.9),
+            max: sorted[count - 1],
+        })
+    }
+}
+
+/// Outcomes of the instances of one transaction over all runs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransactionStatistics {
+    pub transaction_id: TransactionId,
+    pub instances: usize,
+    pub accepted: usize,
+    pub declined: usize, // instances declined at least once
+    pub rejected: usize, // instances rejected at least once
+    pub instances_per_run: Option<Summary>,
+    pub completion_minutes: Option<Summary>, // from the request to the acceptance, of the accepted instances
+}
+
+impl TransactionStatistics {
+    fn rate(&self, count: usize) -> f64 {
+        if self.instances == 0 { 0.0 } else { count as f64 / self.instances as f64 }
+    }
+
+    pub fn decline_rate(&self) -> f64 {
+        self.rate(self.declined)
+    }
+
+    pub fn reject_rate(&self) -> f64 {
+        self.rate(self.rejected)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchReport {
+    pub runs: usize,
+    pub completed_runs: usize, // runs in which the started transaction was accepted
+    pub steps: usize,
+    pub completion_minutes: Option<Summary>, // of the started transaction in the completed runs
+    pub transactions: Vec<TransactionStatistics>, // in the order of the model
+}
+
+impl BatchReport {
+    pub fn statistics_of(&self, transaction_id: &TransactionId) -> Option<&TransactionStatistics> {
+        self.transactions.iter().find(|statistics| statistics.transaction_id == *transaction_id)
+    }
+}
+
+#[derive(Default)]
+struct InstanceOutcome {
+    accepted: bool,
+    declined: bool,
+    rejected: bool,
+    completion_minutes: Option<f64>,
+}
+
+fn instance_outcome(facts: &[&CPWorldItem]) -> InstanceOutcome {
+    let mut outcome = InstanceOutcome::default();
+    let requested_at: Option<&DateTime<Utc>> = facts.first().map(|item| item.get_timestamp());
+    for item in facts {
+        match item.to_fact() {
+            CPFact::CFact(CFact::Declined) => outcome.declined = true,
+            CPFact::CFact(CFact::Rejected) => outcome.rejected = true,
+            _ => {}
+        }
+    }
+    if let Some(last) = facts.last().filter(|item| item.to_fact() == CPFact::CFact(CFact::Accepted)) {
+        outcome.accepted = true;
+        outcome.completion_minutes = requested_at.map(|requested_at| (*last.get_timestamp() - *requested_at).num_seconds() as f64 / 60.0);
+    }
+    outcome
+}
+
+/// A batch in progress. The runs are performed a few at a time by [`BatchRun::advance`], so that a long batch can be
+/// spread e.g. over the frames of the GUI; [`run_batch`] performs them all at once.
+pub struct BatchRun {
+    model: Model, // as it was when the batch started
+    transaction_id: TransactionId,
+    config: BatchConfig,
+    initiator_id: SubjectId,
+    executor_id: SubjectId,
+    fallback: Policy,
+    rng: StdRng,
+    done_runs: usize,
+    report: BatchReport,
+    root_completions: Vec<f64>,
+    outcomes: Vec<Vec<InstanceOutcome>>,
+    instances_per_run: Vec<Vec<f64>>,
+}
+
+impl BatchRun {
+    /// Prepares the batch, starting an instance of the transaction in each run. Subjects that are not automated, and
+    /// automated ones whose policy decides nothing for an item, behave cooperatively. Fails when the transaction
+    /// cannot be started.
+    pub fn new(model: &Model, transaction_id: &TransactionId, config: &BatchConfig) -> Result<Self, ExecutionError> {
+        let transaction = model.find_transaction(transaction_id)
+            .ok_or_else(|| ExecutionError::UnknownTransaction(transaction_id.clone()))?;
+        let initiator_id = config.initiator_id.clone().or_else(|| first_authorised_subject(model, &transaction.initiator_id))
+            .ok_or_else(|| ExecutionError::NoAuthorisedSubject(transaction.initiator_id.clone()))?;
+        let executor_id = config.executor_id.clone().or_else(|| first_authorised_subject(model, &transaction.executor_id))
+            .ok_or_else(|| ExecutionError::NoAuthorisedSubject(transaction.executor_id.clone()))?;
+        Ok(Self {
+            model: model.clone(),
+            transaction_id: transaction_id.clone(),
+            config: config.clone(),
+            initiator_id,
+            executor_id,
+            fallback: Policy::cooperative(),
+            rng: StdRng::seed_from_u64(config.seed),
+            done_runs: 0,
+            report: BatchReport {
+                runs: config.runs,
+                completed_runs: 0,
+                steps: 0,
+                completion_minutes: None,
+                transactions: Vec::new(),
+            },
+            root_completions: Vec::new(),
+            outcomes: model.transactions.iter().map(|_| Vec::new()).collect(),
+            instances_per_run: model.transactions.iter().map(|_| Vec::new()).collect(),
+        })
+    }
+
+    pub fn runs(&self) -> usize {
+        self.config.runs
+    }
+
+    pub fn done_runs(&self) -> usize {
+        self.done_runs
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.done_runs >= self.config.runs
+    }
+
+    /// Performs up to the given number of the remaining runs.
+    pub fn advance(&mut self, runs: usize) -> Result<(), ExecutionError> {
+        let model = &self.model;
+        for _ in 0..runs.min(self.config.runs - self.done_runs) {
+            let mut execution = Execution::default();
+            let root = TransactionInstance::new(None, self.transaction_id.clone(), self.config.product_instance.clone(), self.initiator_id.clone(), self.executor_id.clone());
+            let root_id = root.id.clone();
+            let duration = self.config.durations.iter().find(|duration| duration.applies_to(&self.transaction_id, &CPAct::CAct(CAct::Request)))
+                .map_or_else(chrono::Duration::zero, |duration| chrono::Duration::minutes(duration.sample_minutes(&mut self.rng)));
+            execution.add_transaction_instance_after(model, root, duration)?;
+            self.report.steps += 1 + execution.run_automated(model, &mut self.rng, &self.config.durations, Some(&self.fallback));
+
+            for (t_index, transaction) in model.transactions.iter().enumerate() {
+                let mut count = 0;
+                for transaction_instance in execution.transactions_instances.iter().filter(|t_i| t_i.transaction_id == transaction.id) {
+                    let outcome = instance_outcome(&execution.get_facts_for_transaction_instance(&transaction_instance.id));
+                    if transaction_instance.id == root_id && outcome.accepted {
+                        self.report.completed_runs += 1;
+                        self.root_completions.extend(outcome.completion_minutes);
+                    }
+                    self.outcomes[t_index].push(outcome);
+                    count += 1;
+                }
+                self.instances_per_run[t_index].push(count as f64);
+            }
+            self.done_runs += 1;
+        }
+        Ok(())
+    }
+
+    /// The report of the runs performed so far.
+    pub fn report(&self) -> BatchReport {
+        let mut report = self.report.clone();
+        report.runs = self.done_runs;
+        report.completion_minutes = Summary::from_samples(&self.root_completions);
+        report.transactions = self.model.transactions.iter().zip(&self.outcomes).zip(&self.instances_per_run)
+            .map(|((transaction, outcomes), instances_per_run)| {
+                let completions: Vec<f64> = outcomes.iter().filter_map(|outcome| outcome.completion_minutes).collect();
+                TransactionStatistics {
+                    transaction_id: transaction.id.clone(),
+                    instances: outcomes.len(),
+                    accepted: outcomes.iter().filter(|outcome| outcome.accepted).count(),
+                    declined: outcomes.iter().filter(|outcome| outcome.declined).count(),
+                    rejected: outcomes.iter().filter(|outcome| outcome.rejected).count(),
+                    instances_per_run: Summary::from_samples(instances_per_run),
+                    completion_minutes: Summary::from_samples(&completions),
+                }
+            })
+            .collect();
+        report
+    }
+}
+
+/// Runs the whole batch, see [`BatchRun::new`].
+pub fn run_batch(model: &Model, transaction_id: &TransactionId, config: &BatchConfig) -> Result<BatchReport, ExecutionError> {
+    let mut run = BatchRun::new(model, transaction_id, config)?;
+    run.advance(config.runs)?;
+    Ok(run.report())
+}
@@ -0,0 +1,75 @@
+use chrono::{DateTime, Duration, Utc};
+
+#[derive(serde::Deserialize, serde::Serialize, schemars::JsonSchema, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClockMode {
+    Simulated, // facts are stamped with the simulated time, which moves only when told to
+    RealTime,  // facts are stamped with the wall-clock time, e.g. for live classroom sessions
+}
+
+impl std::fmt::Display for ClockMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use ClockMode::*;
+        match self {
+            Simulated => write!(f, "Simulated"),
+            RealTime => write!(f, "Real time"),
+        }
+    }
+}
+
+/// The time of an execution. In the simulated mode, the time starts at `start` and moves forward either manually
+/// or automatically by `auto_advance_minutes` with every step of the execution.
+#[derive(serde::Deserialize, serde::Serialize, schemars::JsonSchema, Debug, Clone)]
+#[serde(default)]
+pub struct SimulationClock {
+    pub mode: ClockMode,
+    pub start: DateTime<Utc>,
+    pub auto_advance_minutes: i64,
+    now: DateTime<Utc>,
+}
+
+impl Default for SimulationClock {
+    fn default() -> Self {
+        let start = Utc::now();
+        Self {
+            mode: ClockMode::Simulated,
+            start,
+            auto_advance_minutes: 0,
+            now: start,
+        }
+    }
+}
+
+impl SimulationClock {
+    pub fn now(&self) -> DateTime<Utc> {
+        match self.mode {
+            ClockMode::Simulated => self.now,
+            ClockMode::RealTime => Utc::now(),
+        }
+    }
+
+    /// The timestamp for the next step of the execution.
+    pub fn next_step_time(&self) -> DateTime<Utc> {
+        match self.mode {
+            ClockMode::Simulated => self.now + Duration::minutes(self.auto_advance_minutes.max(0)),
+            ClockMode::RealTime => Utc::now(),
+        }
+    }
+
+    /// Moves the simulated time to the timestamp of a step that has happened (the time never goes back).
+    pub fn observe(&mut self, timestamp: &DateTime<Utc>) {
+        if *timestamp > self.now {
+            self.now = *timestamp;
+        }
+    }
+
+    /// Rewinds the simulated time to the start, before the steps are replayed.
+    pub fn reset(&mut self) {
+        self.now = self.start;
+    }
+
+    /// Sets the start of the simulated time; only meaningful before anything has happened.
+    pub fn set_start(&mut self, start: DateTime<Utc>) {
+        self.start = start;
+        self.now = start;
+    }
+}
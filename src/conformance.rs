@@ -0,0 +1,455 @@
+//! Conformance checking of event logs of real coordination and production acts (XES or CSV, e.g. as exported by
+//! [`crate::export::event_log`]) against the model. The log is replayed by the execution engine and every event
+//! that the model does not allow is reported as a deviation. Deviating events are replayed anyway, so that the rest
+//! of the log is checked against what really happened. Deadlines do not expire during the replay: what was done
+//! about them is in the log.
+//!
+//! Events are matched to the model by names: the transaction by its id (taken from the activity, e.g.
+//! `T01 Promised`, unless the log has a `transaction` attribute), the performer and the addressee as subjects.
+//! Transaction instances are identified by the `transactionInstance` attribute if present, otherwise a case is
+//! taken to have one instance of each transaction, a subtransaction of the instance initiating it.
+
+use std::collections::{HashMap, HashSet};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+use crate::automation::first_authorised_subject;
+use crate::execution::{CPWorldItem, Execution, ExecutionError, ExecutionEvent, FactOrigin, TransactionInstance, TransactionInstanceId};
+use crate::import::{cell, parse_csv, parse_fact, Header};
+use crate::model::{CFact, CPFact, MaxMultiplicity, Model, Subject, SubjectId, Transaction};
+use crate::persistence::LoadError;
+
+/// An event of a log, as read from the file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogEvent {
+    pub case_id: String,
+    pub activity: String,
+    pub timestamp: DateTime<Utc>,
+    pub performer: String,
+    pub addressee: Option<String>,
+    pub transaction: Option<String>, // taken from the activity if missing
+    pub fact: Option<String>,        // taken from the activity if missing
+    pub transaction_instance_id: Option<String>,
+    pub parent_transaction_instance_id: Option<String>,
+    pub product_instance: Option<String>,
+}
+
+impl LogEvent {
+    // The id of the transaction and the fact, e.g. `T01` and `Promised` for the activity `T01 Promised`
+    fn transaction_and_fact(&self) -> (String, String) {
+        let (activity_t_id, activity_fact) = self.activity.trim().split_once(' ').unwrap_or((self.activity.trim(), ""));
+        (
+            self.transaction.clone().unwrap_or_else(|| activity_t_id.to_string()),
+            self.fact.clone().unwrap_or_else(|| activity_fact.trim().to_string()),
+        )
+    }
+}
+
+fn parse_timestamp(s: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(s.trim()).map(|timestamp| timestamp.with_timezone(&Utc)).ok()
+        .or_else(|| NaiveDateTime::parse_from_str(s.trim(), "%Y-%m-%d %H:%M:%S%.f").ok().map(|timestamp| timestamp.and_utc()))
+}
+
+// Accepted names of the columns, compared without case, spaces and punctuation; pm4py names included
+const CASE_COLUMN: &[&str] = &["caseid", "case", "caseconceptname"];
+const ACTIVITY_COLUMN: &[&str] = &["activity", "conceptname", "event"];
+const TIMESTAMP_COLUMN: &[&str] = &["timestamp", "timetimestamp", "time"];
+const PERFORMER_COLUMN: &[&str] = &["performer", "orgresource", "resource"];
+const ADDRESSEE_COLUMN: &[&str] = &["addressee"];
+const TRANSACTION_COLUMN: &[&str] = &["transaction"];
+const FACT_COLUMN: &[&str] = &["fact"];
+const TRANSACTION_INSTANCE_COLUMN: &[&str] = &["transactioninstanceid", "transactioninstance"];
+const PARENT_COLUMN: &[&str] = &["parenttransactioninstanceid", "parenttransactioninstance"];
+const PRODUCT_INSTANCE_COLUMN: &[&str] = &["productinstance"];
+
+/// Reads a CSV log with a header row. The case, activity, timestamp and performer columns are required.
+pub fn read_csv_log(text: &str) -> Result<Vec<LogEvent>, LoadError> {
+    let rows = parse_csv(text);
+    let Some((_, header)) = rows.first() else { return Ok(Vec::new()) };
+    let header = Header::new(header);
+    let [case_i, activity_i, timestamp_i, performer_i] = header.columns([CASE_COLUMN, ACTIVITY_COLUMN, TIMESTAMP_COLUMN, PERFORMER_COLUMN])
+        .map_err(|error| LoadError { message: error.message, position: Some((1, 1)) })?;
+    let optional = |row: &[String], names: &[&str]| header.column(names).map(|index| cell(row, index).to_string()).filter(|value| !value.is_empty());
+    let mut events = Vec::new();
+    for (line, row) in rows.iter().skip(1) {
+        let timestamp = parse_timestamp(cell(row, timestamp_i))
+            .ok_or_else(|| LoadError { message: format!("Invalid timestamp \"{}\"", cell(row, timestamp_i)), position: Some((*line, 1)) })?;
+        events.push(LogEvent {
+            case_id: cell(row, case_i).to_string(),
+            activity: cell(row, activity_i).to_string(),
+            timestamp,
+            performer: cell(row, performer_i).to_string(),
+            addressee: optional(row, ADDRESSEE_COLUMN),
+            transaction: optional(row, TRANSACTION_COLUMN),
+            fact: optional(row, FACT_COLUMN),
+            transaction_instance_id: optional(row, TRANSACTION_INSTANCE_COLUMN),
+            parent_transaction_instance_id: optional(row, PARENT_COLUMN),
+            product_instance: optional(row, PRODUCT_INSTANCE_COLUMN),
+        });
+    }
+    Ok(events)
+}
+
+/// Reads an XES log. Events need the `concept:name`, `time:timestamp` and `org:resource` attributes; events with a
+/// `lifecycle:transition` other than `complete` are left out.
+pub fn read_xes_log(text: &str) -> Result<Vec<LogEvent>, LoadError> {
+    let mut reader = Reader::from_str(text);
+    reader.config_mut().trim_text(true);
+    let error_at = |position: u64, message: String| {
+        // Positions are where the reader stopped, before the whitespace preceding the element
+        let position = (position as usize).min(text.len());
+        let position = position + (text.len() - position - text[position..].trim_start().len());
+        let before = &text[..position];
+        let line = before.matches('\n').count() + 1;
+        let column = before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+        LoadError { message, position: Some((line, column)) }
+    };
+
+    let mut events = Vec::new();
+    let mut case_id_o: Option<String> = None;
+    let mut attributes_o: Option<HashMap<String, String>> = None; // of the event being read
+    let mut nesting = 0_usize; // in a trace, of attributes with nested attributes, which are not used
+    let mut event_start = 0;
+    loop {
+        let position = reader.buffer_position();
+        let event = reader.read_event().map_err(|e| error_at(reader.error_position(), e.to_string()))?;
+        let (element, is_empty) = match &event {
+            Event::Start(element) => (Some(element), false),
+            Event::Empty(element) => (Some(element), true),
+            Event::End(element) => {
+                match element.name().as_ref() {
+                    b"event" => {
+                        let attributes = attributes_o.take().unwrap_or_default();
+                        if let Some(event) = xes_event(case_id_o.clone().unwrap_or_default(), attributes).map_err(|message| error_at(event_start, message))? {
+                            events.push(event);
+                        }
+                    }
+                    b"trace" => case_id_o = None,
+                    _ => nesting = nesting.saturating_sub(1),
+                }
+                continue;
+            }
+            Event::Eof => break,
+            _ => continue,
+        };
+        let Some(element) = element else { continue };
+        match element.name().as_ref() {
+            b"trace" => case_id_o = Some(String::new()),
+            b"event" => {
+                attributes_o = Some(HashMap::new());
+                event_start = position;
+            }
+            _ => {
+                let attribute = |name: &str| -> Result<Option<String>, LoadError> {
+                    xml_attribute(element, name).map_err(|e| error_at(position, e.to_string()))
+                };
+                if nesting == 0 {
+                    if let (Some(key), Some(value)) = (attribute("key")?, attribute("value")?) {
+                        match (&mut attributes_o, &mut case_id_o) {
+                            (Some(attributes), _) => { attributes.insert(key, value); }
+                            (None, Some(case_id)) if key == "concept:name" => *case_id = value,
+                            _ => {}
+                        }
+                    }
+                }
+                if !is_empty && case_id_o.is_some() {
+                    nesting += 1;
+                }
+            }
+        }
+    }
+    Ok(events)
+}
+
+fn xml_attribute(element: &BytesStart<'_>, name: &str) -> Result<Option<String>, quick_xml::Error> {
+    match element.try_get_attribute(name)? {
+        Some(attribute) => Ok(Some(attribute.unescape_value()?.to_string())),
+        None => Ok(None),
+    }
+}
+
+fn xes_event(case_id: String, mut attributes: HashMap<String, String>) -> Result<Option<LogEvent>, String> {
+    if attributes.get("lifecycle:transition").is_some_and(|transition| !transition.eq_ignore_ascii_case("complete")) {
+        return Ok(None);
+    }
+    let mut required = |key: &str| attributes.remove(key).ok_or_else(|| format!("Event without {}", key));
+    let activity = required("concept:name")?;
+    let timestamp_text = required("time:timestamp")?;
+    let performer = required("org:resource")?;
+    let timestamp = parse_timestamp(&timestamp_text).ok_or_else(|| format!("Invalid timestamp \"{}\"", timestamp_text))?;
+    Ok(Some(LogEvent {
+        case_id,
+        activity,
+        timestamp,
+        performer,
+        addressee: attributes.remove("addressee"),
+        transaction: attributes.remove("transaction"),
+        fact: attributes.remove("fact"),
+        transaction_instance_id: attributes.remove("transactionInstance"),
+        parent_transaction_instance_id: attributes.remove("parentTransactionInstance"),
+        product_instance: attributes.remove("productInstance"),
+    }))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EnumIter)]
+pub enum DeviationKind {
+    NotInModel,           // unknown transaction, fact or subject, or an initiation the model does not have
+    OutOfOrder,           // an act not following the transaction pattern or the initiating fact
+    Unauthorised,         // a performer not playing the role or not authorised for it in the ADT
+    WaitLinkViolated,
+    MultiplicityExceeded, // more subtransactions than the maximal multiplicity of the initiation
+}
+
+impl std::fmt::Display for DeviationKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use DeviationKind::*;
+        match self {
+            NotInModel => write!(f, "Not in model"),
+            OutOfOrder => write!(f, "Out of order"),
+            Unauthorised => write!(f, "Unauthorised"),
+            WaitLinkViolated => write!(f, "Wait link violated"),
+            MultiplicityExceeded => write!(f, "Multiplicity exceeded"),
+        }
+    }
+}
+
+pub fn all_deviation_kinds() -> Vec<DeviationKind> {
+    DeviationKind::iter().collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Deviation {
+    pub event_index: usize, // 1-based, in the order of the log
+    pub case_id: String,
+    pub activity: String,
+    pub timestamp: DateTime<Utc>,
+    pub kind: DeviationKind,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConformanceReport {
+    pub events: usize,
+    pub deviations: Vec<Deviation>, // in the order of the replay, i.e. by time
+    pub unchecked: usize, // events of transaction instances whose request could not be replayed
+}
+
+impl ConformanceReport {
+    pub fn deviating_events(&self) -> usize {
+        self.deviations.iter().map(|deviation| deviation.event_index).collect::<HashSet<_>>().len()
+    }
+
+    /// The share of the events that conform to the model.
+    pub fn fitness(&self) -> f64 {
+        if self.events == 0 { 1.0 } else { (self.events - self.deviating_events() - self.unchecked) as f64 / self.events as f64 }
+    }
+
+    pub fn count_of(&self, kind: DeviationKind) -> usize {
+        self.deviations.iter().filter(|deviation| deviation.kind == kind).count()
+    }
+}
+
+struct Replay<'a> {
+    model: &'a Model,
+    execution: Execution,
+    instances: Vec<(String, String, TransactionInstanceId)>, // case, key in the log, id in the execution
+    broken_instances: HashSet<String>,
+    report: ConformanceReport,
+}
+
+/// Replays the log against the model and reports the deviations.
+pub fn check_conformance(model: &Model, log: &[LogEvent]) -> ConformanceReport {
+    let mut events: Vec<(usize, &LogEvent)> = log.iter().enumerate().map(|(index, event)| (index + 1, event)).collect();
+    events.sort_by_key(|(_, event)| event.timestamp);
+    let mut replay = Replay {
+        model,
+        execution: Execution::default(),
+        instances: Vec::new(),
+        broken_instances: HashSet::new(),
+        report: ConformanceReport { events: log.len(), ..Default::default() },
+    };
+    if let Some((_, first)) = events.first() {
+        replay.execution.clock.set_start(first.timestamp);
+    }
+    for (event_index, event) in events {
+        replay.replay(event_index, event);
+    }
+    replay.report
+}
+
+impl Replay<'_> {
+    fn deviate(&mut self, event_index: usize, event: &LogEvent, kind: DeviationKind, message: String) {
+        self.report.deviations.push(Deviation {
+            event_index,
+            case_id: event.case_id.clone(),
+            activity: event.activity.clone(),
+            timestamp: event.timestamp,
+            kind,
+            message,
+        });
+    }
+
+    fn find_subject(&self, name: &str) -> Option<&'_ Subject> {
+        self.model.subjects.iter().find(|s| s.name.trim().eq_ignore_ascii_case(name.trim()))
+    }
+
+    fn replay(&mut self, event_index: usize, event: &LogEvent) {
+        let model = self.model;
+        let (t_id, fact_text) = event.transaction_and_fact();
+        let Some(transaction) = model.transactions.iter().find(|t| t.t_id.eq_ignore_ascii_case(&t_id)) else {
+            return self.deviate(event_index, event, DeviationKind::NotInModel, format!("Unknown transaction \"{}\"", t_id));
+        };
+        let Some(fact) = parse_fact(&fact_text) else {
+            return self.deviate(event_index, event, DeviationKind::NotInModel, format!("Unknown fact \"{}\"", fact_text));
+        };
+        let Some(performer) = self.find_subject(&event.performer).cloned() else {
+            return self.deviate(event_index, event, DeviationKind::NotInModel, format!("Unknown subject \"{}\"", event.performer));
+        };
+        let key = event.transaction_instance_id.clone().unwrap_or_else(|| format!("{}/{}", event.case_id, transaction.t_id));
+        if self.broken_instances.contains(&key) {
+            self.report.unchecked += 1;
+            return;
+        }
+        let instance_o = self.instances.iter().find(|(_, k, _)| *k == key).map(|(_, _, id)| id.clone());
+        let res = match instance_o {
+            Some(transaction_instance_id) => {
+                if self.is_generated(&transaction_instance_id, &fact) {
+                    return;
+                }
+                self.replay_fact(event.timestamp, transaction_instance_id, performer.id.clone(), fact)
+            }
+            None => {
+                if fact != CPFact::CFact(CFact::Requested) {
+                    self.broken_instances.insert(key.clone());
+                    return self.deviate(event_index, event, DeviationKind::OutOfOrder, format!("The transaction instance {} does not start with a request, its further events are not checked", key));
+                }
+                let executor_id_o = match &event.addressee {
+                    Some(addressee) => self.find_subject(addressee).map(|s| s.id.clone()),
+                    None => first_authorised_subject(model, &transaction.executor_id),
+                };
+                let Some(executor_id) = executor_id_o else {
+                    self.broken_instances.insert(key);
+                    return self.deviate(event_index, event, DeviationKind::NotInModel, format!("Unknown addressee of the request {}", event.addressee.clone().unwrap_or_default()));
+                };
+                let parent_id_o = self.parent_of(event, transaction);
+                let product_instance = event.product_instance.clone().unwrap_or_else(|| event.case_id.clone());
+                let transaction_instance = TransactionInstance::new(parent_id_o, transaction.id.clone(), product_instance, performer.id.clone(), executor_id);
+                let transaction_instance_id = transaction_instance.id.clone();
+                // Counted before the instance is there
+                let deviation_o = self.initiation_deviation(&transaction_instance, &performer);
+                let res = self.replay_transaction_instance(event.timestamp, transaction_instance);
+                self.instances.push((event.case_id.clone(), key.clone(), transaction_instance_id.clone()));
+                if self.execution.find_transaction_instance(&transaction_instance_id).is_none() {
+                    self.broken_instances.insert(key);
+                }
+                match (res, deviation_o) {
+                    (Err(ExecutionError::NotStartable { .. }), Some((kind, message))) => return self.deviate(event_index, event, kind, message),
+                    (res, _) => res,
+                }
+            }
+        };
+        if let Err(error) = res {
+            let (kind, message) = self.describe(&error);
+            self.deviate(event_index, event, kind, message);
+        }
+    }
+
+    // Creates the instance as the log says. A request the model does not allow is forced in, so that the rest of the
+    // log is checked against what really happened; the error is returned in both cases. The replayed execution is
+    // not to be undone, so nothing of it goes to its event log.
+    fn replay_transaction_instance(&mut self, timestamp: DateTime<Utc>, transaction_instance: TransactionInstance) -> Result<(), ExecutionError> {
+        let event = ExecutionEvent::TransactionInstanceCreated { timestamp, transaction_instance: transaction_instance.clone() };
+        let res = self.execution.replay_event(self.model, &event);
+        if res.is_err() {
+            let transaction_instance_id = transaction_instance.id.clone();
+            let initiator_id = transaction_instance.initiator_id.clone();
+            self.execution.transactions_instances.push(transaction_instance);
+            self.force_fact(timestamp, transaction_instance_id, initiator_id, CPFact::CFact(CFact::Requested));
+        }
+        res
+    }
+
+    // Creates the fact as the log says, forced in like a request, see above
+    fn replay_fact(&mut self, timestamp: DateTime<Utc>, transaction_instance_id: TransactionInstanceId, performer_id: SubjectId, fact: CPFact) -> Result<(), ExecutionError> {
+        let event = ExecutionEvent::FactCreated { timestamp, transaction_instance_id: transaction_instance_id.clone(), performer_id: performer_id.clone(), fact: fact.clone() };
+        let res = self.execution.replay_event(self.model, &event);
+        if res.is_err() {
+            self.force_fact(timestamp, transaction_instance_id, performer_id, fact);
+        }
+        res
+    }
+
+    // Nothing is forced in when the subjects are unknown, as with a request addressed to nobody in the model
+    fn force_fact(&mut self, timestamp: DateTime<Utc>, transaction_instance_id: TransactionInstanceId, performer_id: SubjectId, fact: CPFact) {
+        self.execution.clock.observe(&timestamp);
+        if self.execution.record_fact(self.model, &timestamp, transaction_instance_id.clone(), performer_id, fact, FactOrigin::Explicit).is_ok() {
+            self.execution.perform_tacit_acts(self.model, &timestamp, &transaction_instance_id);
+        }
+    }
+
+    // Whether the fact is the current state of the instance, generated by the engine as a tacit act, so the event
+    // just records it
+    fn is_generated(&self, transaction_instance_id: &TransactionInstanceId, fact: &CPFact) -> bool {
+        self.execution.get_state(transaction_instance_id).as_ref() == Some(fact)
+            && matches!(self.execution.get_facts_for_transaction_instance(transaction_instance_id).last(), Some(CPWorldItem::CWorldItem(c)) if c.tacit)
+    }
+
+    // The explicitly given parent, or the last instance in the case of a transaction initiating the transaction
+    fn parent_of(&self, event: &LogEvent, transaction: &Transaction) -> Option<TransactionInstanceId> {
+        if let Some(parent_key) = &event.parent_transaction_instance_id {
+            return self.instances.iter().find(|(_, key, _)| key == parent_key).map(|(_, _, id)| id.clone());
+        }
+        self.instances.iter().rev()
+            .filter(|(case_id, _, _)| *case_id == event.case_id)
+            .find(|(_, _, id)| {
+                self.execution.find_transaction_instance(id)
+                    .and_then(|t_i| self.model.find_transaction(&t_i.transaction_id))
+                    .is_some_and(|parent| parent.initiations.iter().any(|i| i.initiated_transaction_id == transaction.id))
+            })
+            .map(|(_, _, id)| id.clone())
+    }
+
+    // Why the instance cannot be started, should it be rejected as not startable
+    fn initiation_deviation(&self, transaction_instance: &TransactionInstance, initiator: &Subject) -> Option<(DeviationKind, String)> {
+        let model = self.model;
+        let transaction = model.find_transaction(&transaction_instance.transaction_id)?;
+        if !model.adt.is_authorised(&transaction.initiator_id, &initiator.id) {
+            let actor_role = model.find_actor_role(&transaction.initiator_id).map(|ar| ar.name.clone()).unwrap_or_default();
+            return Some((DeviationKind::Unauthorised, format!("{} is not authorised for actor role {}, the initiator of {}", initiator.name, actor_role, transaction.t_id)));
+        }
+        let Some(parent) = transaction_instance.parent_transaction_instance_id.as_ref().and_then(|id| self.execution.find_transaction_instance(id)) else {
+            return Some((DeviationKind::OutOfOrder, format!("{} is started on its own, but the model initiates it from another transaction", transaction.t_id)));
+        };
+        let parent_transaction = model.find_transaction(&parent.transaction_id)?;
+        let Some(initiation) = parent_transaction.initiations.iter().find(|i| i.initiated_transaction_id == transaction.id) else {
+            return Some((DeviationKind::NotInModel, format!("The model does not initiate {} from {}", transaction.t_id, parent_transaction.t_id)));
+        };
+        let count = self.execution.get_instances_of_transaction(&transaction.id, &Some(parent.id.clone())).ok()?.len();
+        if let MaxMultiplicity::Numeric(max) = initiation.multiplicity.max {
+            if count >= max as usize {
+                return Some((DeviationKind::MultiplicityExceeded, format!("Instance {} of {} within {} {}, but the multiplicity is {}", count + 1, transaction.t_id, parent_transaction.t_id, parent.product_instance, initiation.multiplicity)));
+            }
+        }
+        Some((DeviationKind::OutOfOrder, format!("{} is requested before {} is {}", transaction.t_id, parent_transaction.t_id, initiation.initiating_c_fact)))
+    }
+
+    fn describe(&self, error: &ExecutionError) -> (DeviationKind, String) {
+        let model = self.model;
+        let subject_name = |subject_id| model.find_subject(subject_id).map(|s| s.name.clone()).unwrap_or_default();
+        let actor_role_name = |actor_role_id| model.find_actor_role(actor_role_id).map(|ar| ar.name.clone()).unwrap_or_default();
+        use ExecutionError::*;
+        match error {
+            IllegalTransition { state: None, act, .. } => (DeviationKind::OutOfOrder, format!("{} cannot start a transaction", act)),
+            IllegalTransition { state: Some(state), act, .. } => (DeviationKind::OutOfOrder, format!("{} is not possible after {}", act, state)),
+            WrongPerformer { performer_id, act, required_role, .. } =>
+                (DeviationKind::Unauthorised, format!("{} is not the {} and cannot {}", subject_name(performer_id), required_role.to_string().to_lowercase(), act.to_string().to_lowercase())),
+            NotAuthorised { subject_id, actor_role_id } =>
+                (DeviationKind::Unauthorised, format!("{} is not authorised for actor role {}", subject_name(subject_id), actor_role_name(actor_role_id))),
+            NoAuthorisedSubject(actor_role_id) => (DeviationKind::Unauthorised, format!("No subject is authorised for actor role {}", actor_role_name(actor_role_id))),
+            Impeded { act, reasons } => (DeviationKind::WaitLinkViolated, format!("{} while waiting: {}", act, reasons.join("; "))),
+            NotStartable { .. } | UnknownTransaction(_) | UnknownTransactionInstance(_) | UnknownSubject(_) => (DeviationKind::NotInModel, error.to_string()),
+        }
+    }
+}
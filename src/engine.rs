@@ -0,0 +1,176 @@
+//! Headless simulation engine.
+//!
+//! [`Engine`] bundles a [`Model`] with its [`Execution`] and exposes the simulation through methods returning
+//! [`Result`]s instead of panicking on unknown ids, so that it can be driven from tests, command line tools or
+//! servers without the egui application (build with `--no-default-features`).
+//!
+//! ```
+//! use demosim::engine::Engine;
+//! use demosim::model::{ActorRole, AdtOption, CAct, CPAct, Model, Subject, Transaction};
+//!
+//! let customer = ActorRole { name: "Customer".to_string(), ..Default::default() };
+//! let seller = ActorRole { name: "Seller".to_string(), ..Default::default() };
+//! let alice = Subject { name: "Alice".to_string(), ..Default::default() };
+//! let bob = Subject { name: "Bob".to_string(), ..Default::default() };
+//! let mut sale = Transaction::new();
+//! sale.t_id = "T01".to_string();
+//! sale.initiator_id = customer.id.clone();
+//! sale.executor_id = seller.id.clone();
+//! let mut model = Model::default();
+//! model.adt.mappings.insert((customer.id.clone(), alice.id.clone()), AdtOption::Authorisation);
+//! model.adt.mappings.insert((seller.id.clone(), bob.id.clone()), AdtOption::Authorisation);
+//! model.actor_roles = vec![customer, seller];
+//! model.subjects = vec![alice.clone(), bob.clone()];
+//! model.transactions = vec![sale.clone()];
+//!
+//! let mut engine = Engine::new(model);
+//! let t_i_id = engine.start_transaction(None, &sale.id, "Book", &alice.id, &bob.id).unwrap();
+//! assert_eq!(engine.agenda(&bob.id).unwrap().len(), 1);
+//! engine.perform(&t_i_id, &bob.id, CPAct::CAct(CAct::Promise)).unwrap();
+//! assert!(engine.perform(&t_i_id, &alice.id, CPAct::PAct).is_err());
+//! ```
+
+use chrono::{DateTime, Duration, Utc};
+use rand::Rng;
+use crate::batch::{self, BatchConfig, BatchReport};
+use crate::execution::{AgendaItem, Execution, ExecutionError, TransactionInstance, TransactionInstanceId};
+use crate::model::{CPAct, CPFact, Model, Subject, SubjectId, Transaction, TransactionId};
+
+#[derive(Default, Clone)]
+pub struct Engine {
+    model: Model,
+    execution: Execution,
+}
+
+impl Engine {
+    pub fn new(model: Model) -> Self {
+        Self {
+            model,
+            execution: Execution::default(),
+        }
+    }
+
+    /// Continues an execution of the model, e.g. one restored from a file.
+    pub fn with_execution(model: Model, execution: Execution) -> Self {
+        Self { model, execution }
+    }
+
+    pub fn model(&self) -> &Model {
+        &self.model
+    }
+
+    pub fn execution(&self) -> &Execution {
+        &self.execution
+    }
+
+    pub fn into_parts(self) -> (Model, Execution) {
+        (self.model, self.execution)
+    }
+
+    /// Discards the execution, keeping the model.
+    pub fn reset(&mut self) {
+        self.execution = Execution::default();
+    }
+
+    pub fn subject(&self, subject_id: &SubjectId) -> Result<&Subject, ExecutionError> {
+        self.model.find_subject(subject_id).ok_or_else(|| ExecutionError::UnknownSubject(subject_id.clone()))
+    }
+
+    pub fn subject_by_name(&self, name: &str) -> Option<&Subject> {
+        self.model.subjects.iter().find(|s| s.name == name)
+    }
+
+    pub fn transaction(&self, transaction_id: &TransactionId) -> Result<&Transaction, ExecutionError> {
+        self.model.find_transaction(transaction_id).ok_or_else(|| ExecutionError::UnknownTransaction(transaction_id.clone()))
+    }
+
+    pub fn transaction_by_t_id(&self, t_id: &str) -> Option<&Transaction> {
+        self.model.transactions.iter().find(|t| t.t_id == t_id)
+    }
+
+    pub fn transaction_instance(&self, transaction_instance_id: &TransactionInstanceId) -> Result<&TransactionInstance, ExecutionError> {
+        self.execution.find_transaction_instance(transaction_instance_id)
+            .ok_or_else(|| ExecutionError::UnknownTransactionInstance(transaction_instance_id.clone()))
+    }
+
+    pub fn transaction_instances(&self) -> &[TransactionInstance] {
+        &self.execution.transactions_instances
+    }
+
+    /// Transactions the subject may initiate at the top level (`parent` is `None`) or within the given instance.
+    pub fn startable_transactions(&self, subject_id: &SubjectId, parent: Option<&TransactionInstanceId>) -> Result<Vec<&Transaction>, ExecutionError> {
+        self.subject(subject_id)?;
+        match parent {
+            None => Ok(self.model.directly_startable_transactions(subject_id)),
+            Some(parent_id) => {
+                let parent = self.transaction_instance(parent_id)?;
+                self.transaction(&parent.transaction_id)?;
+                self.execution.startable_subtransactions(&self.model, parent, subject_id)
+            }
+        }
+    }
+
+    /// Creates a new transaction instance and performs its request.
+    pub fn start_transaction(
+        &mut self,
+        parent: Option<&TransactionInstanceId>,
+        transaction_id: &TransactionId,
+        product_instance: impl Into<String>,
+        initiator_id: &SubjectId,
+        executor_id: &SubjectId,
+    ) -> Result<TransactionInstanceId, ExecutionError> {
+        let transaction_instance = TransactionInstance::new(parent.cloned(), transaction_id.clone(), product_instance.into(), initiator_id.clone(), executor_id.clone());
+        let transaction_instance_id = transaction_instance.id.clone();
+        self.execution.add_transaction_instance(&self.model, transaction_instance)?;
+        Ok(transaction_instance_id)
+    }
+
+    pub fn agenda(&self, subject_id: &SubjectId) -> Result<Vec<AgendaItem>, ExecutionError> {
+        self.subject(subject_id)?;
+        Ok(self.execution.agenda_for(subject_id))
+    }
+
+    /// The last fact of the instance; `None` only for an instance that has not been requested yet.
+    pub fn state(&self, transaction_instance_id: &TransactionInstanceId) -> Result<Option<CPFact>, ExecutionError> {
+        self.transaction_instance(transaction_instance_id)?;
+        Ok(self.execution.get_state(transaction_instance_id))
+    }
+
+    pub fn available_acts(&self, transaction_instance_id: &TransactionInstanceId, subject_id: &SubjectId) -> Result<Vec<CPAct>, ExecutionError> {
+        self.subject(subject_id)?;
+        let transaction_instance = self.transaction_instance(transaction_instance_id)?;
+        Ok(self.execution.available_acts(transaction_instance, subject_id))
+    }
+
+    pub fn now(&self) -> DateTime<Utc> {
+        self.execution.now()
+    }
+
+    /// Moves the simulated time forward.
+    pub fn advance_clock(&mut self, duration: Duration) {
+        self.execution.advance_clock(&self.model, duration)
+    }
+
+    /// Lets the automated subjects act; returns the number of steps they made.
+    pub fn run_policies<R: Rng>(&mut self, rng: &mut R) -> usize {
+        self.execution.run_policies(&self.model, rng)
+    }
+
+    /// Runs a batch simulation of the model; the engine's own execution is not affected.
+    pub fn run_batch(&self, transaction_id: &TransactionId, config: &BatchConfig) -> Result<BatchReport, ExecutionError> {
+        batch::run_batch(&self.model, transaction_id, config)
+    }
+
+    /// Takes back the last step; returns `false` when there is nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        self.execution.undo(&self.model)
+    }
+
+    pub fn redo(&mut self) -> bool {
+        self.execution.redo(&self.model)
+    }
+
+    pub fn perform(&mut self, transaction_instance_id: &TransactionInstanceId, subject_id: &SubjectId, act: CPAct) -> Result<(), ExecutionError> {
+        self.execution.process_new_fact(&self.model, transaction_instance_id.clone(), subject_id.clone(), act.to_fact())
+    }
+}
@@ -1,9 +1,10 @@
-use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use chrono::{DateTime, Duration, Utc};
 use uuid::Uuid;
-use crate::model::{CFact, CPAct, CPFact, Impediment, Model, Subject, SubjectId, Transaction, TransactionId};
-use crate::model::CFact::Promised;
+use crate::clock::SimulationClock;
+use crate::model::{ActorRoleId, CAct, CFact, CPAct, CPFact, Impediment, Model, Subject, SubjectId, Transaction, TransactionId, TransactionRole};
 
-#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(serde::Deserialize, serde::Serialize, schemars::JsonSchema, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct TransactionInstanceId(Uuid);
 
 impl std::fmt::Display for TransactionInstanceId {
@@ -12,7 +13,7 @@ impl std::fmt::Display for TransactionInstanceId {
     }
 }
 
-#[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
+#[derive(serde::Deserialize, serde::Serialize, schemars::JsonSchema, Debug, Clone)]
 pub struct TransactionInstance {
     pub id: TransactionInstanceId,
     pub parent_transaction_instance_id: Option<TransactionInstanceId>,
@@ -33,19 +34,89 @@ impl TransactionInstance {
             executor_id: executor,
         }
     }
+
+    pub fn get_subject_id_of_role(&self, role: TransactionRole) -> &SubjectId {
+        match role {
+            TransactionRole::Initiator => &self.initiator_id,
+            TransactionRole::Executor => &self.executor_id,
+        }
+    }
+
+    pub fn plays_role(&self, subject_id: &SubjectId, role: TransactionRole) -> bool {
+        self.get_subject_id_of_role(role) == subject_id
+    }
 }
 
-#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExecutionError {
+    UnknownTransaction(TransactionId),
+    UnknownTransactionInstance(TransactionInstanceId),
+    UnknownSubject(SubjectId),
+    NotStartable {
+        transaction_id: TransactionId,
+        subject_id: SubjectId,
+    },
+    IllegalTransition {
+        transaction_instance_id: TransactionInstanceId,
+        state: Option<CPFact>,
+        act: CPAct,
+    },
+    WrongPerformer {
+        transaction_instance_id: TransactionInstanceId,
+        performer_id: SubjectId,
+        act: CPAct,
+        required_role: TransactionRole,
+    },
+    NotAuthorised {
+        subject_id: SubjectId,
+        actor_role_id: ActorRoleId,
+    },
+    NoAuthorisedSubject(ActorRoleId),
+    Impeded {
+        act: CPAct,
+        reasons: Vec<String>,
+    },
+}
+
+impl std::fmt::Display for ExecutionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use ExecutionError::*;
+        match self {
+            UnknownTransaction(id) => write!(f, "Unknown transaction {}", id),
+            UnknownTransactionInstance(id) => write!(f, "Unknown transaction instance {}", id),
+            UnknownSubject(id) => write!(f, "Unknown subject {}", id),
+            NotStartable { transaction_id, subject_id } => write!(f, "Subject {} cannot initiate transaction {} here", subject_id, transaction_id),
+            IllegalTransition { transaction_instance_id, state, act } => match state {
+                None => write!(f, "{} cannot start transaction instance {}", act, transaction_instance_id),
+                Some(state) => write!(f, "{} is not possible in state {} of transaction instance {}", act, state, transaction_instance_id),
+            },
+            WrongPerformer { transaction_instance_id, performer_id, act, required_role } =>
+                write!(f, "Subject {} is not the {} of transaction instance {} and cannot perform {}", performer_id, required_role, transaction_instance_id, act),
+            NotAuthorised { subject_id, actor_role_id } =>
+                write!(f, "Subject {} is not authorised for actor role {} in the ADT", subject_id, actor_role_id),
+            NoAuthorisedSubject(actor_role_id) => write!(f, "No subject is authorised for actor role {} in the ADT", actor_role_id),
+            Impeded { act, reasons } => write!(f, "{} is impeded: {}", act, reasons.join("; ")),
+        }
+    }
+}
+
+impl std::error::Error for ExecutionError {}
+
+#[derive(serde::Deserialize, serde::Serialize, schemars::JsonSchema, Debug, Clone, PartialEq, Eq)]
 pub struct CWorldItem {
     pub timestamp: DateTime<Utc>,
     pub transaction_instance_id: TransactionInstanceId,
     pub performer: Subject,
     pub addressee: Subject,
     pub fact: CFact,
+    #[serde(default)]
+    pub tacit: bool,
+    #[serde(default)]
+    pub on_deadline_expiry: bool, // performed automatically because the deadline for responding has expired
 }
 
 
-#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq, Eq)]
+#[derive(serde::Deserialize, serde::Serialize, schemars::JsonSchema, Debug, Clone, PartialEq, Eq)]
 pub struct PWorldItem {
     pub timestamp: DateTime<Utc>,
     pub transaction_instance_id: TransactionInstanceId,
@@ -53,7 +124,7 @@ pub struct PWorldItem {
 }
 
 
-#[derive(serde::Deserialize, serde::Serialize, PartialEq, Eq, Debug, Clone)]
+#[derive(serde::Deserialize, serde::Serialize, schemars::JsonSchema, PartialEq, Eq, Debug, Clone)]
 pub enum CPWorldItem {
     CWorldItem(CWorldItem),
     PWorldItem(PWorldItem),
@@ -91,42 +162,319 @@ impl CPWorldItem {
         }
     }
 
+    pub fn is_tacit(&self) -> bool {
+        use CPWorldItem::*;
+        match self {
+            CWorldItem(c) => c.tacit,
+            PWorldItem(_) => false,
+        }
+    }
+
 }
 
 
-#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq, Eq)]
+#[derive(serde::Deserialize, serde::Serialize, schemars::JsonSchema, Debug, Clone, PartialEq, Eq)]
 pub struct AgendaItem {
     pub timestamp: DateTime<Utc>,
     pub transaction_instance_id: TransactionInstanceId,
     pub performer_id: SubjectId,
     pub fact: CPFact,
+    #[serde(default)]
+    pub due: Option<DateTime<Utc>>,
 }
 
 impl AgendaItem {
-    pub fn new(transaction_instance_id: TransactionInstanceId, performer_id: SubjectId, fact: CPFact) -> Self {
+    pub fn new(timestamp: DateTime<Utc>, transaction_instance_id: TransactionInstanceId, performer_id: SubjectId, fact: CPFact) -> Self {
         Self {
-            timestamp: Utc::now(),
+            timestamp,
             transaction_instance_id,
             performer_id,
             fact,
+            due: None,
         }
     }
+
+    pub fn is_overdue(&self, now: &DateTime<Utc>) -> bool {
+        self.due.is_some_and(|due| due <= *now)
+    }
+}
+
+// Guards against deadlines that keep expiring into each other, e.g. declining on expiry and requesting again on expiry
+const MAX_EXPIRIES: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FactOrigin {
+    Explicit,
+    Tacit,
+    DeadlineExpiry,
 }
 
 
-#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Default)]
+/// A step of the execution. The execution is the result of applying its events in order, which makes it possible
+/// to take steps back and forth.
+#[derive(serde::Deserialize, serde::Serialize, schemars::JsonSchema, Debug, Clone)]
+pub enum ExecutionEvent {
+    TransactionInstanceCreated {
+        timestamp: DateTime<Utc>,
+        transaction_instance: TransactionInstance,
+    },
+    FactCreated {
+        timestamp: DateTime<Utc>,
+        transaction_instance_id: TransactionInstanceId,
+        performer_id: SubjectId,
+        fact: CPFact,
+    },
+    TransactionInstanceDeleted {
+        timestamp: DateTime<Utc>,
+        transaction_instance_id: TransactionInstanceId,
+    },
+    ClockAdvanced {
+        timestamp: DateTime<Utc>,
+    },
+}
+
+impl ExecutionEvent {
+    pub fn get_timestamp(&self) -> &DateTime<Utc> {
+        use ExecutionEvent::*;
+        match self {
+            TransactionInstanceCreated { timestamp, .. } => timestamp,
+            FactCreated { timestamp, .. } => timestamp,
+            TransactionInstanceDeleted { timestamp, .. } => timestamp,
+            ClockAdvanced { timestamp } => timestamp,
+        }
+    }
+
+    pub fn describe(&self, model: &Model, execution: &Execution) -> String {
+        let subject_name = |subject_id: &SubjectId| model.find_subject(subject_id).map_or_else(|| subject_id.to_string(), |s| s.name.clone());
+        let transaction_name = |transaction_id: &TransactionId| model.find_transaction(transaction_id).map_or_else(|| transaction_id.to_string(), |t| t.t_id.clone());
+        let instance_name = |transaction_instance_id: &TransactionInstanceId| match execution.find_transaction_instance(transaction_instance_id) {
+            Some(t_i) => format!("{} ({})", transaction_name(&t_i.transaction_id), t_i.product_instance),
+            None => transaction_instance_id.to_string(),
+        };
+        use ExecutionEvent::*;
+        match self {
+            TransactionInstanceCreated { transaction_instance, .. } =>
+                format!("{}: Request {} ({})", subject_name(&transaction_instance.initiator_id), transaction_name(&transaction_instance.transaction_id), transaction_instance.product_instance),
+            FactCreated { transaction_instance_id, performer_id, fact, .. } =>
+                format!("{}: {} {}", subject_name(performer_id), fact.to_act(), instance_name(transaction_instance_id)),
+            TransactionInstanceDeleted { transaction_instance_id, .. } =>
+                format!("Delete {}", instance_name(transaction_instance_id)),
+            ClockAdvanced { timestamp } => format!("Advance clock to {}", timestamp),
+        }
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, schemars::JsonSchema, Debug, Clone, Default)]
 pub struct Execution {
     pub transactions_instances: Vec<TransactionInstance>,
     pub c_p_world: Vec<CPWorldItem>,
     pub agendas: Vec<(SubjectId, AgendaItem)>,
+    // The instances, the C/P world and the agendas above are derived from the events
+    #[serde(default)]
+    events: Vec<ExecutionEvent>,
+    #[serde(default)]
+    undone_events: Vec<ExecutionEvent>,
+    #[serde(default)]
+    pub clock: SimulationClock,
+    // What the policies of automated subjects decided for the items on their agendas, so that it is drawn only once
+    #[serde(skip)]
+    pub(crate) automated_decisions: HashMap<(TransactionInstanceId, SubjectId, CPFact), Option<CPAct>>,
 }
 
 impl Execution {
+    pub fn find_transaction_instance(&self, transaction_instance_id: &TransactionInstanceId) -> Option<&TransactionInstance> {
+        self.transactions_instances.iter().find(|t_i| t_i.id == *transaction_instance_id)
+    }
+
     pub fn get_transaction_instance(&self, transaction_instance_id: &TransactionInstanceId) -> &TransactionInstance {
         self.transactions_instances.iter().find(|t_i| t_i.id == *transaction_instance_id).unwrap()
     }
 
+    pub fn events(&self) -> &[ExecutionEvent] {
+        &self.events
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.events.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.undone_events.is_empty()
+    }
+
+    /// The step `undo` would take back.
+    pub fn last_event(&self) -> Option<&ExecutionEvent> {
+        self.events.last()
+    }
+
+    /// The step `redo` would perform again.
+    pub fn next_redo_event(&self) -> Option<&ExecutionEvent> {
+        self.undone_events.last()
+    }
+
+    /// Takes back the last step. The execution is rebuilt by replaying the remaining events.
+    pub fn undo(&mut self, model: &Model) -> bool {
+        match self.events.pop() {
+            None => false,
+            Some(event) => {
+                self.undone_events.push(event);
+                self.rebuild(model);
+                true
+            }
+        }
+    }
+
+    pub fn redo(&mut self, model: &Model) -> bool {
+        match self.undone_events.pop() {
+            None => false,
+            Some(event) => match self.apply_event(model, &event) {
+                Ok(()) => {
+                    self.events.push(event);
+                    true
+                }
+                Err(e) => {
+                    log::warn!("Cannot redo {:?}: {}", event, e);
+                    self.undone_events.clear();
+                    false
+                }
+            },
+        }
+    }
+
+    /// Recomputes the instances, the C/P world and the agendas from the events. Events that are no longer valid
+    /// (e.g. because the model has changed meanwhile) are dropped.
+    fn rebuild(&mut self, model: &Model) {
+        self.clock.reset();
+        self.transactions_instances.clear();
+        self.c_p_world.clear();
+        self.agendas.clear();
+        for event in std::mem::take(&mut self.events) {
+            match self.apply_event(model, &event) {
+                Ok(()) => self.events.push(event),
+                Err(e) => log::warn!("Dropping {:?} from the execution: {}", event, e),
+            }
+        }
+    }
+
+    /// Prepares an execution read from a file for the model: the state is recomputed from the events, dropping those
+    /// that are not valid for the model, after building them from the state for executions stored without them.
+    /// Returns the number of dropped events.
+    pub fn restore(&mut self, model: &Model) -> usize {
+        self.build_event_log();
+        let events_count = self.events.len();
+        self.rebuild(model);
+        self.undone_events.clear();
+        events_count - self.events.len()
+    }
+
+    // Executions stored before the events were introduced have just the derived state: the events are
+    // reconstructed from it once, when the execution is restored (leaving out tacit facts, which are regenerated by the facts triggering them).
+    fn build_event_log(&mut self) {
+        if !self.events.is_empty() || self.transactions_instances.is_empty() {
+            return;
+        }
+        for c_p_world_item in self.c_p_world.iter().filter(|c_p_world_item| !c_p_world_item.is_tacit()) {
+            let transaction_instance_id = c_p_world_item.get_transaction_instance_id();
+            let fact = c_p_world_item.to_fact();
+            if fact == CPFact::CFact(CFact::Requested) && !self.events.iter().any(|e| matches!(e, ExecutionEvent::TransactionInstanceCreated { transaction_instance, .. } if transaction_instance.id == *transaction_instance_id)) {
+                if let Some(transaction_instance) = self.find_transaction_instance(transaction_instance_id) {
+                    self.events.push(ExecutionEvent::TransactionInstanceCreated {
+                        timestamp: *c_p_world_item.get_timestamp(),
+                        transaction_instance: transaction_instance.clone(),
+                    });
+                }
+            } else {
+                self.events.push(ExecutionEvent::FactCreated {
+                    timestamp: *c_p_world_item.get_timestamp(),
+                    transaction_instance_id: transaction_instance_id.clone(),
+                    performer_id: c_p_world_item.get_performer().id.clone(),
+                    fact,
+                });
+            }
+        }
+    }
+
+    fn perform_event(&mut self, model: &Model, event: ExecutionEvent) -> Result<(), ExecutionError> {
+        self.apply_event(model, &event)?;
+        self.events.push(event);
+        self.undone_events.clear();
+        Ok(())
+    }
+
+    fn apply_event(&mut self, model: &Model, event: &ExecutionEvent) -> Result<(), ExecutionError> {
+        // Whatever expired before this step happens first. When the step then turns out to be invalid, the expiries are
+        // taken back with it, to happen with the next valid step as they do when the execution is rebuilt without it.
+        // The same holds for the time.
+        let clock = self.clock.clone();
+        let expiring = self.agendas.iter().any(|(_, agenda_item)| agenda_item.is_overdue(event.get_timestamp()));
+        let saved_o = expiring.then(|| (self.transactions_instances.clone(), self.c_p_world.clone(), self.agendas.clone()));
+        self.process_expired_deadlines(model, event.get_timestamp());
+        let res = self.apply_step(model, event);
+        if res.is_err() {
+            self.clock = clock;
+            if let Some((transactions_instances, c_p_world, agendas)) = saved_o {
+                self.transactions_instances = transactions_instances;
+                self.c_p_world = c_p_world;
+                self.agendas = agendas;
+            }
+        }
+        res
+    }
+
+    fn apply_step(&mut self, model: &Model, event: &ExecutionEvent) -> Result<(), ExecutionError> {
+        self.clock.observe(event.get_timestamp());
+        use ExecutionEvent::*;
+        match event {
+            TransactionInstanceCreated { timestamp, transaction_instance } =>
+                self.apply_transaction_instance_created(model, timestamp, transaction_instance.clone()),
+            FactCreated { timestamp, transaction_instance_id, performer_id, fact } => {
+                self.check_transition(model, transaction_instance_id, performer_id, fact)?;
+                self.record_fact(model, timestamp, transaction_instance_id.clone(), performer_id.clone(), fact.clone(), FactOrigin::Explicit)?;
+                self.perform_tacit_acts(model, timestamp, transaction_instance_id);
+                Ok(())
+            }
+            TransactionInstanceDeleted { transaction_instance_id, .. } => {
+                self.find_transaction_instance(transaction_instance_id)
+                    .ok_or_else(|| ExecutionError::UnknownTransactionInstance(transaction_instance_id.clone()))?;
+                self.remove_transaction_instance(transaction_instance_id);
+                Ok(())
+            }
+            ClockAdvanced { .. } => Ok(()),
+        }
+    }
+
+    pub fn now(&self) -> DateTime<Utc> {
+        self.clock.now()
+    }
+
+    /// Moves the simulated time forward. This is a step of the execution, so it can be undone.
+    pub fn advance_clock(&mut self, model: &Model, duration: Duration) {
+        if duration > Duration::zero() {
+            let timestamp = self.clock.now() + duration;
+            _ = self.perform_event(model, ExecutionEvent::ClockAdvanced { timestamp });
+        }
+    }
+
+    /// Deletes the instance with its subtransaction instances, all of their facts and agenda items, as one step to
+    /// be undone.
     pub fn delete_transaction_instance(&mut self, transaction_instance_id: &TransactionInstanceId) {
+        // Deletion does not depend on the model, so it is applied directly
+        if self.find_transaction_instance(transaction_instance_id).is_some() {
+            self.remove_transaction_instance(transaction_instance_id);
+            self.events.push(ExecutionEvent::TransactionInstanceDeleted { timestamp: self.clock.now(), transaction_instance_id: transaction_instance_id.clone() });
+            self.undone_events.clear();
+        }
+    }
+
+    // Removes the instance together with its subtransactions, which would be left without a parent otherwise
+    fn remove_transaction_instance(&mut self, transaction_instance_id: &TransactionInstanceId) {
+        let children: Vec<TransactionInstanceId> = self.transactions_instances.iter()
+            .filter(|t_i| t_i.parent_transaction_instance_id.as_ref() == Some(transaction_instance_id))
+            .map(|t_i| t_i.id.clone())
+            .collect();
+        for child_id in &children {
+            self.remove_transaction_instance(child_id);
+        }
         if let Some(pos) = self.transactions_instances.iter().position(|t_i| t_i.id == *transaction_instance_id) {
             self.transactions_instances.remove(pos);
         }
@@ -138,16 +486,24 @@ impl Execution {
         self.agendas.retain(|(_, agenda_item)| agenda_item.transaction_instance_id != *transaction_instance_id);
     }
 
+    /// Instances of the transaction within the given instance and its ancestors, as counted against the
+    /// multiplicities of initiations and awaited by wait links. Instances whose request has been revoked are left
+    /// out, as they are cancelled.
     // This is recursive -- it gathers instances from the whole ancestry
-    pub fn get_instances_of_transaction(&self, transaction_id: &TransactionId, parent_transaction_instance_id_o: &Option<TransactionInstanceId>) -> Vec<&TransactionInstance> {
-        let direct_parent = self.transactions_instances.iter().filter(|t_i| t_i.transaction_id == *transaction_id && t_i.parent_transaction_instance_id == *parent_transaction_instance_id_o);
+    pub fn get_instances_of_transaction(&self, transaction_id: &TransactionId, parent_transaction_instance_id_o: &Option<TransactionInstanceId>) -> Result<Vec<&TransactionInstance>, ExecutionError> {
+        let direct_parent = self.transactions_instances.iter()
+            .filter(|t_i| t_i.transaction_id == *transaction_id && t_i.parent_transaction_instance_id == *parent_transaction_instance_id_o)
+            .filter(|t_i| !self.is_cancelled(&t_i.id));
         let mut all: Vec<&TransactionInstance> = match parent_transaction_instance_id_o {
             None => Vec::new(),
-            Some(parent_transaction_instance_id) => self.get_instances_of_transaction(transaction_id, &self.get_transaction_instance(parent_transaction_instance_id).parent_transaction_instance_id),
+            Some(parent_transaction_instance_id) => {
+                let parent = self.find_transaction_instance(parent_transaction_instance_id)
+                    .ok_or_else(|| ExecutionError::UnknownTransactionInstance(parent_transaction_instance_id.clone()))?;
+                self.get_instances_of_transaction(transaction_id, &parent.parent_transaction_instance_id)?
+            }
         };
         all.extend(direct_parent);
-        all
-
+        Ok(all)
     }
 
     #[inline]
@@ -160,25 +516,132 @@ impl Execution {
             Declared => transaction_instance.initiator_id.clone(),
             Accepted => transaction_instance.executor_id.clone(),
             Rejected => transaction_instance.executor_id.clone(),
-            // RequestRevoked => transaction_instance.executor_id.clone(),
-            // PromiseRevoked => transaction_instance.initiator_id.clone(),
-            // DeclineRevoked => transaction_instance.initiator_id.clone(),
-            // AcceptRevoked => transaction_instance.executor_id.clone(),
-            // RejectRevoked => transaction_instance.executor_id.clone(),
+            Quitted => transaction_instance.executor_id.clone(),
+            Stopped => transaction_instance.initiator_id.clone(),
+            RequestRevoked => transaction_instance.executor_id.clone(),
+            PromiseRevoked => transaction_instance.initiator_id.clone(),
+            DeclineRevoked => transaction_instance.initiator_id.clone(),
+            DeclareRevoked => transaction_instance.initiator_id.clone(),
+            AcceptRevoked => transaction_instance.executor_id.clone(),
+            RejectRevoked => transaction_instance.executor_id.clone(),
         }
     }
 
-    pub fn add_transaction_instance(&mut self, model: &Model, transaction_instance: TransactionInstance) {
+    pub fn add_transaction_instance(&mut self, model: &Model, transaction_instance: TransactionInstance) -> Result<(), ExecutionError> {
+        self.add_transaction_instance_after(model, transaction_instance, Duration::zero())
+    }
+
+    /// Like [`Execution::add_transaction_instance`], but the request takes the given time, which passes only when the
+    /// request succeeds.
+    pub(crate) fn add_transaction_instance_after(&mut self, model: &Model, transaction_instance: TransactionInstance, duration: Duration) -> Result<(), ExecutionError> {
+        self.perform_event(model, ExecutionEvent::TransactionInstanceCreated { timestamp: self.clock.next_step_time() + duration, transaction_instance })
+    }
+
+    fn apply_transaction_instance_created(&mut self, model: &Model, timestamp: &DateTime<Utc>, transaction_instance: TransactionInstance) -> Result<(), ExecutionError> {
+        let transaction = model.find_transaction(&transaction_instance.transaction_id)
+            .ok_or_else(|| ExecutionError::UnknownTransaction(transaction_instance.transaction_id.clone()))?;
+        let initiator_id = &transaction_instance.initiator_id;
+        model.find_subject(initiator_id).ok_or_else(|| ExecutionError::UnknownSubject(initiator_id.clone()))?;
+        // Top-level instances only of transactions not initiated from others, subtransactions according to the initiations
+        let startable = match &transaction_instance.parent_transaction_instance_id {
+            None => model.directly_startable_transactions(initiator_id).contains(&transaction),
+            Some(parent_id) => {
+                let parent = self.find_transaction_instance(parent_id).ok_or_else(|| ExecutionError::UnknownTransactionInstance(parent_id.clone()))?;
+                self.startable_subtransactions(model, parent, initiator_id)?.contains(&transaction)
+            }
+        };
+        if !startable {
+            return Err(ExecutionError::NotStartable { transaction_id: transaction.id.clone(), subject_id: initiator_id.clone() });
+        }
+        // The executor is only addressed by the request, so it has to be checked here
+        model.find_subject(&transaction_instance.executor_id).ok_or_else(|| ExecutionError::UnknownSubject(transaction_instance.executor_id.clone()))?;
+        if !model.adt.is_authorised(&transaction.executor_id, &transaction_instance.executor_id) {
+            return Err(ExecutionError::NotAuthorised { subject_id: transaction_instance.executor_id.clone(), actor_role_id: transaction.executor_id.clone() });
+        }
         let transaction_instance_id = transaction_instance.id.clone();
         let initiator_id = transaction_instance.initiator_id.clone();
         self.transactions_instances.push(transaction_instance);
-        self.process_new_fact(model, transaction_instance_id, initiator_id, CPFact::CFact(CFact::Requested));
+        let fact = CPFact::CFact(CFact::Requested);
+        if let Err(e) = self.check_transition(model, &transaction_instance_id, &initiator_id, &fact) {
+            self.transactions_instances.pop();
+            return Err(e);
+        }
+        if let Err(e) = self.record_fact(model, timestamp, transaction_instance_id.clone(), initiator_id, fact, FactOrigin::Explicit) {
+            self.transactions_instances.pop();
+            return Err(e);
+        }
+        self.perform_tacit_acts(model, timestamp, &transaction_instance_id);
+        Ok(())
     }
 
-    pub fn process_new_fact(&mut self, model: &Model, transaction_instance_id: TransactionInstanceId, performer_id: SubjectId, fact: CPFact) {
-        let transaction_instance = self.get_transaction_instance(&transaction_instance_id).to_owned();
-        let performer = model.get_subject(&performer_id).to_owned();
-        let agenda_item = AgendaItem::new(transaction_instance_id.clone(), performer_id.clone(), fact.clone());
+    /// Checks that the performer may create the fact in the transaction instance right now: the act must follow
+    /// the current state according to the transaction pattern, the performer must play the role performing the act
+    /// and be authorised for the corresponding actor role, and no wait link may hold the act back.
+    pub fn check_transition(&self, model: &Model, transaction_instance_id: &TransactionInstanceId, performer_id: &SubjectId, fact: &CPFact) -> Result<(), ExecutionError> {
+        let transaction_instance = self.find_transaction_instance(transaction_instance_id)
+            .ok_or_else(|| ExecutionError::UnknownTransactionInstance(transaction_instance_id.clone()))?;
+        let transaction = model.find_transaction(&transaction_instance.transaction_id)
+            .ok_or_else(|| ExecutionError::UnknownTransaction(transaction_instance.transaction_id.clone()))?;
+        model.find_subject(performer_id).ok_or_else(|| ExecutionError::UnknownSubject(performer_id.clone()))?;
+        let act = fact.to_act();
+        let state = self.get_state(transaction_instance_id);
+        let allowed = match &state {
+            None => act == CPAct::CAct(CAct::Request),
+            Some(state) => state.next_acts().contains(&act),
+        };
+        if !allowed {
+            return Err(ExecutionError::IllegalTransition { transaction_instance_id: transaction_instance_id.clone(), state, act });
+        }
+        let role = act.performer_role();
+        if !transaction_instance.plays_role(performer_id, role) {
+            return Err(ExecutionError::WrongPerformer {
+                transaction_instance_id: transaction_instance_id.clone(),
+                performer_id: performer_id.clone(),
+                act,
+                required_role: role,
+            });
+        }
+        let actor_role_id = transaction.get_actor_role_id(role);
+        if !model.adt.is_authorised(actor_role_id, performer_id) {
+            return Err(ExecutionError::NotAuthorised { subject_id: performer_id.clone(), actor_role_id: actor_role_id.clone() });
+        }
+        if let Some(reasons) = self.get_act_impediments(model, transaction, &self.impediments_context(transaction_instance), &act) {
+            return Err(ExecutionError::Impeded { act, reasons });
+        }
+        Ok(())
+    }
+
+    pub fn process_new_fact(&mut self, model: &Model, transaction_instance_id: TransactionInstanceId, performer_id: SubjectId, fact: CPFact) -> Result<(), ExecutionError> {
+        self.process_new_fact_after(model, transaction_instance_id, performer_id, fact, Duration::zero())
+    }
+
+    /// Like [`Execution::process_new_fact`], but the act takes the given time, which passes only when the act succeeds.
+    pub(crate) fn process_new_fact_after(&mut self, model: &Model, transaction_instance_id: TransactionInstanceId, performer_id: SubjectId, fact: CPFact, duration: Duration) -> Result<(), ExecutionError> {
+        self.perform_event(model, ExecutionEvent::FactCreated { timestamp: self.clock.next_step_time() + duration, transaction_instance_id, performer_id, fact })
+    }
+
+    /// Applies the step as it happened according to a log of a real execution (see [`crate::conformance`]). Deadlines
+    /// do not expire on their own, the log tells what was done about them, and the step is not put in the event log,
+    /// as such a replay is not to be undone.
+    pub(crate) fn replay_event(&mut self, model: &Model, event: &ExecutionEvent) -> Result<(), ExecutionError> {
+        let clock = self.clock.clone();
+        let res = self.apply_step(model, event);
+        if res.is_err() {
+            self.clock = clock;
+        }
+        res
+    }
+
+    // Nothing is recorded when the instance, its transaction or a subject taking part in the act is unknown
+    pub(crate) fn record_fact(&mut self, model: &Model, timestamp: &DateTime<Utc>, transaction_instance_id: TransactionInstanceId, performer_id: SubjectId, fact: CPFact, origin: FactOrigin) -> Result<(), ExecutionError> {
+        let transaction_instance = self.find_transaction_instance(&transaction_instance_id)
+            .ok_or_else(|| ExecutionError::UnknownTransactionInstance(transaction_instance_id.clone()))?
+            .to_owned();
+        let transaction = model.find_transaction(&transaction_instance.transaction_id)
+            .ok_or_else(|| ExecutionError::UnknownTransaction(transaction_instance.transaction_id.clone()))?;
+        let performer = model.find_subject(&performer_id).ok_or_else(|| ExecutionError::UnknownSubject(performer_id.clone()))?.to_owned();
+        let mut agenda_item = AgendaItem::new(*timestamp, transaction_instance_id.clone(), performer_id.clone(), fact.clone());
+        agenda_item.due = transaction.get_deadline_for_fact(&fact).map(|deadline| *timestamp + Duration::minutes(deadline.within_minutes));
         use CPFact::*;
         match &fact {
             PFact => {
@@ -188,26 +651,136 @@ impl Execution {
                     performer,
                 });
                 self.c_p_world.push(new_c_p_world_item);
-                self.agendas.push((transaction_instance.executor_id.clone(), agenda_item));
             },
             CFact(c_fact) => {
                 let addressee_id = self.get_addressee_for_c_fact(&transaction_instance, &c_fact);
-                let addressee = model.get_subject(&addressee_id).to_owned();
+                let addressee = model.find_subject(&addressee_id).ok_or_else(|| ExecutionError::UnknownSubject(addressee_id.clone()))?.to_owned();
                 let new_c_p_world_item = CPWorldItem::CWorldItem(CWorldItem {
                     timestamp: agenda_item.timestamp.clone(),
                     transaction_instance_id: transaction_instance_id.clone(),
                     performer,
                     addressee,
                     fact: c_fact.clone(),
+                    tacit: origin == FactOrigin::Tacit,
+                    on_deadline_expiry: origin == FactOrigin::DeadlineExpiry,
                 });
                 self.c_p_world.push(new_c_p_world_item);
-                if *c_fact == Promised {
-                    self.agendas.push((performer_id, agenda_item));
-                } else if !fact.next_acts().is_empty() {
-                    self.agendas.push((addressee_id, agenda_item));
-                }
             },
         };
+        // The new fact supersedes whatever was pending in the transaction (a revocation may even hit the party
+        // who did not have the turn), so the agenda of the instance is rebuilt from scratch.
+        self.agendas.retain(|(_, agenda_item1)| agenda_item1.transaction_instance_id != transaction_instance_id);
+        if let Some(role) = fact.agenda_role() {
+            self.agendas.push((transaction_instance.get_subject_id_of_role(role).clone(), agenda_item));
+        }
+        Ok(())
+    }
+
+    // Performs the acts configured to happen on expiry of deadlines that have expired up to the given time, in the
+    // order of expiration. An expiry act that is not possible (e.g. because of a wait link) is just skipped, the
+    // item stays overdue on the agenda.
+    fn process_expired_deadlines(&mut self, model: &Model, until: &DateTime<Utc>) {
+        let mut skipped: Vec<AgendaItem> = Vec::new();
+        for _ in 0..MAX_EXPIRIES {
+            let expired = self.agendas.iter()
+                .filter(|(_, agenda_item)| agenda_item.is_overdue(until) && !skipped.contains(agenda_item))
+                .filter_map(|(_, agenda_item)| {
+                    let transaction_instance = self.find_transaction_instance(&agenda_item.transaction_instance_id)?;
+                    let transaction = model.find_transaction(&transaction_instance.transaction_id)?;
+                    let on_expiry = transaction.get_deadline_for_fact(&agenda_item.fact)?.on_expiry.clone()?;
+                    let performer_id = transaction_instance.get_subject_id_of_role(on_expiry.performer_role()).clone();
+                    Some((agenda_item.clone(), performer_id, CPFact::CFact(on_expiry.to_fact())))
+                })
+                .min_by_key(|(agenda_item, _, _)| agenda_item.due);
+            let Some((agenda_item, performer_id, fact)) = expired else { return };
+            let due = agenda_item.due.unwrap_or(*until);
+            if self.check_transition(model, &agenda_item.transaction_instance_id, &performer_id, &fact).is_err()
+                || self.record_fact(model, &due, agenda_item.transaction_instance_id.clone(), performer_id, fact, FactOrigin::DeadlineExpiry).is_err() {
+                skipped.push(agenda_item);
+                continue;
+            }
+            self.clock.observe(&due);
+            self.perform_tacit_acts(model, &due, &agenda_item.transaction_instance_id);
+        }
+        log::warn!("Stopped performing acts on deadline expiry after {} of them", MAX_EXPIRIES);
+    }
+
+    pub fn overdue_agenda_items(&self) -> Vec<&(SubjectId, AgendaItem)> {
+        let now = self.now();
+        self.agendas.iter().filter(|(_, agenda_item)| agenda_item.is_overdue(&now)).collect()
+    }
+
+    // Keeps generating the acts the transaction is configured to perform tacitly until it is someone's turn to act
+    // explicitly (or a wait link holds the act back -- then it stays on the agenda to be performed by hand).
+    // A revoked act is never repeated tacitly, otherwise the revocation would have no effect.
+    pub(crate) fn perform_tacit_acts(&mut self, model: &Model, timestamp: &DateTime<Utc>, transaction_instance_id: &TransactionInstanceId) {
+        let Some(transaction_instance) = self.find_transaction_instance(transaction_instance_id).cloned() else { return };
+        let Some(transaction) = model.find_transaction(&transaction_instance.transaction_id) else { return };
+        while let Some(state) = self.get_state(transaction_instance_id) {
+            if matches!(&state, CPFact::CFact(c_fact) if c_fact.is_revocation()) { break; }
+            let Some(role) = state.agenda_role() else { break };
+            let Some(tacit_act) = state.next_acts().into_iter().find(|act| act.performer_role() == role && transaction.is_tacit(act)) else { break };
+            let performer_id = transaction_instance.get_subject_id_of_role(role).clone();
+            if self.check_transition(model, transaction_instance_id, &performer_id, &tacit_act.to_fact()).is_err()
+                || self.record_fact(model, timestamp, transaction_instance_id.clone(), performer_id, tacit_act.to_fact(), FactOrigin::Tacit).is_err() {
+                break;
+            }
+        }
+    }
+
+    /// Facts of the transaction instance that are currently in effect, i.e. the path from the request to the
+    /// current state with the facts undone by revocations left out.
+    pub fn get_effective_facts(&self, transaction_instance_id: &TransactionInstanceId) -> Vec<CPFact> {
+        let mut path: Vec<CPFact> = Vec::new();
+        for fact in self.get_facts_for_transaction_instance(transaction_instance_id).iter().map(|c_p_world_item| c_p_world_item.to_fact()) {
+            match &fact {
+                CPFact::CFact(c_fact) if c_fact.is_revocation() => {
+                    match c_fact.rollback_target() {
+                        None => path.clear(),
+                        Some(target) => {
+                            let target = CPFact::CFact(target);
+                            while path.last().is_some_and(|f| *f != target) {
+                                path.pop();
+                            }
+                        }
+                    }
+                }
+                _ => path.push(fact),
+            }
+        }
+        path
+    }
+
+    pub fn is_fact_reached(&self, transaction_instance_id: &TransactionInstanceId, fact: &CPFact) -> bool {
+        self.get_effective_facts(transaction_instance_id).contains(fact)
+    }
+
+    /// Whether the request of the transaction instance has been revoked, which ends it without a result.
+    pub fn is_cancelled(&self, transaction_instance_id: &TransactionInstanceId) -> bool {
+        self.get_state(transaction_instance_id) == Some(CPFact::CFact(CFact::RequestRevoked))
+    }
+
+    /// The last fact created in the transaction instance (which may be a revocation).
+    pub fn get_state(&self, transaction_instance_id: &TransactionInstanceId) -> Option<CPFact> {
+        self.get_facts_for_transaction_instance(transaction_instance_id).last().map(|c_p_world_item| c_p_world_item.to_fact())
+    }
+
+    /// Acts the subject may perform in the transaction instance now, with respect to the role(s) it plays in it.
+    pub fn available_acts(&self, transaction_instance: &TransactionInstance, subject_id: &SubjectId) -> Vec<CPAct> {
+        match self.get_state(&transaction_instance.id) {
+            None => Vec::new(),
+            Some(state) => state.next_acts().into_iter()
+                .filter(|act| transaction_instance.plays_role(subject_id, act.performer_role()))
+                .collect(),
+        }
+    }
+
+    /// Instances where the subject may revoke something but that are not on its agenda.
+    pub fn revocable_transaction_instances(&self, subject_id: &SubjectId) -> Vec<&TransactionInstance> {
+        self.transactions_instances.iter()
+            .filter(|t_i| !self.agendas.iter().any(|(subject_id1, agenda_item)| subject_id1 == subject_id && agenda_item.transaction_instance_id == t_i.id))
+            .filter(|t_i| self.available_acts(t_i, subject_id).iter().any(|act| act.is_revocation()))
+            .collect()
     }
 
     pub fn agenda_for(&self, subject_id: &SubjectId) -> Vec<AgendaItem> {
@@ -216,12 +789,6 @@ impl Execution {
             .collect()
     }
 
-    pub fn remove_agenda_item(&mut self, agenda_item: &AgendaItem) {
-        if let Some(pos) = self.agendas.iter().position(|(_, agenda_item1)| *agenda_item1 == *agenda_item) {
-            self.agendas.remove(pos);
-        }
-    }
-
     pub fn get_facts_for_transaction_instance(&self, transaction_instance_id: &TransactionInstanceId) -> Vec<&CPWorldItem> {
         self.c_p_world.iter().filter(|c_p_world_item| c_p_world_item.get_transaction_instance_id() == transaction_instance_id).collect()
     }
@@ -247,36 +814,41 @@ impl Execution {
         model: &'a Model,
         parent_transaction_instance: &TransactionInstance,
         subject_id: &SubjectId,
-    ) -> Vec<&'a Transaction> {
-        let parent_transaction = model.get_transaction(&parent_transaction_instance.transaction_id);
-
-        let mut res: Vec<&Transaction> = parent_transaction
-            .initiations
-            .iter()
-            .filter_map(|initiation| {
-                let initiated_transaction = model.get_transaction(&initiation.initiated_transaction_id);
-                // Check if the multiplicity constraint is met
-                let instance_count = self.get_instances_of_transaction(&initiated_transaction.id, &Some(parent_transaction_instance.id.clone())).len();
-                let within_bound = initiation.multiplicity.max.is_within_bound(instance_count);
-                within_bound.then(|| ())?;
-
-                // Check if the subject is an initiator
-                let is_initiator = model.get_initiator_subjects_ids(&initiation.initiated_transaction_id).contains(subject_id);
-                is_initiator.then(|| ())?;
-
-                // Check if the initiating C-fact condition matches
-                let fact_matches = self
-                    .get_c_p_world_item_by_fact(
-                        &parent_transaction_instance.id,
-                        &CPFact::CFact(initiation.initiating_c_fact.clone()),
-                    )
-                    .is_some();
-                fact_matches.then(|| initiated_transaction)
-            })
-            .collect();
+    ) -> Result<Vec<&'a Transaction>, ExecutionError> {
+        let parent_transaction = model.find_transaction(&parent_transaction_instance.transaction_id)
+            .ok_or_else(|| ExecutionError::UnknownTransaction(parent_transaction_instance.transaction_id.clone()))?;
+
+        let mut res: Vec<&Transaction> = Vec::new();
+        for initiation in &parent_transaction.initiations {
+            let Some(initiated_transaction) = model.find_transaction(&initiation.initiated_transaction_id) else { continue };
+            // Check if the multiplicity constraint is met
+            let instance_count = self.get_instances_of_transaction(&initiated_transaction.id, &Some(parent_transaction_instance.id.clone()))?.len();
+            let within_bound = initiation.multiplicity.max.is_within_bound(instance_count);
+
+            // Check if the subject is an initiator
+            let is_initiator = model.get_initiator_subjects_ids(&initiation.initiated_transaction_id).contains(subject_id);
+
+            // Check if the initiating C-fact condition matches
+            let fact_matches = self.is_fact_reached(
+                &parent_transaction_instance.id,
+                &CPFact::CFact(initiation.initiating_c_fact.clone()),
+            );
+            if within_bound && is_initiator && fact_matches {
+                res.push(initiated_transaction);
+            }
+        }
         res.sort();
         res.dedup();
-        res
+        Ok(res)
+    }
+
+    /// The instance whose subtransactions the wait links of the next act of the transaction instance refer to: the
+    /// instance itself, or the instance initiating it while it has not been requested yet.
+    pub fn impediments_context(&self, transaction_instance: &TransactionInstance) -> Option<TransactionInstanceId> {
+        match self.get_state(&transaction_instance.id) {
+            None => transaction_instance.parent_transaction_instance_id.clone(),
+            Some(_) => Some(transaction_instance.id.clone()),
+        }
     }
 
     pub fn get_act_impediments(&self, model: &Model, transaction: &Transaction, parent_transaction_instance_id_o: &Option<TransactionInstanceId>, act: &CPAct) -> Option<Vec<String>> {
@@ -287,8 +859,17 @@ impl Execution {
         } else {
             let mut res: Vec<String> = Vec::new();
             for imp in &impediments {
-                let impeding_transaction = model.get_transaction(&imp.impeding_transaction_id);
-                let impeding_transaction_instances = self.get_instances_of_transaction(&impeding_transaction.id, parent_transaction_instance_id_o);
+                let Some(impeding_transaction) = model.find_transaction(&imp.impeding_transaction_id) else {
+                    res.push(format!("Impeding transaction {} does not exist", imp.impeding_transaction_id));
+                    continue;
+                };
+                let impeding_transaction_instances = match self.get_instances_of_transaction(&impeding_transaction.id, parent_transaction_instance_id_o) {
+                    Ok(impeding_transaction_instances) => impeding_transaction_instances,
+                    Err(e) => {
+                        res.push(e.to_string());
+                        continue;
+                    }
+                };
                 // println!("imp_transaction: {}, imp_transaction_instances with parent {:?}: {:?}", impeding_transaction.t_id, parent_transaction_instance_id_o, impeding_transaction_instances);
                 if impeding_transaction_instances.is_empty() {
                     // We must now check if the instance is actually required (e.g. if min multiplicity of initiation is > 0) but if yes, report the impeding transaction
@@ -299,7 +880,7 @@ impl Execution {
                     }
                 } else { // There are impeding transaction instances, check if they reached the required fact
                     for t_i in impeding_transaction_instances {
-                        if self.get_c_p_world_item_by_fact(&t_i.id, &CPFact::CFact(imp.impeding_c_fact.clone())).is_none() {
+                        if !self.is_fact_reached(&t_i.id, &CPFact::CFact(imp.impeding_c_fact.clone())) {
                             res.push(format!("Waiting for transaction instance {} being fact {}", t_i.id.to_string(), imp.impeding_c_fact));
                         }
                     }
@@ -0,0 +1,139 @@
+//! The C/P world of an execution as an event log for process mining tools (e.g. ProM, pm4py): IEEE XES or a flat
+//! CSV. A case (XES trace) is a tree of transaction instances: a root instance with all its subtransactions. The
+//! activity of an event is the fact with the transaction, e.g. `T01 Promised`.
+
+use std::fmt::Write;
+use chrono::SecondsFormat;
+use crate::execution::{CPWorldItem, Execution, TransactionInstanceId};
+use crate::model::Model;
+use super::{root_transaction_instance, xml_escape};
+
+struct LogEvent<'a> {
+    item: &'a CPWorldItem,
+    activity: String,
+    transaction: String,
+    product_instance: &'a str,
+    parent_id: Option<&'a TransactionInstanceId>,
+    addressee: Option<&'a str>,
+}
+
+// The events grouped by case, the cases in the order they started, the events of a case in the order they happened
+fn cases<'a>(model: &'a Model, execution: &'a Execution) -> Vec<(&'a TransactionInstanceId, Vec<LogEvent<'a>>)> {
+    let mut cases: Vec<(&TransactionInstanceId, Vec<LogEvent<'_>>)> = Vec::new();
+    for item in &execution.c_p_world {
+        let Some(transaction_instance) = execution.find_transaction_instance(item.get_transaction_instance_id()) else { continue };
+        let transaction = model.find_transaction(&transaction_instance.transaction_id).map(|t| t.t_id.clone()).unwrap_or_default();
+        let case_id = root_transaction_instance(execution, &transaction_instance.id);
+        let event = LogEvent {
+            item,
+            activity: format!("{} {}", transaction, item.to_fact()),
+            transaction,
+            product_instance: &transaction_instance.product_instance,
+            parent_id: transaction_instance.parent_transaction_instance_id.as_ref(),
+            addressee: match item {
+                CPWorldItem::CWorldItem(c) => Some(&c.addressee.name),
+                CPWorldItem::PWorldItem(_) => None,
+            },
+        };
+        match cases.iter_mut().find(|(id, _)| *id == case_id) {
+            Some((_, events)) => events.push(event),
+            None => cases.push((case_id, vec![event])),
+        }
+    }
+    for (_, events) in &mut cases {
+        events.sort_by_key(|event| *event.item.get_timestamp());
+    }
+    cases
+}
+
+fn timestamp(item: &CPWorldItem) -> String {
+    item.get_timestamp().to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+/// IEEE XES (1849-2016) log of the execution.
+pub fn to_xes(model: &Model, execution: &Execution) -> String {
+    let mut xes = String::new();
+    let string = |xes: &mut String, indent: &str, key: &str, value: &str| {
+        let _ = writeln!(xes, "{}<string key=\"{}\" value=\"{}\"/>", indent, key, xml_escape(value));
+    };
+    let _ = writeln!(xes, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    let _ = writeln!(xes, r#"<log xes.version="1849-2016" xes.features="" xmlns="http://www.xes-standard.org/">"#);
+    let _ = writeln!(xes, r#"  <extension name="Concept" prefix="concept" uri="http://www.xes-standard.org/concept.xesext"/>"#);
+    let _ = writeln!(xes, r#"  <extension name="Time" prefix="time" uri="http://www.xes-standard.org/time.xesext"/>"#);
+    let _ = writeln!(xes, r#"  <extension name="Organizational" prefix="org" uri="http://www.xes-standard.org/org.xesext"/>"#);
+    let _ = writeln!(xes, r#"  <global scope="trace">"#);
+    let _ = writeln!(xes, r#"    <string key="concept:name" value="__INVALID__"/>"#);
+    let _ = writeln!(xes, r#"  </global>"#);
+    let _ = writeln!(xes, r#"  <global scope="event">"#);
+    let _ = writeln!(xes, r#"    <string key="concept:name" value="__INVALID__"/>"#);
+    let _ = writeln!(xes, r#"    <date key="time:timestamp" value="1970-01-01T00:00:00.000Z"/>"#);
+    let _ = writeln!(xes, r#"  </global>"#);
+    let _ = writeln!(xes, r#"  <classifier name="Activity" keys="concept:name"/>"#);
+    let _ = writeln!(xes, r#"  <classifier name="Activity and performer" keys="concept:name org:resource"/>"#);
+    string(&mut xes, "  ", "concept:name", &model.name);
+    for (case_id, events) in cases(model, execution) {
+        let _ = writeln!(xes, "  <trace>");
+        string(&mut xes, "    ", "concept:name", &case_id.to_string());
+        if let Some(root) = events.iter().find(|event| event.item.get_transaction_instance_id() == case_id) {
+            string(&mut xes, "    ", "transaction", &root.transaction);
+            string(&mut xes, "    ", "productInstance", root.product_instance);
+        }
+        for event in &events {
+            let _ = writeln!(xes, "    <event>");
+            string(&mut xes, "      ", "concept:name", &event.activity);
+            let _ = writeln!(xes, "      <date key=\"time:timestamp\" value=\"{}\"/>", timestamp(event.item));
+            string(&mut xes, "      ", "org:resource", &event.item.get_performer().name);
+            if let Some(addressee) = event.addressee {
+                string(&mut xes, "      ", "addressee", addressee);
+            }
+            string(&mut xes, "      ", "transaction", &event.transaction);
+            string(&mut xes, "      ", "transactionInstance", &event.item.get_transaction_instance_id().to_string());
+            if let Some(parent_id) = event.parent_id {
+                string(&mut xes, "      ", "parentTransactionInstance", &parent_id.to_string());
+            }
+            string(&mut xes, "      ", "productInstance", event.product_instance);
+            let _ = writeln!(xes, "      <boolean key=\"tacit\" value=\"{}\"/>", event.item.is_tacit());
+            let _ = writeln!(xes, "    </event>");
+        }
+        let _ = writeln!(xes, "  </trace>");
+    }
+    let _ = writeln!(xes, "</log>");
+    xes
+}
+
+const CSV_HEADER: [&str; 11] = ["case_id", "activity", "timestamp", "performer", "addressee", "transaction", "fact",
+    "transaction_instance_id", "parent_transaction_instance_id", "product_instance", "tacit"];
+
+/// The same log as [`to_xes`] as CSV, one event per row.
+pub fn to_csv(model: &Model, execution: &Execution) -> String {
+    let mut csv = String::new();
+    let _ = writeln!(csv, "{}", CSV_HEADER.join(","));
+    for (case_id, events) in cases(model, execution) {
+        for event in events {
+            let row = [
+                case_id.to_string(),
+                event.activity.clone(),
+                timestamp(event.item),
+                event.item.get_performer().name.clone(),
+                event.addressee.unwrap_or_default().to_string(),
+                event.transaction.clone(),
+                event.item.to_fact().to_string(),
+                event.item.get_transaction_instance_id().to_string(),
+                event.parent_id.map(|id| id.to_string()).unwrap_or_default(),
+                event.product_instance.to_string(),
+                event.item.is_tacit().to_string(),
+            ];
+            let row: Vec<String> = row.iter().map(|value| csv_value(value)).collect();
+            let _ = writeln!(csv, "{}", row.join(","));
+        }
+    }
+    csv
+}
+
+fn csv_value(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
@@ -0,0 +1,153 @@
+//! Export to formats of other tools: diagrams of the model, e.g. for course slides, and event logs of executions for
+//! process mining and sequence diagrams of them. Each diagram of the model can be written as Graphviz DOT, to be laid
+//! out by Graphviz, and as a standalone SVG laid out by DEMOsim itself.
+
+pub mod ocd;
+pub mod psd;
+pub mod event_log;
+pub mod sequence;
+
+use std::collections::HashSet;
+use std::fmt::Write;
+use crate::execution::{Execution, TransactionInstanceId};
+use crate::model::{Model, Transaction};
+
+/// A DOT identifier or label: quoted, with quotes, backslashes and line breaks escaped.
+pub(crate) fn dot_string(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n"))
+}
+
+/// A Mermaid label: quoted, with quotes, `#` (which starts an entity code) and markup as entity codes, line breaks as
+/// spaces.
+pub(crate) fn mermaid_string(s: &str) -> String {
+    let escaped: String = s.chars()
+        .map(|c| match c {
+            '"' => "#quot;".to_string(),
+            '#' => "#35;".to_string(),
+            '<' => "#lt;".to_string(),
+            '>' => "#gt;".to_string(),
+            '\n' => " ".to_string(),
+            c => c.to_string(),
+        })
+        .collect();
+    format!("\"{}\"", escaped)
+}
+
+/// Text or an attribute value in XML, with line breaks as character references, which attribute values would lose.
+pub(crate) fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;").replace('\n', "&#10;")
+}
+
+/// The transactions in the depth-first order of the tree of initiations, with their depth in it. A transaction
+/// initiated from more than one transaction appears under the first one, those initiated only in cycles are roots.
+pub(crate) fn initiation_tree(model: &Model) -> Vec<(&Transaction, usize)> {
+    let is_initiated = |transaction: &Transaction| model.transactions.iter().any(|t| t.initiations.iter().any(|i| i.initiated_transaction_id == transaction.id));
+    let mut tree = Vec::new();
+    let mut visited = HashSet::new();
+    let roots: Vec<&Transaction> = model.transactions.iter().filter(|t| !is_initiated(t)).chain(model.transactions.iter()).collect();
+    for root in roots {
+        let mut stack = vec![(root, 0)];
+        while let Some((transaction, depth)) = stack.pop() {
+            if !visited.insert(&transaction.id) {
+                continue;
+            }
+            tree.push((transaction, depth));
+            for initiation in transaction.initiations.iter().rev() {
+                if let Some(child) = model.find_transaction(&initiation.initiated_transaction_id) {
+                    stack.push((child, depth + 1));
+                }
+            }
+        }
+    }
+    tree
+}
+
+/// The transaction instance started on its own whose subtree the instance is in.
+pub(crate) fn root_transaction_instance<'a>(execution: &'a Execution, mut transaction_instance_id: &'a TransactionInstanceId) -> &'a TransactionInstanceId {
+    while let Some(parent_id) = execution.find_transaction_instance(transaction_instance_id).and_then(|t_i| t_i.parent_transaction_instance_id.as_ref()) {
+        transaction_instance_id = parent_id;
+    }
+    transaction_instance_id
+}
+
+pub(crate) const FONT_SIZE: f32 = 12.0;
+
+/// Rough width of a text in the default font, SVG has no way to measure it.
+pub(crate) fn text_width(text: &str, font_size: f32) -> f32 {
+    text.chars().count() as f32 * font_size * 0.6
+}
+
+/// A standalone SVG document, drawn shape by shape; later shapes are drawn over the earlier ones.
+pub(crate) struct Svg {
+    width: f32,
+    height: f32,
+    body: String,
+}
+
+impl Svg {
+    pub(crate) fn new(width: f32, height: f32) -> Self {
+        Self { width, height, body: String::new() }
+    }
+
+    pub(crate) fn line(&mut self, (x1, y1): (f32, f32), (x2, y2): (f32, f32), dashed: bool) {
+        let dash = if dashed { r#" stroke-dasharray="6 4""# } else { "" };
+        let _ = writeln!(self.body, r#"  <line x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}" stroke="black"{}/>"#, x1, y1, x2, y2, dash);
+    }
+
+    pub(crate) fn rect(&mut self, (x, y): (f32, f32), (width, height): (f32, f32), fill: &str) {
+        let _ = writeln!(self.body, r#"  <rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" fill="{}" stroke="black"/>"#, x, y, width, height, fill);
+    }
+
+    pub(crate) fn circle(&mut self, (cx, cy): (f32, f32), r: f32, fill: &str) {
+        let _ = writeln!(self.body, r#"  <circle cx="{:.1}" cy="{:.1}" r="{:.1}" fill="{}" stroke="black"/>"#, cx, cy, r, fill);
+    }
+
+    pub(crate) fn polygon(&mut self, points: &[(f32, f32)], fill: &str) {
+        let points: Vec<String> = points.iter().map(|(x, y)| format!("{:.1},{:.1}", x, y)).collect();
+        let _ = writeln!(self.body, r#"  <polygon points="{}" fill="{}" stroke="black"/>"#, points.join(" "), fill);
+    }
+
+    /// A line ending with an arrowhead, both ends shortened by `trim` (e.g. the radii of the shapes they connect).
+    pub(crate) fn arrow(&mut self, (x1, y1): (f32, f32), (x2, y2): (f32, f32), trim: f32, dashed: bool) {
+        let length = ((x2 - x1).powi(2) + (y2 - y1).powi(2)).sqrt();
+        if length <= 2.0 * trim {
+            return;
+        }
+        let (ux, uy) = ((x2 - x1) / length, (y2 - y1) / length);
+        let (start, end) = ((x1 + ux * trim, y1 + uy * trim), (x2 - ux * trim, y2 - uy * trim));
+        self.line(start, end, dashed);
+        let back = (end.0 - ux * 9.0, end.1 - uy * 9.0);
+        self.polygon(&[end, (back.0 - uy * 4.0, back.1 + ux * 4.0), (back.0 + uy * 4.0, back.1 - ux * 4.0)], "black");
+    }
+
+    /// Text centred horizontally at `x`, with its baseline at `y`.
+    pub(crate) fn text(&mut self, (x, y): (f32, f32), text: &str, font_size: f32) {
+        self.anchored_text((x, y), text, font_size, "middle");
+    }
+
+    /// Text starting at `x`, with its baseline at `y`.
+    pub(crate) fn left_text(&mut self, (x, y): (f32, f32), text: &str, font_size: f32) {
+        self.anchored_text((x, y), text, font_size, "start");
+    }
+
+    fn anchored_text(&mut self, (x, y): (f32, f32), text: &str, font_size: f32, anchor: &str) {
+        let _ = writeln!(self.body, r#"  <text x="{:.1}" y="{:.1}" font-size="{}" text-anchor="{}">{}</text>"#, x, y, font_size, anchor, xml_escape(text));
+    }
+
+    pub(crate) fn finish(self) -> String {
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w:.0}\" height=\"{h:.0}\" viewBox=\"0 0 {w:.0} {h:.0}\" font-family=\"Helvetica, Arial, sans-serif\">\n  <rect width=\"100%\" height=\"100%\" fill=\"white\"/>\n{}</svg>\n",
+            self.body, w = self.width, h = self.height,
+        )
+    }
+}
+
+/// The point where the line from the centre of a box towards `to` leaves the box.
+pub(crate) fn box_border_point((cx, cy): (f32, f32), (half_width, half_height): (f32, f32), (to_x, to_y): (f32, f32)) -> (f32, f32) {
+    let (dx, dy) = (to_x - cx, to_y - cy);
+    if dx == 0.0 && dy == 0.0 {
+        return (cx, cy);
+    }
+    let t = (half_width / dx.abs()).min(half_height / dy.abs());
+    (cx + t * dx, cy + t * dy)
+}
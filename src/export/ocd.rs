@@ -0,0 +1,168 @@
+//! The Organization Construction Diagram: actor roles (boxes, grey for the environmental ones executing no transaction)
+//! connected to the transactions (diamonds in disks) they initiate by initiator links and to those they execute by
+//! executor links, ending with a small square at the executor.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use crate::model::{ActorRoleId, Model, TransactionId};
+use super::{box_border_point, dot_string, initiation_tree, text_width, Svg, FONT_SIZE};
+
+const ENVIRONMENT_FILL: &str = "#dddddd";
+const PRODUCTION_FILL: &str = "#f4c7c3"; // the diamond
+pub(crate) const ROLE_HEIGHT: f32 = 40.0;
+pub(crate) const ROLE_MIN_WIDTH: f32 = 120.0;
+const COLUMN_WIDTH: f32 = 280.0;
+const ROW_HEIGHT: f32 = 100.0;
+const MARGIN: f32 = 40.0;
+pub(crate) const TRANSACTION_RADIUS: f32 = 22.0;
+
+/// Graphviz DOT of the OCD. Graphviz has no diamond-in-disk shape, so transactions are disks with a diamond in
+/// their label.
+pub fn ocd_to_dot(model: &Model) -> String {
+    let mut dot = String::new();
+    let _ = writeln!(dot, "digraph {} {{", dot_string(&format!("{} OCD", model.name)));
+    let _ = writeln!(dot, "  rankdir=LR;");
+    let _ = writeln!(dot, "  node [fontname=\"Helvetica\", fontsize=12];");
+    let _ = writeln!(dot, "  edge [arrowhead=none];");
+    for (index, actor_role) in model.actor_roles.iter().enumerate() {
+        let fill = if is_environmental(model, &actor_role.id) { ENVIRONMENT_FILL } else { "white" };
+        let _ = writeln!(dot, "  A{} [shape=box, style=filled, fillcolor=\"{}\", label={}];", index, fill, dot_string(&actor_role.name));
+    }
+    for (index, transaction) in model.transactions.iter().enumerate() {
+        let _ = writeln!(dot, "  T{} [shape=circle, style=filled, fillcolor=\"{}\", label={}, xlabel={}];",
+            index, PRODUCTION_FILL, dot_string(&format!("◇\n{}", transaction.t_id)), dot_string(&transaction.name));
+    }
+    let actor_role_node = |actor_role_id: &ActorRoleId| model.actor_roles.iter().position(|ar| ar.id == *actor_role_id).map(|index| format!("A{}", index));
+    for (index, transaction) in model.transactions.iter().enumerate() {
+        if let Some(initiator) = actor_role_node(&transaction.initiator_id) {
+            let _ = writeln!(dot, "  {} -> T{}; // initiator link", initiator, index);
+        }
+        if let Some(executor) = actor_role_node(&transaction.executor_id) {
+            let _ = writeln!(dot, "  T{} -> {} [arrowhead=box]; // executor link", index, executor);
+        }
+    }
+    let _ = writeln!(dot, "}}");
+    dot
+}
+
+/// Standalone SVG of the OCD, as placed in the OCD editor. Shapes not placed there are laid out automatically:
+/// actor roles in columns by their depth in the tree of initiations, transactions between their initiator and
+/// executor.
+pub fn ocd_to_svg(model: &Model) -> String {
+    let layout = Layout::new(model);
+    let mut svg = Svg::new(layout.width, layout.height);
+    let role_width = |name: &str| (text_width(name, FONT_SIZE) + 20.0).max(ROLE_MIN_WIDTH);
+
+    // Links first, the shapes cover their ends
+    let mut executor_squares = Vec::new();
+    for transaction in &model.transactions {
+        let Some(&t_center) = layout.transactions.get(&transaction.id) else { continue };
+        if let Some(&center) = layout.actor_roles.get(&transaction.initiator_id) {
+            svg.line(center, t_center, false);
+        }
+        if let Some((&center, actor_role)) = layout.actor_roles.get(&transaction.executor_id).zip(model.find_actor_role(&transaction.executor_id)) {
+            svg.line(t_center, center, false);
+            executor_squares.push(box_border_point(center, (role_width(&actor_role.name) / 2.0, ROLE_HEIGHT / 2.0), t_center));
+        }
+    }
+    for actor_role in &model.actor_roles {
+        let Some(&(x, y)) = layout.actor_roles.get(&actor_role.id) else { continue };
+        let width = role_width(&actor_role.name);
+        let fill = if is_environmental(model, &actor_role.id) { ENVIRONMENT_FILL } else { "white" };
+        svg.rect((x - width / 2.0, y - ROLE_HEIGHT / 2.0), (width, ROLE_HEIGHT), fill);
+        svg.text((x, y + FONT_SIZE / 3.0), &actor_role.name, FONT_SIZE);
+    }
+    for (x, y) in executor_squares {
+        svg.rect((x - 4.0, y - 4.0), (8.0, 8.0), "black");
+    }
+    for transaction in &model.transactions {
+        let Some(&(x, y)) = layout.transactions.get(&transaction.id) else { continue };
+        svg.circle((x, y), TRANSACTION_RADIUS, "white");
+        let r = TRANSACTION_RADIUS;
+        svg.polygon(&[(x, y - r), (x + r, y), (x, y + r), (x - r, y)], PRODUCTION_FILL);
+        svg.text((x, y + FONT_SIZE / 3.0), &transaction.t_id, FONT_SIZE - 1.0);
+        svg.text((x, y + r + FONT_SIZE + 2.0), &transaction.name, FONT_SIZE - 1.0);
+    }
+    svg.finish()
+}
+
+// Environmental actor roles only initiate transactions of the organisation
+pub(crate) fn is_environmental(model: &Model, actor_role_id: &ActorRoleId) -> bool {
+    !model.transactions.iter().any(|t| t.executor_id == *actor_role_id)
+}
+
+/// Centres of the shapes: where they are placed in the OCD editor, the others laid out automatically.
+pub(crate) struct Layout {
+    pub(crate) actor_roles: HashMap<ActorRoleId, (f32, f32)>,
+    pub(crate) transactions: HashMap<TransactionId, (f32, f32)>,
+    pub(crate) width: f32,
+    pub(crate) height: f32,
+}
+
+impl Layout {
+    pub(crate) fn new(model: &Model) -> Self {
+        let tree = initiation_tree(model);
+        // An executor is in the column right of the transaction it executes, the environment in the first one
+        let mut columns: HashMap<ActorRoleId, usize> = HashMap::new();
+        let mut rows: Vec<Vec<ActorRoleId>> = Vec::new();
+        let mut place = |actor_role_id: &ActorRoleId, column: usize| {
+            if !columns.contains_key(actor_role_id) && model.find_actor_role(actor_role_id).is_some() {
+                columns.insert(actor_role_id.clone(), column);
+                if rows.len() <= column {
+                    rows.resize(column + 1, Vec::new());
+                }
+                rows[column].push(actor_role_id.clone());
+            }
+        };
+        for (transaction, depth) in &tree {
+            place(&transaction.executor_id, depth + 1);
+        }
+        for (transaction, _) in &tree {
+            place(&transaction.initiator_id, 0);
+        }
+        for actor_role in &model.actor_roles {
+            place(&actor_role.id, 0);
+        }
+
+        let column_x = |column: usize| MARGIN + ROLE_MIN_WIDTH / 2.0 + column as f32 * COLUMN_WIDTH;
+        let row_y = |row: usize| MARGIN + ROLE_HEIGHT / 2.0 + row as f32 * ROW_HEIGHT;
+        let mut actor_roles = HashMap::new();
+        for (column, column_rows) in rows.iter().enumerate() {
+            for (row, actor_role_id) in column_rows.iter().enumerate() {
+                actor_roles.insert(actor_role_id.clone(), (column_x(column), row_y(row)));
+            }
+        }
+        for (actor_role_id, position) in &model.ocd_layout.actor_roles {
+            if let Some(center) = actor_roles.get_mut(actor_role_id) {
+                *center = (position.x, position.y);
+            }
+        }
+        // Between the initiator and the executor, on the level of the executor; transactions of the same executor
+        // are moved apart
+        let mut transactions = HashMap::new();
+        let mut executed_count: HashMap<&ActorRoleId, usize> = HashMap::new();
+        for transaction in &model.transactions {
+            let executor = actor_roles.get(&transaction.executor_id).copied();
+            let initiator = actor_roles.get(&transaction.initiator_id).copied();
+            let (x, y) = match (initiator, executor) {
+                (Some((ix, _)), Some((ex, ey))) if ix != ex => ((ix + ex) / 2.0, ey),
+                (_, Some((ex, ey))) | (Some((ex, ey)), None) => (ex - COLUMN_WIDTH / 2.0, ey),
+                (None, None) => (column_x(0) + COLUMN_WIDTH / 2.0, row_y(0)),
+            };
+            let count = executed_count.entry(&transaction.executor_id).or_insert(0);
+            let position = model.ocd_layout.transactions.get(&transaction.id).map_or((x, y + *count as f32 * ROW_HEIGHT / 2.0), |p| (p.x, p.y));
+            transactions.insert(transaction.id.clone(), position);
+            *count += 1;
+        }
+
+        let max_rows = rows.iter().map(|column_rows| column_rows.len()).max().unwrap_or(0).max(1);
+        let max_x = actor_roles.values().chain(transactions.values()).map(|(x, _)| *x).fold(0.0, f32::max);
+        let max_y = actor_roles.values().chain(transactions.values()).map(|(_, y)| *y).fold(row_y(max_rows - 1), f32::max);
+        Self {
+            actor_roles,
+            transactions,
+            width: max_x + ROLE_MIN_WIDTH / 2.0 + MARGIN + 60.0, // room for names longer than the minimal box
+            height: max_y + ROLE_HEIGHT / 2.0 + MARGIN + FONT_SIZE * 2.0,
+        }
+    }
+}
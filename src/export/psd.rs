@@ -0,0 +1,224 @@
+//! The Process Structure Diagram: the process steps of each transaction (its C-acts with their C-facts and the
+//! execution), the response links given by the initiations, labelled with their multiplicity, and the dashed wait
+//! links given by the impediments. A step is shown by its act, a link from a step starts at its fact.
+//!
+//! Each transaction shows the steps of the basic transaction pattern and any other steps its links refer to.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use crate::model::{all_acts, CAct, CPAct, Model, Transaction, TransactionId};
+use super::{dot_string, initiation_tree, mermaid_string, Svg, FONT_SIZE};
+
+pub(crate) const STEP_RADIUS: f32 = 16.0;
+const STEP_WIDTH: f32 = 70.0;
+const LANE_HEIGHT: f32 = 90.0;
+pub(crate) const LABEL_WIDTH: f32 = 180.0;
+pub(crate) const MARGIN: f32 = 40.0;
+const P_STEP_FILL: &str = "#f4c7c3";
+
+fn basic_steps() -> Vec<CPAct> {
+    use CAct::*;
+    vec![CPAct::CAct(Request), CPAct::CAct(Promise), CPAct::PAct, CPAct::CAct(Declare), CPAct::CAct(Accept)]
+}
+
+pub(crate) enum LinkKind {
+    Response(String), // the multiplicity
+    Wait,
+}
+
+// A link from the fact of a step to the act of a step
+pub(crate) struct Link<'a> {
+    pub(crate) from: (&'a TransactionId, CPAct),
+    pub(crate) to: (&'a TransactionId, CPAct),
+    pub(crate) kind: LinkKind,
+}
+
+fn links(model: &Model) -> Vec<Link<'_>> {
+    let mut links = Vec::new();
+    for transaction in &model.transactions {
+        for initiation in &transaction.initiations {
+            links.push(Link {
+                from: (&transaction.id, CPAct::CAct(initiation.initiating_c_fact.to_act())),
+                to: (&initiation.initiated_transaction_id, CPAct::CAct(initiation.initiated_c_act.clone())),
+                kind: LinkKind::Response(initiation.multiplicity.to_string()),
+            });
+        }
+        for impediment in &transaction.impediments {
+            links.push(Link {
+                from: (&impediment.impeding_transaction_id, CPAct::CAct(impediment.impeding_c_fact.to_act())),
+                to: (&transaction.id, impediment.impeded_act.clone()),
+                kind: LinkKind::Wait,
+            });
+        }
+    }
+    links.retain(|link| model.find_transaction(link.from.0).is_some() && model.find_transaction(link.to.0).is_some());
+    links
+}
+
+// The steps shown for each transaction, in the order of the transaction pattern
+fn steps<'a>(model: &'a Model, links: &[Link<'a>]) -> HashMap<&'a TransactionId, Vec<CPAct>> {
+    let mut steps: HashMap<&TransactionId, Vec<CPAct>> = model.transactions.iter().map(|t| (&t.id, basic_steps())).collect();
+    for (t_id, act) in links.iter().flat_map(|link| [&link.from, &link.to]) {
+        let t_steps = steps.get_mut(t_id).unwrap();
+        if !t_steps.contains(act) {
+            t_steps.push(act.clone());
+        }
+    }
+    let order = all_acts();
+    for t_steps in steps.values_mut() {
+        t_steps.sort_by_key(|act| order.iter().position(|a| a == act));
+    }
+    steps
+}
+
+fn node_id(transaction_index: usize, act: &CPAct) -> String {
+    format!("T{}_{}", transaction_index, act.abbreviation().replace('-', "_"))
+}
+
+fn transaction_label(transaction: &Transaction) -> String {
+    format!("{} {}", transaction.t_id, transaction.name)
+}
+
+/// Graphviz DOT of the PSD, a cluster for each transaction.
+pub fn psd_to_dot(model: &Model) -> String {
+    let links = links(model);
+    let steps = steps(model, &links);
+    let index_of = |t_id: &TransactionId| model.transactions.iter().position(|t| t.id == *t_id).unwrap();
+    let mut dot = String::new();
+    let _ = writeln!(dot, "digraph {} {{", dot_string(&format!("{} PSD", model.name)));
+    let _ = writeln!(dot, "  rankdir=LR;");
+    let _ = writeln!(dot, "  node [fontname=\"Helvetica\", fontsize=11, width=0.45, fixedsize=true];");
+    let _ = writeln!(dot, "  edge [fontname=\"Helvetica\", fontsize=10];");
+    for (index, transaction) in model.transactions.iter().enumerate() {
+        let t_steps = &steps[&transaction.id];
+        let _ = writeln!(dot, "  subgraph cluster_{} {{", index);
+        let _ = writeln!(dot, "    label={};", dot_string(&transaction_label(transaction)));
+        for act in t_steps {
+            let shape = match act {
+                CPAct::PAct => format!("shape=diamond, style=filled, fillcolor=\"{}\"", P_STEP_FILL),
+                CPAct::CAct(_) => "shape=circle".to_string(),
+            };
+            let _ = writeln!(dot, "    {} [{}, label={}];", node_id(index, act), shape, dot_string(act.abbreviation()));
+        }
+        let sequence: Vec<String> = t_steps.iter().map(|act| node_id(index, act)).collect();
+        let _ = writeln!(dot, "    {};", sequence.join(" -> "));
+        let _ = writeln!(dot, "  }}");
+    }
+    for link in &links {
+        let (from, to) = (node_id(index_of(link.from.0), &link.from.1), node_id(index_of(link.to.0), &link.to.1));
+        match &link.kind {
+            LinkKind::Response(multiplicity) => { let _ = writeln!(dot, "  {} -> {} [label={}]; // response link", from, to, dot_string(multiplicity)); }
+            LinkKind::Wait => { let _ = writeln!(dot, "  {} -> {} [style=dashed]; // wait link", from, to); }
+        }
+    }
+    let _ = writeln!(dot, "}}");
+    dot
+}
+
+/// Mermaid flowchart of the PSD, a subgraph for each transaction.
+pub fn psd_to_mermaid(model: &Model) -> String {
+    let links = links(model);
+    let steps = steps(model, &links);
+    let index_of = |t_id: &TransactionId| model.transactions.iter().position(|t| t.id == *t_id).unwrap();
+    let mut mermaid = String::new();
+    let _ = writeln!(mermaid, "flowchart LR");
+    for (index, transaction) in model.transactions.iter().enumerate() {
+        let _ = writeln!(mermaid, "  subgraph T{} [{}]", index, mermaid_string(&transaction_label(transaction)));
+        let nodes: Vec<String> = steps[&transaction.id].iter()
+            .map(|act| match act {
+                CPAct::PAct => format!("{}{{{}}}", node_id(index, act), mermaid_string(act.abbreviation())),
+                CPAct::CAct(_) => format!("{}(({}))", node_id(index, act), mermaid_string(act.abbreviation())),
+            })
+            .collect();
+        let _ = writeln!(mermaid, "    {}", nodes.join(" --> "));
+        let _ = writeln!(mermaid, "  end");
+    }
+    for link in &links {
+        let (from, to) = (node_id(index_of(link.from.0), &link.from.1), node_id(index_of(link.to.0), &link.to.1));
+        match &link.kind {
+            LinkKind::Response(multiplicity) => { let _ = writeln!(mermaid, "  {} -->|{}| {}", from, mermaid_string(multiplicity), to); }
+            LinkKind::Wait => { let _ = writeln!(mermaid, "  {} -.-> {}", from, to); }
+        }
+    }
+    mermaid
+}
+
+/// Where the steps are drawn: each transaction has a lane of its steps, the lanes are in the order of the tree of
+/// initiations and each subtransaction starts under the step of its parent initiating it.
+pub(crate) struct PsdLayout<'a> {
+    pub(crate) links: Vec<Link<'a>>,
+    pub(crate) steps: HashMap<&'a TransactionId, Vec<CPAct>>,
+    lane_starts: HashMap<&'a TransactionId, (f32, f32)>, // the centre of the first step
+    pub(crate) width: f32,
+    pub(crate) height: f32,
+}
+
+impl<'a> PsdLayout<'a> {
+    pub(crate) fn new(model: &'a Model) -> Self {
+        let links = links(model);
+        let steps = steps(model, &links);
+        let mut layout = Self { links, steps, lane_starts: HashMap::new(), width: 0.0, height: 0.0 };
+        for (row, (transaction, _)) in initiation_tree(model).into_iter().enumerate() {
+            let y = MARGIN + LANE_HEIGHT / 2.0 + row as f32 * LANE_HEIGHT;
+            let parent_step = layout.links.iter()
+                .filter(|link| matches!(link.kind, LinkKind::Response(_)) && *link.to.0 == transaction.id)
+                .find(|link| layout.lane_starts.contains_key(link.from.0));
+            let x = match parent_step {
+                Some(link) => layout.step_center(link.from.0, &link.from.1).0,
+                None => MARGIN + LABEL_WIDTH + STEP_RADIUS,
+            };
+            layout.lane_starts.insert(&transaction.id, (x, y));
+        }
+        layout.width = layout.lane_starts.iter().map(|(t_id, (x, _))| x + (layout.steps[t_id].len() as f32 - 1.0) * STEP_WIDTH).fold(0.0, f32::max) + STEP_RADIUS + MARGIN;
+        layout.height = layout.lane_starts.values().map(|(_, y)| *y).fold(0.0, f32::max) + LANE_HEIGHT / 2.0 + MARGIN;
+        layout
+    }
+
+    pub(crate) fn lane_start(&self, transaction_id: &TransactionId) -> (f32, f32) {
+        self.lane_starts[transaction_id]
+    }
+
+    pub(crate) fn step_center(&self, transaction_id: &TransactionId, act: &CPAct) -> (f32, f32) {
+        let (x, y) = self.lane_starts[transaction_id];
+        let position = self.steps[transaction_id].iter().position(|a| a == act).unwrap_or(0);
+        (x + position as f32 * STEP_WIDTH, y)
+    }
+}
+
+/// Standalone SVG of the PSD, laid out as described at [`PsdLayout`].
+pub fn psd_to_svg(model: &Model) -> String {
+    let layout = PsdLayout::new(model);
+    let mut svg = Svg::new(layout.width, layout.height);
+    for transaction in &model.transactions {
+        let (x, y) = layout.lane_start(&transaction.id);
+        svg.left_text((MARGIN, y - 4.0), &transaction.t_id, FONT_SIZE);
+        svg.left_text((MARGIN, y + FONT_SIZE), &transaction.name, FONT_SIZE - 1.0);
+        let t_steps = &layout.steps[&transaction.id];
+        for (position, act) in t_steps.iter().enumerate() {
+            let center = (x + position as f32 * STEP_WIDTH, y);
+            if position + 1 < t_steps.len() {
+                svg.arrow(center, (center.0 + STEP_WIDTH, y), STEP_RADIUS, false);
+            }
+            match act {
+                CPAct::PAct => {
+                    let r = STEP_RADIUS;
+                    svg.polygon(&[(center.0, y - r), (center.0 + r, y), (center.0, y + r), (center.0 - r, y)], P_STEP_FILL);
+                }
+                CPAct::CAct(_) => svg.circle(center, STEP_RADIUS, "white"),
+            }
+            svg.text((center.0, y + FONT_SIZE / 3.0), act.abbreviation(), FONT_SIZE - 2.0);
+        }
+    }
+    for link in &layout.links {
+        let from = layout.step_center(link.from.0, &link.from.1);
+        let to = layout.step_center(link.to.0, &link.to.1);
+        match &link.kind {
+            LinkKind::Response(multiplicity) => {
+                svg.arrow(from, to, STEP_RADIUS, false);
+                svg.left_text(((from.0 + to.0) / 2.0 + 5.0, (from.1 + to.1) / 2.0), multiplicity, FONT_SIZE - 2.0);
+            }
+            LinkKind::Wait => svg.arrow(from, to, STEP_RADIUS, true),
+        }
+    }
+    svg.finish()
+}
@@ -0,0 +1,115 @@
+//! The sequence diagram of a tree of transaction instances: a root instance with all its subtransactions, as Mermaid
+//! or PlantUML. The subjects are the lifelines, the coordination facts are messages from the performer to the
+//! addressee (dashed if tacit) and the production facts are notes on the lifeline of the performer.
+
+use std::fmt::Write;
+use crate::execution::{CPWorldItem, Execution, TransactionInstanceId};
+use crate::model::{CFact, Model, Subject, SubjectId};
+use super::root_transaction_instance;
+
+// The C/P world items of the tree in the order they happened and the subjects in the order they first appear
+fn items_and_subjects<'a>(execution: &'a Execution, root_id: &TransactionInstanceId) -> (Vec<&'a CPWorldItem>, Vec<&'a Subject>) {
+    let mut items: Vec<&CPWorldItem> = execution.c_p_world.iter()
+        .filter(|item| root_transaction_instance(execution, item.get_transaction_instance_id()) == root_id)
+        .collect();
+    items.sort_by_key(|item| *item.get_timestamp());
+    let mut subjects: Vec<&Subject> = Vec::new();
+    for item in &items {
+        let addressee_o = match item {
+            CPWorldItem::CWorldItem(c) => Some(&c.addressee),
+            CPWorldItem::PWorldItem(_) => None,
+        };
+        for subject in std::iter::once(item.get_performer()).chain(addressee_o) {
+            if !subjects.iter().any(|s| s.id == subject.id) {
+                subjects.push(subject);
+            }
+        }
+    }
+    (items, subjects)
+}
+
+fn participant(subjects: &[&Subject], subject_id: &SubjectId) -> String {
+    format!("S{}", subjects.iter().position(|s| s.id == *subject_id).unwrap_or_default())
+}
+
+// The fact with its transaction, with the product instance on requests, e.g. `T01 Requested: membership`
+fn item_text(model: &Model, execution: &Execution, item: &CPWorldItem) -> String {
+    let Some(transaction_instance) = execution.find_transaction_instance(item.get_transaction_instance_id()) else { return item.to_fact().to_string() };
+    let t_id = model.find_transaction(&transaction_instance.transaction_id).map(|t| t.t_id.clone()).unwrap_or_default();
+    match item {
+        CPWorldItem::CWorldItem(c) if c.fact == CFact::Requested => format!("{} {}: {}", t_id, c.fact, transaction_instance.product_instance),
+        _ => format!("{} {}", t_id, item.to_fact()),
+    }
+}
+
+fn title(model: &Model, execution: &Execution, root_id: &TransactionInstanceId) -> String {
+    execution.find_transaction_instance(root_id)
+        .map(|t_i| {
+            let transaction = model.find_transaction(&t_i.transaction_id).map(|t| format!("{} {}", t.t_id, t.name)).unwrap_or_default();
+            format!("{}, {}", transaction, t_i.product_instance)
+        })
+        .unwrap_or_default()
+}
+
+// Mermaid ends a message at a semicolon or a line break, `#` starts an entity code and `<` markup
+fn mermaid_text(s: &str) -> String {
+    s.chars()
+        .map(|c| match c {
+            '#' => "#35;".to_string(),
+            ';' => "#59;".to_string(),
+            '<' => "#lt;".to_string(),
+            '>' => "#gt;".to_string(),
+            '\n' => " ".to_string(),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+/// Mermaid sequence diagram of the tree of the root transaction instance.
+pub fn sequence_to_mermaid(model: &Model, execution: &Execution, root_id: &TransactionInstanceId) -> String {
+    let (items, subjects) = items_and_subjects(execution, root_id);
+    let mut mermaid = String::new();
+    let _ = writeln!(mermaid, "sequenceDiagram");
+    let _ = writeln!(mermaid, "  title {}", mermaid_text(&title(model, execution, root_id)));
+    for (index, subject) in subjects.iter().enumerate() {
+        let _ = writeln!(mermaid, "  participant S{} as {}", index, mermaid_text(&subject.name));
+    }
+    for item in items {
+        let text = mermaid_text(&item_text(model, execution, item));
+        match item {
+            CPWorldItem::CWorldItem(c) => {
+                let arrow = if c.tacit { "-->>" } else { "->>" };
+                let _ = writeln!(mermaid, "  {}{}{}: {}", participant(&subjects, &c.performer.id), arrow, participant(&subjects, &c.addressee.id), text);
+            }
+            CPWorldItem::PWorldItem(p) => { let _ = writeln!(mermaid, "  Note over {}: {}", participant(&subjects, &p.performer.id), text); }
+        }
+    }
+    mermaid
+}
+
+fn plantuml_string(s: &str) -> String {
+    format!("\"{}\"", s.replace('"', "'").replace('\n', " "))
+}
+
+/// PlantUML sequence diagram of the tree of the root transaction instance.
+pub fn sequence_to_plantuml(model: &Model, execution: &Execution, root_id: &TransactionInstanceId) -> String {
+    let (items, subjects) = items_and_subjects(execution, root_id);
+    let mut plantuml = String::new();
+    let _ = writeln!(plantuml, "@startuml");
+    let _ = writeln!(plantuml, "title {}", title(model, execution, root_id).replace('\n', " "));
+    for (index, subject) in subjects.iter().enumerate() {
+        let _ = writeln!(plantuml, "participant {} as S{}", plantuml_string(&subject.name), index);
+    }
+    for item in items {
+        let text = item_text(model, execution, item).replace('\n', " ");
+        match item {
+            CPWorldItem::CWorldItem(c) => {
+                let arrow = if c.tacit { "-->" } else { "->" };
+                let _ = writeln!(plantuml, "{} {} {} : {}", participant(&subjects, &c.performer.id), arrow, participant(&subjects, &c.addressee.id), text);
+            }
+            CPWorldItem::PWorldItem(p) => { let _ = writeln!(plantuml, "note over {} : {}", participant(&subjects, &p.performer.id), text); }
+        }
+    }
+    let _ = writeln!(plantuml, "@enduml");
+    plantuml
+}
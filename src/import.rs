@@ -0,0 +1,332 @@
+//! Import of models written as spreadsheets (saved as CSV): the Transaction Product Table and a sheet of the links
+//! between the transactions. Sheets are recognised by their header row, the order of the columns does not matter and
+//! unknown columns are ignored. Values may be separated by commas or semicolons.
+//!
+//! The Transaction Product Table has the columns `transaction id`, `transaction name`, `product`, `initiator` and
+//! `executor` (the names of actor roles, created unless the model already has them).
+//!
+//! The links sheet has the columns `link` (`initiation` or `wait`), `from transaction`, `from fact`,
+//! `to transaction`, `to act` and optionally `multiplicity` (of initiations, `1..1` if empty). An initiation link
+//! says that the fact of the parent transaction initiates the act of the child one, a wait link that the act has to
+//! wait for the fact. Facts and acts are given by their names (e.g. `Promised`, `Request`, `Execute`) or their usual
+//! abbreviations (`rq`, `pm`, `ex`, `da`, `ac`, ...). A transaction is initiated at most once from the same
+//! transaction and never from itself; links already in the model are not added again.
+
+use crate::model::{all_c_acts, all_c_facts, ActorRole, ActorRoleId, CAct, CFact, CPAct, CPFact, Impediment, Initiation, Model, Multiplicity, Transaction, TransactionId};
+
+/// A problem with a row of a sheet. The row is skipped, the other ones are imported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RowError {
+    pub line: usize, // 1-based, the header being line 1
+    pub message: String,
+}
+
+impl std::fmt::Display for RowError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Line {}: {}", self.line, self.message)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sheet {
+    TransactionProductTable,
+    Links,
+}
+
+impl std::fmt::Display for Sheet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use Sheet::*;
+        match self {
+            TransactionProductTable => write!(f, "Transaction Product Table"),
+            Links => write!(f, "Links"),
+        }
+    }
+}
+
+impl Sheet {
+    /// Recognises the sheet by its header row.
+    pub fn detect(text: &str) -> Option<Sheet> {
+        let rows = parse_csv(text);
+        let (_, header) = rows.first()?;
+        let header = Header::new(header);
+        if header.column(LINK_COLUMN).is_some() {
+            Some(Sheet::Links)
+        } else if header.column(T_ID_COLUMN).is_some() {
+            Some(Sheet::TransactionProductTable)
+        } else {
+            None
+        }
+    }
+}
+
+/// What an import added to the model.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportSummary {
+    pub actor_roles: usize,
+    pub transactions: usize,
+    pub initiations: usize,
+    pub impediments: usize,
+    pub errors: Vec<RowError>,
+}
+
+impl std::fmt::Display for ImportSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} actor roles, {} transactions, {} initiations and {} wait links imported",
+            self.actor_roles, self.transactions, self.initiations, self.impediments)?;
+        if !self.errors.is_empty() {
+            write!(f, ", {} rows skipped", self.errors.len())?;
+        }
+        Ok(())
+    }
+}
+
+// Accepted names of the columns, compared without case, spaces and punctuation
+const T_ID_COLUMN: &[&str] = &["transactionid", "tid", "id"];
+const NAME_COLUMN: &[&str] = &["transactionname", "name", "transaction"];
+const PRODUCT_COLUMN: &[&str] = &["product", "productkind"];
+const INITIATOR_COLUMN: &[&str] = &["initiator", "initiatorrole", "initiatoractorrole"];
+const EXECUTOR_COLUMN: &[&str] = &["executor", "executorrole", "executoractorrole"];
+const LINK_COLUMN: &[&str] = &["link", "linktype"];
+const FROM_TRANSACTION_COLUMN: &[&str] = &["fromtransaction"];
+const FROM_FACT_COLUMN: &[&str] = &["fromfact"];
+const TO_TRANSACTION_COLUMN: &[&str] = &["totransaction"];
+const TO_ACT_COLUMN: &[&str] = &["toact"];
+const MULTIPLICITY_COLUMN: &[&str] = &["multiplicity"];
+
+fn normalised(s: &str) -> String {
+    s.chars().filter(|c| c.is_alphanumeric()).flat_map(|c| c.to_lowercase()).collect()
+}
+
+pub(crate) struct Header {
+    names: Vec<String>,
+}
+
+impl Header {
+    pub(crate) fn new(row: &[String]) -> Self {
+        Self { names: row.iter().map(|name| normalised(name)).collect() }
+    }
+
+    pub(crate) fn column(&self, names: &[&str]) -> Option<usize> {
+        names.iter().find_map(|name| self.names.iter().position(|n| n == name))
+    }
+
+    // The indices of the columns, or the error listing the missing ones
+    pub(crate) fn columns<const N: usize>(&self, columns: [&[&str]; N]) -> Result<[usize; N], RowError> {
+        let indices = columns.map(|names| self.column(names));
+        let missing: Vec<&str> = columns.iter().zip(indices).filter(|(_, index)| index.is_none()).map(|(names, _)| names[0]).collect();
+        if missing.is_empty() {
+            Ok(indices.map(|index| index.unwrap()))
+        } else {
+            Err(RowError { line: 1, message: format!("Missing columns: {}", missing.join(", ")) })
+        }
+    }
+}
+
+pub(crate) fn cell(row: &[String], index: usize) -> &str {
+    row.get(index).map(|value| value.trim()).unwrap_or("")
+}
+
+/// Adds the transactions of a Transaction Product Table to the model. Transactions whose id is already in the model
+/// are skipped.
+pub fn import_transaction_product_table(model: &mut Model, text: &str) -> ImportSummary {
+    let mut summary = ImportSummary::default();
+    let rows = parse_csv(text);
+    let Some((_, header)) = rows.first() else { return summary };
+    let [t_id_i, name_i, product_i, initiator_i, executor_i] = match Header::new(header).columns([T_ID_COLUMN, NAME_COLUMN, PRODUCT_COLUMN, INITIATOR_COLUMN, EXECUTOR_COLUMN]) {
+        Ok(indices) => indices,
+        Err(error) => {
+            summary.errors.push(error);
+            return summary;
+        }
+    };
+    for (line, row) in rows.iter().skip(1) {
+        let t_id = cell(row, t_id_i);
+        let (initiator, executor) = (cell(row, initiator_i), cell(row, executor_i));
+        let mut error = |message: String| summary.errors.push(RowError { line: *line, message });
+        if t_id.is_empty() {
+            error("Missing transaction id".to_string());
+            continue;
+        }
+        if find_transaction(model, t_id).is_some() {
+            error(format!("Transaction {} already exists", t_id));
+            continue;
+        }
+        if initiator.is_empty() || executor.is_empty() {
+            error(format!("Transaction {} is missing its initiator or executor", t_id));
+            continue;
+        }
+        let mut transaction = Transaction::new();
+        transaction.t_id = t_id.to_string();
+        transaction.name = cell(row, name_i).to_string();
+        transaction.product = cell(row, product_i).to_string();
+        transaction.initiator_id = actor_role_by_name(model, initiator, &mut summary);
+        transaction.executor_id = actor_role_by_name(model, executor, &mut summary);
+        model.transactions.push(transaction);
+        summary.transactions += 1;
+    }
+    summary
+}
+
+/// Adds the initiations and wait links of a links sheet to the transactions of the model, which have to exist.
+pub fn import_links(model: &mut Model, text: &str) -> ImportSummary {
+    let mut summary = ImportSummary::default();
+    let rows = parse_csv(text);
+    let Some((_, header)) = rows.first() else { return summary };
+    let header = Header::new(header);
+    let [link_i, from_t_i, from_fact_i, to_t_i, to_act_i] = match header.columns([LINK_COLUMN, FROM_TRANSACTION_COLUMN, FROM_FACT_COLUMN, TO_TRANSACTION_COLUMN, TO_ACT_COLUMN]) {
+        Ok(indices) => indices,
+        Err(error) => {
+            summary.errors.push(error);
+            return summary;
+        }
+    };
+    let multiplicity_i = header.column(MULTIPLICITY_COLUMN);
+    for (line, row) in rows.iter().skip(1) {
+        let multiplicity = multiplicity_i.map_or("", |index| cell(row, index));
+        match import_link(model, cell(row, link_i), [cell(row, from_t_i), cell(row, from_fact_i), cell(row, to_t_i), cell(row, to_act_i)], multiplicity) {
+            Ok(Link::Initiation) => summary.initiations += 1,
+            Ok(Link::Wait) => summary.impediments += 1,
+            Err(message) => summary.errors.push(RowError { line: *line, message }),
+        }
+    }
+    summary
+}
+
+enum Link {
+    Initiation,
+    Wait,
+}
+
+fn import_link(model: &mut Model, link: &str, [from_t_id, from_fact, to_t_id, to_act]: [&str; 4], multiplicity: &str) -> Result<Link, String> {
+    let from_id = find_transaction(model, from_t_id).ok_or_else(|| format!("Unknown transaction \"{}\"", from_t_id))?;
+    let to_id = find_transaction(model, to_t_id).ok_or_else(|| format!("Unknown transaction \"{}\"", to_t_id))?;
+    let c_fact = parse_c_fact(from_fact).ok_or_else(|| format!("Unknown C-fact \"{}\"", from_fact))?;
+    let act = parse_act(to_act).ok_or_else(|| format!("Unknown act \"{}\"", to_act))?;
+    match normalised(link).as_str() {
+        "initiation" | "initiates" | "i" => {
+            let CPAct::CAct(c_act) = act else { return Err("A transaction cannot be initiated by its execution".to_string()) };
+            if from_id == to_id {
+                return Err(format!("Transaction {} cannot initiate itself", from_t_id));
+            }
+            let multiplicity: Multiplicity = if multiplicity.is_empty() {
+                Multiplicity::default()
+            } else {
+                multiplicity.parse().map_err(|_| format!("Invalid multiplicity \"{}\", expected e.g. 1..1 or 0..*", multiplicity))?
+            };
+            let parent = model.transactions.iter_mut().find(|t| t.id == from_id).unwrap();
+            if parent.initiations.iter().any(|initiation| initiation.initiated_transaction_id == to_id) {
+                return Err(format!("Transaction {} already initiates {}", from_t_id, to_t_id));
+            }
+            parent.initiations.push(Initiation {
+                initiating_c_fact: c_fact,
+                initiated_transaction_id: to_id,
+                initiated_c_act: c_act,
+                multiplicity_tmp_str: multiplicity.to_string(),
+                multiplicity,
+            });
+            Ok(Link::Initiation)
+        }
+        "wait" | "waits" | "w" => {
+            let impeded = model.transactions.iter_mut().find(|t| t.id == to_id).unwrap();
+            let impediment = Impediment {
+                impeded_act: act,
+                impeding_transaction_id: from_id,
+                impeding_c_fact: c_fact,
+            };
+            if impeded.impediments.contains(&impediment) {
+                return Err(format!("{} in transaction {} already waits for {} {}", impediment.impeded_act, to_t_id, from_t_id, impediment.impeding_c_fact));
+            }
+            impeded.impediments.push(impediment);
+            Ok(Link::Wait)
+        }
+        _ => Err(format!("Unknown link \"{}\", expected initiation or wait", link)),
+    }
+}
+
+fn find_transaction(model: &Model, t_id: &str) -> Option<TransactionId> {
+    model.transactions.iter().find(|t| t.t_id.eq_ignore_ascii_case(t_id)).map(|t| t.id.clone())
+}
+
+fn actor_role_by_name(model: &mut Model, name: &str, summary: &mut ImportSummary) -> ActorRoleId {
+    if let Some(actor_role) = model.actor_roles.iter().find(|ar| ar.name.trim().eq_ignore_ascii_case(name)) {
+        return actor_role.id.clone();
+    }
+    let actor_role = ActorRole { name: name.to_string(), ..Default::default() };
+    let id = actor_role.id.clone();
+    model.actor_roles.push(actor_role);
+    summary.actor_roles += 1;
+    id
+}
+
+fn parse_c_act(s: &str) -> Option<CAct> {
+    let s = normalised(s);
+    all_c_acts().into_iter().find(|c_act| normalised(&c_act.to_string()) == s || normalised(c_act.abbreviation()) == s)
+}
+
+fn parse_act(s: &str) -> Option<CPAct> {
+    match normalised(s).as_str() {
+        "execute" | "execution" | "ex" | "pact" => Some(CPAct::PAct),
+        _ => parse_c_act(s).map(CPAct::CAct),
+    }
+}
+
+fn parse_c_fact(s: &str) -> Option<CFact> {
+    let n = normalised(s);
+    all_c_facts().into_iter().find(|c_fact| normalised(&c_fact.to_string()) == n)
+        .or_else(|| parse_c_act(s).map(|c_act| c_act.to_fact()))
+}
+
+/// A C-fact or the P-fact, by its name or the abbreviation of its act.
+pub(crate) fn parse_fact(s: &str) -> Option<CPFact> {
+    match normalised(s).as_str() {
+        "executed" | "execute" | "ex" | "pfact" => Some(CPFact::PFact),
+        _ => parse_c_fact(s).map(CPFact::CFact),
+    }
+}
+
+// The non-empty rows with the lines they start on. Quoted values may contain separators, doubled quotes and line
+// breaks. The separator is a semicolon if the header has more of them than commas (spreadsheets in some locales).
+pub(crate) fn parse_csv(text: &str) -> Vec<(usize, Vec<String>)> {
+    let text = text.trim_start_matches('\u{feff}');
+    let header = text.lines().next().unwrap_or("");
+    let separator = if header.matches(';').count() > header.matches(',').count() { ';' } else { ',' };
+
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut value = String::new();
+    let (mut line, mut row_line) = (1, 1);
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                value.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            '\n' if !quoted => {
+                row.push(std::mem::take(&mut value));
+                if row.iter().any(|value| !value.trim().is_empty()) {
+                    rows.push((row_line, std::mem::take(&mut row)));
+                }
+                row.clear();
+                line += 1;
+                row_line = line;
+            }
+            // Windows line breaks within quoted values become plain ones, as between the rows
+            '\r' if !quoted || chars.peek() == Some(&'\n') => {}
+            c if c == separator && !quoted => row.push(std::mem::take(&mut value)),
+            c => {
+                if c == '\n' {
+                    line += 1;
+                }
+                value.push(c);
+            }
+        }
+    }
+    row.push(value);
+    if row.iter().any(|value| !value.trim().is_empty()) {
+        rows.push((row_line, row));
+    }
+    rows
+}
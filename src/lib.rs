@@ -1,9 +1,22 @@
 #![warn(clippy::all, rust_2018_idioms)]
 
+#[cfg(feature = "gui")]
 mod app;
 pub mod model;
 pub mod execution;
+pub mod clock;
+pub mod automation;
+pub mod batch;
+pub mod validation;
+pub mod persistence;
+pub mod import;
+pub mod export;
+pub mod conformance;
+pub mod engine;
+#[cfg(feature = "gui")]
 pub mod components;
+#[cfg(feature = "gui")]
 pub mod windows;
 
+#[cfg(feature = "gui")]
 pub use app::DemosimApp;
@@ -5,7 +5,7 @@ use uuid::Uuid;
 use strum_macros::EnumIter;
 use strum::IntoEnumIterator;
 
-#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq, Eq, Hash, EnumIter)]
+#[derive(serde::Deserialize, serde::Serialize, schemars::JsonSchema, Debug, Clone, PartialEq, Eq, Hash, EnumIter)]
 pub enum CAct {
     Request,
     Promise,
@@ -13,11 +13,14 @@ pub enum CAct {
     Declare,
     Accept,
     Reject,
-    // RevokeRequest,
-    // RevokePromise,
-    // RevokeDecline,
-    // RevokeAccept,
-    // RevokeReject,
+    Quit,
+    Stop,
+    RevokeRequest,
+    RevokePromise,
+    RevokeDecline,
+    RevokeDeclare,
+    RevokeAccept,
+    RevokeReject,
 }
 
 impl CAct {
@@ -30,13 +33,61 @@ impl CAct {
             Declare => CFact::Declared,
             Accept => CFact::Accepted,
             Reject => CFact::Rejected,
-            // RevokeRequest => CFact::RequestRevoked,
-            // RevokePromise => CFact::PromiseRevoked,
-            // RevokeDecline => CFact::DeclineRevoked,
-            // RevokeAccept => CFact::AcceptRevoked,
-            // RevokeReject => CFact::RejectRevoked,
+            Quit => CFact::Quitted,
+            Stop => CFact::Stopped,
+            RevokeRequest => CFact::RequestRevoked,
+            RevokePromise => CFact::PromiseRevoked,
+            RevokeDecline => CFact::DeclineRevoked,
+            RevokeDeclare => CFact::DeclareRevoked,
+            RevokeAccept => CFact::AcceptRevoked,
+            RevokeReject => CFact::RejectRevoked,
         }
     }
+
+    pub fn is_revocation(&self) -> bool {
+        use CAct::*;
+        matches!(self, RevokeRequest | RevokePromise | RevokeDecline | RevokeDeclare | RevokeAccept | RevokeReject)
+    }
+
+    pub fn performer_role(&self) -> TransactionRole {
+        use CAct::*;
+        match self {
+            Request | Accept | Reject | Quit | RevokeRequest | RevokeAccept | RevokeReject => TransactionRole::Initiator,
+            Promise | Decline | Declare | Stop | RevokePromise | RevokeDecline | RevokeDeclare => TransactionRole::Executor,
+        }
+    }
+
+    /// The usual abbreviation in DEMO diagrams, e.g. `rq` for a request.
+    pub fn abbreviation(&self) -> &'static str {
+        use CAct::*;
+        match self {
+            Request => "rq",
+            Promise => "pm",
+            Decline => "dc",
+            Declare => "da",
+            Accept => "ac",
+            Reject => "rj",
+            Quit => "qt",
+            Stop => "st",
+            RevokeRequest => "rv-rq",
+            RevokePromise => "rv-pm",
+            RevokeDecline => "rv-dc",
+            RevokeDeclare => "rv-da",
+            RevokeAccept => "rv-ac",
+            RevokeReject => "rv-rj",
+        }
+    }
+
+    /// Only acts leading the transaction towards its completion may be performed tacitly; this also guarantees
+    /// that automatically generated acts cannot loop forever.
+    pub fn can_be_tacit(&self) -> bool {
+        use CAct::*;
+        matches!(self, Promise | Declare | Accept)
+    }
+}
+
+pub fn all_tacit_candidate_acts() -> Vec<CAct> {
+    CAct::iter().filter(|c_act| c_act.can_be_tacit()).collect()
 }
 
 impl Default for CAct {
@@ -55,11 +106,14 @@ impl std::fmt::Display for CAct {
             Declare => write!(f, "Declare"),
             Accept => write!(f, "Accept"),
             Reject => write!(f, "Reject"),
-            // RevokeRequest => write!(f, "Revoke Request"),
-            // RevokePromise => write!(f, "Revoke Promise"),
-            // RevokeDecline => write!(f, "Revoke Decline"),
-            // RevokeAccept => write!(f, "Revoke Accept"),
-            // RevokeReject => write!(f, "Revoke Reject"),
+            Quit => write!(f, "Quit"),
+            Stop => write!(f, "Stop"),
+            RevokeRequest => write!(f, "Revoke Request"),
+            RevokePromise => write!(f, "Revoke Promise"),
+            RevokeDecline => write!(f, "Revoke Decline"),
+            RevokeDeclare => write!(f, "Revoke Declare"),
+            RevokeAccept => write!(f, "Revoke Accept"),
+            RevokeReject => write!(f, "Revoke Reject"),
         }
     }
 }
@@ -74,7 +128,7 @@ pub fn all_acts() -> Vec<CPAct> {
     acts
 }
 
-#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq, Eq)]
+#[derive(serde::Deserialize, serde::Serialize, schemars::JsonSchema, Debug, Clone, PartialEq, Eq, Hash)]
 pub enum CPAct {
     CAct(CAct),
     PAct,
@@ -97,10 +151,47 @@ impl CPAct {
             CPAct::PAct => CPFact::PFact,
         }
     }
+
+    pub fn is_revocation(&self) -> bool {
+        match self {
+            CPAct::CAct(c_act) => c_act.is_revocation(),
+            CPAct::PAct => false,
+        }
+    }
+
+    pub fn performer_role(&self) -> TransactionRole {
+        match self {
+            CPAct::CAct(c_act) => c_act.performer_role(),
+            CPAct::PAct => TransactionRole::Executor,
+        }
+    }
+
+    pub fn abbreviation(&self) -> &'static str {
+        match self {
+            CPAct::CAct(c_act) => c_act.abbreviation(),
+            CPAct::PAct => "ex",
+        }
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, schemars::JsonSchema, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TransactionRole {
+    Initiator,
+    Executor,
+}
+
+impl std::fmt::Display for TransactionRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use TransactionRole::*;
+        match self {
+            Initiator => write!(f, "Initiator"),
+            Executor => write!(f, "Executor"),
+        }
+    }
 }
 
 
-#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq, Eq, Hash, EnumIter)]
+#[derive(serde::Deserialize, serde::Serialize, schemars::JsonSchema, Debug, Clone, PartialEq, Eq, Hash, EnumIter)]
 pub enum CFact {
     Requested,
     Promised,
@@ -108,11 +199,63 @@ pub enum CFact {
     Declared,
     Accepted,
     Rejected,
-    // RequestRevoked,
-    // PromiseRevoked,
-    // DeclineRevoked,
-    // AcceptRevoked,
-    // RejectRevoked,
+    Quitted,
+    Stopped,
+    RequestRevoked,
+    PromiseRevoked,
+    DeclineRevoked,
+    DeclareRevoked,
+    AcceptRevoked,
+    RejectRevoked,
+}
+
+impl CFact {
+    pub fn to_act(&self) -> CAct {
+        use CFact::*;
+        match self {
+            Requested => CAct::Request,
+            Promised => CAct::Promise,
+            Declined => CAct::Decline,
+            Declared => CAct::Declare,
+            Accepted => CAct::Accept,
+            Rejected => CAct::Reject,
+            Quitted => CAct::Quit,
+            Stopped => CAct::Stop,
+            RequestRevoked => CAct::RevokeRequest,
+            PromiseRevoked => CAct::RevokePromise,
+            DeclineRevoked => CAct::RevokeDecline,
+            DeclareRevoked => CAct::RevokeDeclare,
+            AcceptRevoked => CAct::RevokeAccept,
+            RejectRevoked => CAct::RevokeReject,
+        }
+    }
+
+    /// Facts after which the transaction cannot continue (except by revoking the acceptance).
+    pub fn is_terminal(&self) -> bool {
+        use CFact::*;
+        matches!(self, Accepted | Quitted | Stopped | RequestRevoked)
+    }
+
+    pub fn is_revocation(&self) -> bool {
+        use CFact::*;
+        matches!(self, RequestRevoked | PromiseRevoked | DeclineRevoked | DeclareRevoked | AcceptRevoked | RejectRevoked)
+    }
+
+    /// The fact a revocation rolls the transaction back to. `None` for regular facts and for a revoked request,
+    /// which cancels the transaction altogether.
+    ///
+    /// As in the revocation patterns of DEMO, a revoked declaration goes back to the promise, not to the production:
+    /// the product is only stated by the declaration, so revoking it withdraws the P-fact too and the executor has to
+    /// produce again. A revoked acceptance or rejection goes back to the declaration and keeps the product.
+    pub fn rollback_target(&self) -> Option<CFact> {
+        use CFact::*;
+        match self {
+            PromiseRevoked | DeclineRevoked => Some(Requested),
+            DeclareRevoked => Some(Promised),
+            AcceptRevoked | RejectRevoked => Some(Declared),
+            _ => None,
+        }
+    }
 }
 
 impl Default for CFact {
@@ -131,11 +274,14 @@ impl std::fmt::Display for CFact {
             Declared => write!(f, "Declared"),
             Accepted => write!(f, "Accepted"),
             Rejected => write!(f, "Rejected"),
-            // RequestRevoked => write!(f, "Request Revoked"),
-            // PromiseRevoked => write!(f, "Promise Revoked"),
-            // DeclineRevoked => write!(f, "Decline Revoked"),
-            // AcceptRevoked => write!(f, "Accept Revoked"),
-            // RejectRevoked => write!(f, "Reject Revoked"),
+            Quitted => write!(f, "Quitted"),
+            Stopped => write!(f, "Stopped"),
+            RequestRevoked => write!(f, "Request Revoked"),
+            PromiseRevoked => write!(f, "Promise Revoked"),
+            DeclineRevoked => write!(f, "Decline Revoked"),
+            DeclareRevoked => write!(f, "Declare Revoked"),
+            AcceptRevoked => write!(f, "Accept Revoked"),
+            RejectRevoked => write!(f, "Reject Revoked"),
         }
     }
 }
@@ -144,27 +290,49 @@ pub fn all_c_facts() -> Vec<CFact> {
     CFact::iter().collect()
 }
 
-#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq, Eq)]
+#[derive(serde::Deserialize, serde::Serialize, schemars::JsonSchema, Debug, Clone, PartialEq, Eq, Hash)]
 pub enum CPFact {
     CFact(CFact),
     PFact,
 }
 
 impl CPFact {
+    pub fn to_act(&self) -> CPAct {
+        match self {
+            CPFact::CFact(c_fact) => CPAct::CAct(c_fact.to_act()),
+            CPFact::PFact => CPAct::PAct,
+        }
+    }
+
+    /// Acts that may follow this fact, including revocations. Each act is performed either by the initiator
+    /// or by the executor, see [`CPAct::performer_role`].
     pub fn next_acts(&self) -> Vec<CPAct> {
         use CFact::*;
         use CAct::*;
         match self {
-            CPFact::PFact => vec![CPAct::CAct(Declare)],
+            CPFact::PFact => vec![CPAct::CAct(Declare), CPAct::CAct(RevokePromise), CPAct::CAct(RevokeRequest)],
             CPFact::CFact(c_fact) => match c_fact {
-                Requested => vec![CPAct::CAct(Promise), CPAct::CAct(Decline)],
-                Promised => vec![CPAct::PAct],
-                Declared => vec![CPAct::CAct(Accept), CPAct::CAct(Reject)],
-                Rejected => vec![CPAct::CAct(Declare)],
-                _ => vec![],
+                Requested => vec![CPAct::CAct(Promise), CPAct::CAct(Decline), CPAct::CAct(RevokeRequest)],
+                Promised => vec![CPAct::PAct, CPAct::CAct(RevokePromise), CPAct::CAct(RevokeRequest)],
+                Declined => vec![CPAct::CAct(Request), CPAct::CAct(Quit), CPAct::CAct(RevokeDecline)],
+                Declared => vec![CPAct::CAct(Accept), CPAct::CAct(Reject), CPAct::CAct(RevokeDeclare), CPAct::CAct(RevokeRequest)],
+                Accepted => vec![CPAct::CAct(RevokeAccept)],
+                Rejected => vec![CPAct::CAct(Declare), CPAct::CAct(Stop), CPAct::CAct(RevokeReject), CPAct::CAct(RevokeRequest)],
+                Quitted | Stopped | RequestRevoked => vec![],
+                // After a revocation the transaction continues as from the state it was rolled back to
+                PromiseRevoked | DeclineRevoked | DeclareRevoked | AcceptRevoked | RejectRevoked =>
+                    CPFact::CFact(c_fact.rollback_target().unwrap()).next_acts(),
             },
         }
     }
+
+    /// The role that has to act next in the regular (non-revoking) course of the transaction, i.e. whose agenda
+    /// this fact belongs to.
+    pub fn agenda_role(&self) -> Option<TransactionRole> {
+        self.next_acts().iter()
+            .find(|act| !act.is_revocation())
+            .map(|act| act.performer_role())
+    }
 }
 
 impl std::fmt::Display for CPFact {
@@ -178,7 +346,7 @@ impl std::fmt::Display for CPFact {
 }
 
 
-#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(serde::Deserialize, serde::Serialize, schemars::JsonSchema, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct ActorRoleId(Uuid);
 
 impl std::fmt::Display for ActorRoleId {
@@ -188,7 +356,7 @@ impl std::fmt::Display for ActorRoleId {
 }
 
 
-#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(serde::Deserialize, serde::Serialize, schemars::JsonSchema, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct ActorRole {
     pub id: ActorRoleId,
     pub name: String,
@@ -203,7 +371,7 @@ impl Default for ActorRole {
     }
 }
 
-#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(serde::Deserialize, serde::Serialize, schemars::JsonSchema, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct TransactionId(Uuid);
 
 impl std::fmt::Display for TransactionId {
@@ -213,7 +381,7 @@ impl std::fmt::Display for TransactionId {
 }
 
 
-#[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
+#[derive(serde::Deserialize, serde::Serialize, schemars::JsonSchema, Debug, Clone)]
 pub enum MaxMultiplicity {
     Numeric(u8),
     Any,
@@ -249,7 +417,7 @@ impl MaxMultiplicity {
     }
 }
 
-#[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
+#[derive(serde::Deserialize, serde::Serialize, schemars::JsonSchema, Debug, Clone)]
 pub struct Multiplicity {
     pub min: u8,
     pub max: MaxMultiplicity,
@@ -310,7 +478,7 @@ impl std::fmt::Display for Multiplicity {
     }
 }
 
-#[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
+#[derive(serde::Deserialize, serde::Serialize, schemars::JsonSchema, Debug, Clone)]
 pub struct Initiation {
     pub initiating_c_fact: CFact,
     pub initiated_transaction_id: TransactionId,
@@ -320,14 +488,33 @@ pub struct Initiation {
     pub multiplicity_tmp_str: String, // This would be much better out of model (AppContext), but I lost all my hair...
 }
 
-#[derive(serde::Deserialize, serde::Serialize, PartialEq, Eq, Debug, Clone)]
+#[derive(serde::Deserialize, serde::Serialize, schemars::JsonSchema, PartialEq, Eq, Debug, Clone)]
 pub struct Impediment {
     pub impeded_act: CPAct,
     pub impeding_transaction_id: TransactionId,
     pub impeding_c_fact: CFact,
 }
 
-#[derive(serde::Deserialize, serde::Serialize, Debug, Clone)]
+/// Time within which an act is expected after the fact preceding it. A deadline without an act applies to all the
+/// acts of the transaction not having their own. When it expires, `on_expiry` may be performed automatically.
+#[derive(serde::Deserialize, serde::Serialize, schemars::JsonSchema, PartialEq, Eq, Debug, Clone)]
+pub struct Deadline {
+    pub act: Option<CPAct>,
+    pub within_minutes: i64,
+    pub on_expiry: Option<CAct>,
+}
+
+impl Default for Deadline {
+    fn default() -> Self {
+        Self {
+            act: None,
+            within_minutes: 24 * 60,
+            on_expiry: None,
+        }
+    }
+}
+
+#[derive(serde::Deserialize, serde::Serialize, schemars::JsonSchema, Debug, Clone)]
 pub struct Transaction {
     pub id: TransactionId,
     pub t_id: String,
@@ -337,6 +524,10 @@ pub struct Transaction {
     pub executor_id: ActorRoleId,
     pub initiations: Vec<Initiation>,
     pub impediments: Vec<Impediment>,
+    #[serde(default)]
+    pub tacit_acts: Vec<CAct>, // acts generated automatically by the engine instead of waiting for the performer
+    #[serde(default)]
+    pub deadlines: Vec<Deadline>,
 }
 
 impl PartialEq for Transaction {
@@ -375,12 +566,41 @@ impl Transaction {
             executor_id: ActorRole::default().id,
             initiations: Vec::default(),
             impediments: Vec::default(),
+            tacit_acts: Vec::default(),
+            deadlines: Vec::default(),
+        }
+    }
+
+    pub fn get_actor_role_id(&self, role: TransactionRole) -> &ActorRoleId {
+        match role {
+            TransactionRole::Initiator => &self.initiator_id,
+            TransactionRole::Executor => &self.executor_id,
+        }
+    }
+
+    /// The deadline for responding to the fact: the tightest one among those of the acts expected next, or the
+    /// transaction-wide one.
+    pub fn get_deadline_for_fact(&self, fact: &CPFact) -> Option<&Deadline> {
+        let expected_acts: Vec<CPAct> = fact.next_acts().into_iter().filter(|act| !act.is_revocation()).collect();
+        if expected_acts.is_empty() {
+            return None;
+        }
+        self.deadlines.iter()
+            .filter(|deadline| deadline.act.as_ref().is_some_and(|act| expected_acts.contains(act)))
+            .min_by_key(|deadline| deadline.within_minutes)
+            .or_else(|| self.deadlines.iter().find(|deadline| deadline.act.is_none()))
+    }
+
+    pub fn is_tacit(&self, act: &CPAct) -> bool {
+        match act {
+            CPAct::CAct(c_act) => c_act.can_be_tacit() && self.tacit_acts.contains(c_act),
+            CPAct::PAct => false,
         }
     }
 }
 
 
-#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(serde::Deserialize, serde::Serialize, schemars::JsonSchema, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct SubjectId(Uuid);
 
 impl std::fmt::Display for SubjectId {
@@ -390,7 +610,7 @@ impl std::fmt::Display for SubjectId {
 }
 
 
-#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(serde::Deserialize, serde::Serialize, schemars::JsonSchema, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Subject {
     pub id: SubjectId,
     pub name: String,
@@ -406,7 +626,7 @@ impl Default for Subject {
 }
 
 
-#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq, Eq, EnumIter)]
+#[derive(serde::Deserialize, serde::Serialize, schemars::JsonSchema, Debug, Clone, PartialEq, Eq, EnumIter)]
 pub enum AdtOption {
     Authorisation,
     Delegation,
@@ -442,12 +662,49 @@ pub fn all_adt_options() -> Vec<AdtOption> {
     AdtOption::iter().collect()
 }
 
-#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Default, PartialEq)]
+#[derive(serde::Deserialize, serde::Serialize, schemars::JsonSchema, Debug, Clone, Default, PartialEq)]
 pub struct Adt { // Authorisation Delegation Table
+    #[serde(with = "adt_mappings")]
+    #[schemars(with = "Vec<AdtEntry>")]
     pub mappings: HashMap<(ActorRoleId, SubjectId), AdtOption>
 }
 
+/// An entry of the ADT as stored in files: a map with tuple keys cannot be represented in JSON.
+#[derive(serde::Deserialize, serde::Serialize, schemars::JsonSchema)]
+struct AdtEntry {
+    actor_role_id: ActorRoleId,
+    subject_id: SubjectId,
+    option: AdtOption,
+}
+
+// The mappings are written as a list of entries, sorted so that saving a model twice gives the same file. Files of
+// format version 2 and older have a map instead, which is read as such and upgraded in `persistence`.
+mod adt_mappings {
+    use std::collections::HashMap;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use super::{ActorRoleId, AdtEntry, AdtOption, SubjectId};
+
+    type Mappings = HashMap<(ActorRoleId, SubjectId), AdtOption>;
+
+    pub fn serialize<S: Serializer>(mappings: &Mappings, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut entries: Vec<AdtEntry> = mappings.iter()
+            .map(|((actor_role_id, subject_id), option)| AdtEntry { actor_role_id: actor_role_id.clone(), subject_id: subject_id.clone(), option: option.clone() })
+            .collect();
+        entries.sort_by_key(|entry| (entry.actor_role_id.to_string(), entry.subject_id.to_string()));
+        entries.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Mappings, D::Error> {
+        let entries = Vec::<AdtEntry>::deserialize(deserializer)?;
+        Ok(entries.into_iter().map(|AdtEntry { actor_role_id, subject_id, option }| ((actor_role_id, subject_id), option)).collect())
+    }
+}
+
 impl Adt {
+    pub fn is_authorised(&self, ar_id: &ActorRoleId, subject_id: &SubjectId) -> bool {
+        self.mappings.contains_key(&(ar_id.clone(), subject_id.clone()))
+    }
+
     pub fn is_mapped(&self, ar_id: &ActorRoleId) -> bool {
         self.mappings.keys().find(|(ar_id1, _)| ar_id1 == ar_id).is_some()
     }
@@ -466,13 +723,95 @@ impl Adt {
 }
 
 
-#[derive(serde::Deserialize, serde::Serialize)]
+/// When a subject has the `fact` on its agenda (in the given transaction or in any), it performs the `act` with the
+/// given probability. One number is drawn per agenda item and the rules take their shares of the hundred in order,
+/// so e.g. "Promise 80 %" and "Decline 20 %" promise four requests in five and decline the rest. Whatever share
+/// is left over leaves the item to be handled by hand.
+#[derive(serde::Deserialize, serde::Serialize, schemars::JsonSchema, PartialEq, Eq, Debug, Clone)]
+pub struct PolicyRule {
+    pub transaction_id: Option<TransactionId>,
+    pub fact: CPFact,
+    pub act: CPAct,
+    pub probability_percent: u8,
+}
+
+impl Default for PolicyRule {
+    fn default() -> Self {
+        Self {
+            transaction_id: None,
+            fact: CPFact::CFact(CFact::Requested),
+            act: CPAct::CAct(CAct::Promise),
+            probability_percent: 100,
+        }
+    }
+}
+
+/// Behaviour of an automated subject. A subject that is not automated is played by hand.
+#[derive(serde::Deserialize, serde::Serialize, schemars::JsonSchema, PartialEq, Eq, Debug, Clone, Default)]
+pub struct Policy {
+    pub automated: bool,
+    pub rules: Vec<PolicyRule>,
+}
+
+impl Policy {
+    /// Always proceeds towards the completion of the transaction: promises, executes, declares and accepts.
+    pub fn cooperative() -> Self {
+        use CFact::*;
+        use CAct::*;
+        let rule = |fact: CPFact, act: CPAct| PolicyRule { transaction_id: None, fact, act, probability_percent: 100 };
+        Self {
+            automated: true,
+            rules: vec![
+                rule(CPFact::CFact(Requested), CPAct::CAct(Promise)),
+                rule(CPFact::CFact(Promised), CPAct::PAct),
+                rule(CPFact::PFact, CPAct::CAct(Declare)),
+                rule(CPFact::CFact(Declared), CPAct::CAct(Accept)),
+                rule(CPFact::CFact(Rejected), CPAct::CAct(Declare)),
+                rule(CPFact::CFact(Declined), CPAct::CAct(Quit)),
+            ],
+        }
+    }
+
+    /// Rules applicable to the fact on the agenda, in order. For a revocation, the rules of the fact the transaction
+    /// was rolled back to apply.
+    pub fn get_rules_for(&self, transaction_id: &TransactionId, fact: &CPFact) -> Vec<&PolicyRule> {
+        let fact = match fact {
+            CPFact::CFact(c_fact) => c_fact.rollback_target().map_or_else(|| fact.clone(), CPFact::CFact),
+            CPFact::PFact => CPFact::PFact,
+        };
+        self.rules.iter()
+            .filter(|rule| rule.fact == fact && (rule.transaction_id.is_none() || rule.transaction_id.as_ref() == Some(transaction_id)))
+            .collect()
+    }
+}
+
+/// A point of the OCD editor canvas.
+#[derive(serde::Deserialize, serde::Serialize, schemars::JsonSchema, PartialEq, Debug, Clone, Copy, Default)]
+pub struct Position {
+    pub x: f32,
+    pub y: f32,
+}
+
+/// Where the shapes of the OCD editor are placed; those not placed yet are laid out automatically.
+#[derive(serde::Deserialize, serde::Serialize, schemars::JsonSchema, PartialEq, Debug, Clone, Default)]
+pub struct OcdLayout {
+    #[serde(default)]
+    pub actor_roles: HashMap<ActorRoleId, Position>,
+    #[serde(default)]
+    pub transactions: HashMap<TransactionId, Position>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, schemars::JsonSchema, Clone)]
 pub struct Model {
     pub name: String,
     pub actor_roles: Vec<ActorRole>,
     pub transactions: Vec<Transaction>,
     pub subjects: Vec<Subject>,
     pub adt: Adt,
+    #[serde(default)]
+    pub policies: HashMap<SubjectId, Policy>,
+    #[serde(default)]
+    pub ocd_layout: OcdLayout,
 }
 
 impl Default for Model {
@@ -483,11 +822,29 @@ impl Default for Model {
             transactions: Vec::new(),
             subjects: Vec::new(),
             adt: Adt::default(),
+            policies: HashMap::new(),
+            ocd_layout: OcdLayout::default(),
         }
     }
 }
 
 impl Model {
+    pub fn is_automated(&self, s_id: &SubjectId) -> bool {
+        self.policies.get(s_id).is_some_and(|policy| policy.automated)
+    }
+
+    pub fn find_actor_role(&self, ar_id: &ActorRoleId) -> Option<&ActorRole> {
+        self.actor_roles.iter().find(|ar| ar.id == *ar_id)
+    }
+
+    pub fn find_transaction(&self, transaction_id: &TransactionId) -> Option<&Transaction> {
+        self.transactions.iter().find(|t| t.id == *transaction_id)
+    }
+
+    pub fn find_subject(&self, s_id: &SubjectId) -> Option<&Subject> {
+        self.subjects.iter().find(|s| s.id == *s_id)
+    }
+
     pub fn get_actor_role(&self, ar_id: &ActorRoleId) -> &ActorRole {
         self.actor_roles.iter().find(|ar| ar.id == *ar_id).unwrap()
     }
@@ -513,7 +870,7 @@ impl Model {
         if res.is_empty() {
             None
         } else {
-            if res.len() > 1 { eprintln!("Warning: multiple initiations found for transaction {}", transaction.t_id); }
+            // Multiple initiations are reported by `Model::validate`
             Some(res[0])
         }
     }
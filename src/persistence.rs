@@ -0,0 +1,316 @@
+//! Reading and writing of model (`.dms`) and session (`.dmss`) files, in RON or, for other tools, in JSON. Both
+//! formats carry the same documents, described by the JSON Schemas in the `schema` directory.
+
+use schemars::gen::SchemaSettings;
+use schemars::schema::{RootSchema, SchemaObject};
+use schemars::visit::{self, Visitor};
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use crate::execution::Execution;
+use crate::model::{Adt, Model, OcdLayout};
+
+/// Why a file could not be loaded. The position (1-based line and column) is known for syntax and type errors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadError {
+    pub message: String,
+    pub position: Option<(usize, usize)>,
+}
+
+impl std::fmt::Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.position {
+            Some((line, column)) => write!(f, "Line {}, column {}: {}", line, column, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+impl From<ron::error::SpannedError> for LoadError {
+    fn from(error: ron::error::SpannedError) -> Self {
+        Self {
+            message: error.code.to_string(),
+            position: Some((error.position.line, error.position.col)),
+        }
+    }
+}
+
+impl From<serde_json::Error> for LoadError {
+    fn from(error: serde_json::Error) -> Self {
+        let position = (error.line() > 0).then_some((error.line(), error.column()));
+        Self {
+            message: error.to_string(),
+            position,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Ron,
+    Json,
+}
+
+impl Format {
+    /// JSON for `.json` files, RON otherwise.
+    pub fn from_file_name(file_name: &str) -> Self {
+        if file_name.to_lowercase().ends_with(".json") { Format::Json } else { Format::Ron }
+    }
+
+    fn parse<T: DeserializeOwned>(&self, text: &str) -> Result<T, LoadError> {
+        match self {
+            Format::Ron => Ok(ron::from_str(text)?),
+            Format::Json => Ok(serde_json::from_str(text)?),
+        }
+    }
+
+    fn write<T: Serialize>(&self, document: &T) -> String {
+        match self {
+            Format::Ron => ron::ser::to_string_pretty(document, ron::ser::PrettyConfig::default()).expect("documents are always serialisable"),
+            Format::Json => serde_json::to_string_pretty(document).expect("documents are always serialisable"),
+        }
+    }
+}
+
+/// Version of the file format written by this version of DEMOsim. Files of older versions are upgraded on load by
+/// the chain of `upgrade_v*` functions, each turning a file of its version into one of the next version.
+///
+/// History of the format:
+/// 1. The bare model, without any header. Tacit acts, deadlines and policies were added to it later as optional
+///    fields, so such files are all read as version 1.
+/// 2. The model wrapped in a [`ModelFile`] starting with `format_version`; sessions were introduced.
+/// 3. The ADT is a list of entries instead of a map with tuple keys (not representable in JSON); JSON was introduced.
+///    The places of the shapes of the OCD editor were added later as an optional field.
+pub const FORMAT_VERSION: u32 = 3;
+
+// Just the version of a file. Files of version 1 have no header, so the version defaults to 1.
+#[derive(serde::Deserialize)]
+struct FormatHeader {
+    #[serde(default = "legacy_format_version")]
+    format_version: u32,
+}
+
+fn legacy_format_version() -> u32 {
+    1
+}
+
+fn read_format_version(text: &str, format: Format) -> Result<u32, LoadError> {
+    let header: FormatHeader = format.parse(text)?;
+    if header.format_version > FORMAT_VERSION {
+        return Err(LoadError {
+            message: format!("The file has format version {}, but this version of DEMOsim reads versions up to {}; please upgrade DEMOsim", header.format_version, FORMAT_VERSION),
+            position: None,
+        });
+    }
+    Ok(header.format_version)
+}
+
+/// A model file of the current version.
+#[derive(serde::Deserialize, serde::Serialize, schemars::JsonSchema)]
+pub struct ModelFile {
+    pub format_version: u32,
+    pub model: Model,
+}
+
+// The documents of format version 2 as they were read, frozen: only what differs from the next version is defined
+// here, the rest is shared with it. A version 1 file is the bare model of version 2.
+mod v2 {
+    use std::collections::HashMap;
+    use crate::execution::Execution;
+    use crate::model::{ActorRole, ActorRoleId, AdtOption, Policy, Subject, SubjectId, Transaction};
+
+    #[derive(serde::Deserialize)]
+    pub(super) struct Model {
+        pub name: String,
+        pub actor_roles: Vec<ActorRole>,
+        pub transactions: Vec<Transaction>,
+        pub subjects: Vec<Subject>,
+        pub adt: Adt,
+        #[serde(default)]
+        pub policies: HashMap<SubjectId, Policy>,
+    }
+
+    // A map with tuple keys, which cannot be represented in JSON
+    #[derive(serde::Deserialize)]
+    pub(super) struct Adt {
+        pub mappings: HashMap<(ActorRoleId, SubjectId), AdtOption>,
+    }
+
+    #[derive(serde::Deserialize)]
+    pub(super) struct ModelFile {
+        pub model: Model,
+    }
+
+    #[derive(serde::Deserialize)]
+    pub(super) struct SessionFile {
+        pub model: Model,
+        pub execution: Execution,
+    }
+}
+
+fn upgrade_v1(model: v2::Model) -> v2::ModelFile {
+    v2::ModelFile { model }
+}
+
+// The ADT becomes a list of entries; the places of the shapes of the OCD editor are new
+fn upgrade_v2_model(model: v2::Model) -> Model {
+    let v2::Model { name, actor_roles, transactions, subjects, adt, policies } = model;
+    Model { name, actor_roles, transactions, subjects, adt: Adt { mappings: adt.mappings }, policies, ocd_layout: OcdLayout::default() }
+}
+
+fn upgrade_v2(model_file: v2::ModelFile) -> ModelFile {
+    ModelFile { format_version: 3, model: upgrade_v2_model(model_file.model) }
+}
+
+fn upgrade_v2_session(session_file: v2::SessionFile) -> SessionFile {
+    SessionFile { format_version: 3, model: upgrade_v2_model(session_file.model), execution: session_file.execution }
+}
+
+/// A model read from a file, possibly upgraded from an older format version and repaired (see [`Model::repair`]).
+pub struct LoadedModel {
+    pub model: Model,
+    pub format_version: u32, // of the file as it was read
+    pub repairs: Vec<String>,
+}
+
+/// Parses a model file of any format version.
+pub fn read_model(text: &str, format: Format) -> Result<LoadedModel, LoadError> {
+    let format_version = read_format_version(text, format)?;
+    let ModelFile { mut model, .. } = match format_version {
+        1 => upgrade_v2(upgrade_v1(format.parse(text)?)),
+        2 => upgrade_v2(format.parse(text)?),
+        _ => format.parse(text)?,
+    };
+    let repairs = model.repair();
+    Ok(LoadedModel { model, format_version, repairs })
+}
+
+/// Writes a model file of the current format version.
+pub fn write_model(model: &Model, format: Format) -> String {
+    #[derive(serde::Serialize)]
+    struct ModelFileRef<'a> {
+        format_version: u32,
+        model: &'a Model,
+    }
+    format.write(&ModelFileRef { format_version: FORMAT_VERSION, model })
+}
+
+/// Parses a `.dms` model file of any format version.
+pub fn model_from_ron(text: &str) -> Result<LoadedModel, LoadError> {
+    read_model(text, Format::Ron)
+}
+
+pub fn model_to_ron(model: &Model) -> String {
+    write_model(model, Format::Ron)
+}
+
+pub fn model_from_json(text: &str) -> Result<LoadedModel, LoadError> {
+    read_model(text, Format::Json)
+}
+
+pub fn model_to_json(model: &Model) -> String {
+    write_model(model, Format::Json)
+}
+
+/// A model together with its (possibly half-played) execution.
+pub struct Session {
+    pub model: Model,
+    pub execution: Execution,
+}
+
+/// A session file of the current version. Sessions were introduced in format version 2, the first ones had no
+/// `format_version` yet.
+#[derive(serde::Deserialize, serde::Serialize, schemars::JsonSchema)]
+pub struct SessionFile {
+    pub format_version: u32,
+    pub model: Model,
+    pub execution: Execution,
+}
+
+#[derive(serde::Serialize)]
+struct SessionFileRef<'a> {
+    format_version: u32,
+    model: &'a Model,
+    execution: &'a Execution,
+}
+
+/// A session read from a file, possibly repaired.
+pub struct LoadedSession {
+    pub session: Session,
+    pub repairs: Vec<String>,
+}
+
+pub fn read_session(text: &str, format: Format) -> Result<LoadedSession, LoadError> {
+    let SessionFile { model, execution, .. } = match read_format_version(text, format)? {
+        1 | 2 => upgrade_v2_session(format.parse(text)?),
+        _ => format.parse(text)?,
+    };
+    let mut session = Session { model, execution };
+    let mut repairs = session.model.repair();
+    let dropped = session.execution.restore(&session.model);
+    if dropped > 0 {
+        repairs.push(format!("Dropped {} steps of the execution that are not valid for the model", dropped));
+    }
+    Ok(LoadedSession { session, repairs })
+}
+
+pub fn write_session(session: &Session, format: Format) -> String {
+    format.write(&SessionFileRef { format_version: FORMAT_VERSION, model: &session.model, execution: &session.execution })
+}
+
+/// Parses a `.dmss` session file.
+pub fn session_from_ron(text: &str) -> Result<LoadedSession, LoadError> {
+    read_session(text, Format::Ron)
+}
+
+pub fn session_to_ron(session: &Session) -> String {
+    write_session(session, Format::Ron)
+}
+
+pub fn session_from_json(text: &str) -> Result<LoadedSession, LoadError> {
+    read_session(text, Format::Json)
+}
+
+pub fn session_to_json(session: &Session) -> String {
+    write_session(session, Format::Json)
+}
+
+/// JSON Schema of model files.
+pub fn model_json_schema() -> RootSchema {
+    json_schema::<ModelFile>()
+}
+
+/// JSON Schema of session files.
+pub fn session_json_schema() -> RootSchema {
+    json_schema::<SessionFile>()
+}
+
+fn json_schema<T: JsonSchema>() -> RootSchema {
+    SchemaSettings::default().with_visitor(WithoutTimeDefaults).into_generator().into_root_schema_for::<T>()
+}
+
+// The clock defaults to the current time, which would make the schemas differ every time they are generated
+#[derive(Debug, Clone)]
+struct WithoutTimeDefaults;
+
+impl Visitor for WithoutTimeDefaults {
+    fn visit_schema_object(&mut self, schema: &mut SchemaObject) {
+        if let Some(metadata) = schema.metadata.as_mut() {
+            if metadata.default.as_ref().is_some_and(contains_date_time) {
+                metadata.default = None;
+            }
+        }
+        visit::visit_schema_object(self, schema);
+    }
+}
+
+fn contains_date_time(value: &serde_json::Value) -> bool {
+    match value {
+        serde_json::Value::String(s) => chrono::DateTime::parse_from_rfc3339(s).is_ok(),
+        serde_json::Value::Array(values) => values.iter().any(contains_date_time),
+        serde_json::Value::Object(values) => values.values().any(contains_date_time),
+        _ => false,
+    }
+}
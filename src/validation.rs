@@ -0,0 +1,208 @@
+//! Consistency checks of a [`Model`]. The editors allow the model to be inconsistent while it is being built, so
+//! problems are reported as diagnostics instead of being prevented.
+
+use std::collections::HashSet;
+use crate::model::{ActorRoleId, MaxMultiplicity, Model, SubjectId, TransactionId};
+
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,   // the simulation of the affected part fails or behaves arbitrarily
+    Warning, // the model is probably incomplete
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use Severity::*;
+        let s = match self {
+            Error => "Error",
+            Warning => "Warning",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// The part of the model a diagnostic is about.
+#[derive(serde::Deserialize, serde::Serialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ModelEntity {
+    ActorRole(ActorRoleId),
+    Transaction(TransactionId),
+    Subject(SubjectId),
+    Adt,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub entity: ModelEntity,
+    pub message: String,
+}
+
+impl Model {
+    /// Checks the consistency of the model. Errors come first, otherwise in the order of the model.
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        let mut report = |severity: Severity, entity: ModelEntity, message: String| {
+            diagnostics.push(Diagnostic { severity, entity, message });
+        };
+        let actor_role_name = |ar_id: &ActorRoleId| self.find_actor_role(ar_id).map(|ar| ar.name.clone());
+        let transaction_name = |t_id: &TransactionId| self.find_transaction(t_id).map(|t| t.t_id.clone()).unwrap_or("?".to_string());
+
+        for actor_role in &self.actor_roles {
+            let entity = || ModelEntity::ActorRole(actor_role.id.clone());
+            if actor_role.name.trim().is_empty() {
+                report(Severity::Warning, entity(), "Actor role has no name".to_string());
+            }
+            if !self.subjects.iter().any(|s| self.adt.is_authorised(&actor_role.id, &s.id)) {
+                report(Severity::Warning, entity(), format!("No subject is authorised for actor role {} in the ADT", actor_role.name));
+            }
+            let executed: Vec<String> = self.transactions.iter().filter(|t| t.executor_id == actor_role.id).map(|t| t.t_id.clone()).collect();
+            if executed.len() > 1 {
+                report(Severity::Warning, entity(), format!("Actor role {} executes more than one transaction: {}", actor_role.name, executed.join(", ")));
+            }
+            if executed.is_empty() && !self.transactions.iter().any(|t| t.initiator_id == actor_role.id) {
+                report(Severity::Warning, entity(), format!("Actor role {} takes part in no transaction", actor_role.name));
+            }
+        }
+
+        let mut seen_t_ids = HashSet::new();
+        for transaction in &self.transactions {
+            let entity = || ModelEntity::Transaction(transaction.id.clone());
+            if transaction.t_id.trim().is_empty() {
+                report(Severity::Warning, entity(), format!("Transaction {} has no id", transaction.name));
+            } else if !seen_t_ids.insert(transaction.t_id.clone()) {
+                report(Severity::Warning, entity(), format!("Transaction id {} is used more than once", transaction.t_id));
+            }
+            if actor_role_name(&transaction.initiator_id).is_none() {
+                report(Severity::Error, entity(), format!("The initiator of transaction {} is not an existing actor role", transaction.t_id));
+            }
+            if actor_role_name(&transaction.executor_id).is_none() {
+                report(Severity::Error, entity(), format!("The executor of transaction {} is not an existing actor role", transaction.t_id));
+            }
+            for initiation in &transaction.initiations {
+                if self.find_transaction(&initiation.initiated_transaction_id).is_none() {
+                    report(Severity::Error, entity(), format!("Transaction {} initiates a transaction that does not exist", transaction.t_id));
+                }
+                if let MaxMultiplicity::Numeric(max) = initiation.multiplicity.max {
+                    if initiation.multiplicity.min > max {
+                        report(Severity::Error, entity(), format!("Multiplicity of the initiation of {} from {} has minimum greater than maximum", transaction_name(&initiation.initiated_transaction_id), transaction.t_id));
+                    }
+                }
+            }
+            for impediment in &transaction.impediments {
+                if self.find_transaction(&impediment.impeding_transaction_id).is_none() {
+                    report(Severity::Error, entity(), format!("{} in transaction {} waits for a transaction that does not exist", impediment.impeded_act, transaction.t_id));
+                }
+            }
+            if transaction.deadlines.iter().any(|deadline| deadline.within_minutes <= 0) {
+                report(Severity::Error, entity(), format!("Transaction {} has a deadline that is not at least one minute long", transaction.t_id));
+            }
+            let parents: Vec<String> = self.transactions.iter()
+                .filter(|t| t.initiations.iter().any(|i| i.initiated_transaction_id == transaction.id))
+                .map(|t| t.t_id.clone())
+                .collect();
+            if parents.len() > 1 {
+                report(Severity::Warning, entity(), format!("Transaction {} is initiated from more than one transaction ({}); only the initiation from {} is used", transaction.t_id, parents.join(", "), parents[0]));
+            }
+            if self.initiates_transitively(&transaction.id, &transaction.id) {
+                report(Severity::Error, entity(), format!("Transaction {} initiates itself (directly or through its subtransactions)", transaction.t_id));
+            }
+        }
+
+        for subject in &self.subjects {
+            let entity = || ModelEntity::Subject(subject.id.clone());
+            if subject.name.trim().is_empty() {
+                report(Severity::Warning, entity(), "Subject has no name".to_string());
+            }
+            if self.adt.get_roles_of_subject(subject).is_empty() {
+                report(Severity::Warning, entity(), format!("Subject {} fulfils no actor role", subject.name));
+            }
+            if let Some(policy) = self.policies.get(&subject.id) {
+                if policy.rules.iter().any(|rule| rule.transaction_id.as_ref().is_some_and(|t_id| self.find_transaction(t_id).is_none())) {
+                    report(Severity::Warning, entity(), format!("The policy of subject {} refers to a transaction that does not exist", subject.name));
+                }
+            }
+        }
+
+        for (actor_role_id, subject_id) in self.adt.mappings.keys() {
+            if self.find_actor_role(actor_role_id).is_none() || self.find_subject(subject_id).is_none() {
+                report(Severity::Warning, ModelEntity::Adt, "The ADT refers to a deleted actor role or subject".to_string());
+                break;
+            }
+        }
+
+        diagnostics.sort_by_key(|diagnostic| diagnostic.severity);
+        diagnostics
+    }
+
+    fn initiates_transitively(&self, from: &TransactionId, target: &TransactionId) -> bool {
+        let mut visited = HashSet::new();
+        let mut stack = vec![from.clone()];
+        while let Some(t_id) = stack.pop() {
+            let Some(transaction) = self.find_transaction(&t_id) else { continue };
+            for initiation in &transaction.initiations {
+                if initiation.initiated_transaction_id == *target {
+                    return true;
+                }
+                if visited.insert(initiation.initiated_transaction_id.clone()) {
+                    stack.push(initiation.initiated_transaction_id.clone());
+                }
+            }
+        }
+        false
+    }
+
+    /// Removes broken references, so that a model with some of its parts lost (e.g. edited by hand or saved by a buggy
+    /// version) can be loaded and fixed in the editors. Returns the descriptions of the repairs. A missing initiator or
+    /// executor is left as it is: it cannot be made up, and [`Model::validate`] reports it.
+    pub fn repair(&mut self) -> Vec<String> {
+        let mut repairs = Vec::new();
+        let transaction_ids: HashSet<TransactionId> = self.transactions.iter().map(|t| t.id.clone()).collect();
+
+        for transaction in &mut self.transactions {
+            let initiations_count = transaction.initiations.len();
+            transaction.initiations.retain(|initiation| transaction_ids.contains(&initiation.initiated_transaction_id));
+            if transaction.initiations.len() < initiations_count {
+                repairs.push(format!("Removed initiations of missing transactions from transaction {}", transaction.t_id));
+            }
+            let impediments_count = transaction.impediments.len();
+            transaction.impediments.retain(|impediment| transaction_ids.contains(&impediment.impeding_transaction_id));
+            if transaction.impediments.len() < impediments_count {
+                repairs.push(format!("Removed wait links to missing transactions from transaction {}", transaction.t_id));
+            }
+            let deadlines_count = transaction.deadlines.len();
+            transaction.deadlines.retain(|deadline| deadline.within_minutes > 0);
+            if transaction.deadlines.len() < deadlines_count {
+                repairs.push(format!("Removed deadlines shorter than a minute from transaction {}", transaction.t_id));
+            }
+        }
+
+        let mappings_count = self.adt.mappings.len();
+        let actor_roles = &self.actor_roles;
+        let subjects = &self.subjects;
+        self.adt.mappings.retain(|(actor_role_id, subject_id), _| actor_roles.iter().any(|ar| ar.id == *actor_role_id) && subjects.iter().any(|s| s.id == *subject_id));
+        if self.adt.mappings.len() < mappings_count {
+            repairs.push(format!("Removed {} ADT entries of missing actor roles or subjects", mappings_count - self.adt.mappings.len()));
+        }
+
+        let policies_count = self.policies.len();
+        self.policies.retain(|subject_id, _| subjects.iter().any(|s| s.id == *subject_id));
+        if self.policies.len() < policies_count {
+            repairs.push("Removed policies of missing subjects".to_string());
+        }
+        for (subject_id, policy) in &mut self.policies {
+            let rules_count = policy.rules.len();
+            policy.rules.retain(|rule| match &rule.transaction_id {
+                Some(t_id) => transaction_ids.contains(t_id),
+                None => true,
+            });
+            if policy.rules.len() < rules_count {
+                let name = subjects.iter().find(|s| s.id == *subject_id).map(|s| s.name.clone()).unwrap_or_default();
+                repairs.push(format!("Removed policy rules of subject {} referring to missing transactions", name));
+            }
+        }
+        // Positions of deleted shapes are just left behind by the editors, not worth reporting
+        self.ocd_layout.actor_roles.retain(|actor_role_id, _| actor_roles.iter().any(|ar| ar.id == *actor_role_id));
+        self.ocd_layout.transactions.retain(|transaction_id, _| transaction_ids.contains(transaction_id));
+        repairs
+    }
+}
@@ -1,9 +1,12 @@
 use eframe::epaint::Color32;
 use egui::RichText;
 use crate::model::{ActorRole, Transaction};
+use crate::validation::ModelEntity;
 
-pub fn actor_roles_ui(ui: &mut egui::Ui, transactions: &Vec<Transaction>, actor_roles: &mut Vec<ActorRole>) {
+/// Returns whether the actor roles were changed.
+pub fn actor_roles_ui(ui: &mut egui::Ui, transactions: &[Transaction], actor_roles: &mut Vec<ActorRole>, hi_entity_o: &Option<ModelEntity>) -> bool {
     let mut to_delete = Vec::new();
+    let mut changed = false;
     egui::Grid::new("Actor Roles")
         .striped(true)
         .spacing(&[0.0, 5.0])
@@ -21,15 +24,22 @@ pub fn actor_roles_ui(ui: &mut egui::Ui, transactions: &Vec<Transaction>, actor_
                             to_delete.push(index);
                         }
                 });
-                ui.add(egui::TextEdit::singleline(&mut actor_role.name).min_size([200.0, 20.0 ].into()));
+                let name_edit = ui.add(egui::TextEdit::singleline(&mut actor_role.name).min_size([200.0, 20.0 ].into()));
+                changed |= name_edit.changed();
+                if *hi_entity_o == Some(ModelEntity::ActorRole(actor_role.id.clone())) {
+                    name_edit.highlight();
+                }
                 ui.end_row();
             }
         });
+    changed |= !to_delete.is_empty();
     for index in to_delete.into_iter().rev() {
         actor_roles.remove(index);
     }
     ui.add_space(16.0);
     if ui.button(RichText::new("➕").color(Color32::GREEN)).clicked() {
         actor_roles.push(ActorRole::default());
+        changed = true;
     }
+    changed
 }
@@ -2,7 +2,9 @@ use eframe::epaint::Color32;
 use egui::RichText;
 use crate::model::{Subject, Adt, AdtOption, ActorRole};
 
-pub fn adt_ui(ui: &mut egui::Ui, actor_roles: &Vec<ActorRole>, subjects: &Vec<Subject>, adt: &mut Adt) {
+/// Returns whether the ADT was changed.
+pub fn adt_ui(ui: &mut egui::Ui, actor_roles: &[ActorRole], subjects: &[Subject], adt: &mut Adt) -> bool {
+    let mut changed = false;
     egui::Grid::new("ADT")
         .striped(true)
         .spacing(&[5.0, 5.0])
@@ -20,7 +22,7 @@ pub fn adt_ui(ui: &mut egui::Ui, actor_roles: &Vec<ActorRole>, subjects: &Vec<Su
                     let mut choice: String = adt.mappings
                         .get(&(actor_role.id.clone(), subject.id.clone()))
                         .map_or_else(|| "".to_string(), |adt_option| adt_option.to_string());
-                    ui.add(egui::TextEdit::singleline(&mut choice));
+                    changed |= ui.add(egui::TextEdit::singleline(&mut choice)).changed();
                     if let Some(adt_option) = AdtOption::from_str(&choice) {
                       adt.mappings.insert((actor_role.id.clone(), subject.id.clone()), adt_option);
                     } else {
@@ -32,4 +34,5 @@ pub fn adt_ui(ui: &mut egui::Ui, actor_roles: &Vec<ActorRole>, subjects: &Vec<Su
         });
     ui.add_space(20.0);
     ui.label(RichText::new("A = Authorized, D = Delegated").color(Color32::DARK_GRAY));
+    changed
 }
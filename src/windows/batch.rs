@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+use eframe::epaint::Color32;
+use egui::{RichText, TextWrapMode};
+use crate::app::AppContext;
+use crate::batch::{ActDuration, BatchReport, BatchRun, Summary};
+use crate::model::{all_acts, Model, SubjectId, Transaction, TransactionId};
+
+fn hours_text(minutes: f64) -> String {
+    format!("{:.1} h", minutes / 60.0)
+}
+
+fn subject_combo(ui: &mut egui::Ui, id_salt: &str, model: &Model, subject_id_o: &mut Option<SubjectId>) {
+    let subject_text = |s_id_o: &Option<SubjectId>| s_id_o.as_ref()
+        .map_or_else(|| "First authorised".to_string(), |s_id| model.find_subject(s_id).map(|s| s.name.clone()).unwrap_or("subject not found".to_string()));
+    egui::ComboBox::from_id_salt(id_salt)
+        .selected_text(subject_text(subject_id_o))
+        .show_ui(ui, |ui| {
+            ui.style_mut().wrap_mode = Some(TextWrapMode::Extend);
+            ui.selectable_value(subject_id_o, None, subject_text(&None));
+            for subject in &model.subjects {
+                ui.selectable_value(subject_id_o, Some(subject.id.clone()), subject.name.clone());
+            }
+        });
+}
+
+fn durations_ui(ui: &mut egui::Ui, transactions: &[Transaction], transactions_map: &HashMap<TransactionId, String>, durations: &mut Vec<ActDuration>) {
+    let transaction_text = |t_id_o: &Option<TransactionId>| t_id_o.as_ref()
+        .map_or_else(|| "Any transaction".to_string(), |t_id| transactions_map.get(t_id).cloned().unwrap_or("tr not found".to_string()));
+    let mut to_delete = Vec::new();
+    for (d_index, duration) in durations.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            if ui.button(RichText::new("❌").color(Color32::RED)).clicked() {
+                to_delete.push(d_index);
+            }
+            egui::ComboBox::from_id_salt(format!("{}_{}", "Duration-Transaction", d_index))
+                .selected_text(transaction_text(&duration.transaction_id))
+                .show_ui(ui, |ui| {
+                    ui.style_mut().wrap_mode = Some(TextWrapMode::Extend);
+                    ui.selectable_value(&mut duration.transaction_id, None, transaction_text(&None));
+                    for transaction in transactions {
+                        ui.selectable_value(&mut duration.transaction_id, Some(transaction.id.clone()), transaction_text(&Some(transaction.id.clone())));
+                    }
+                });
+            egui::ComboBox::from_id_salt(format!("{}_{}", "Duration-Act", d_index))
+                .selected_text(duration.act.to_string())
+                .show_ui(ui, |ui| {
+                    for act in all_acts() {
+                        ui.selectable_value(&mut duration.act, act.clone(), act.to_string());
+                    }
+                });
+            ui.label("takes");
+            for minutes in [&mut duration.min_minutes, &mut duration.max_minutes] {
+                ui.add(egui::DragValue::from_get_set(|hours_o: Option<f64>| {
+                        if let Some(hours) = hours_o {
+                            *minutes = (hours * 60.0).round().max(0.0) as i64;
+                        }
+                        *minutes as f64 / 60.0
+                    })
+                    .speed(0.25)
+                    .suffix(" h"));
+            }
+        });
+    }
+    if ui.button(RichText::new("➕").color(Color32::GREEN)).clicked() {
+        durations.push(ActDuration::default());
+    }
+    for index in to_delete.into_iter().rev() {
+        durations.remove(index);
+    }
+}
+
+fn summary_labels(ui: &mut egui::Ui, summary_o: &Option<Summary>) {
+    match summary_o {
+        None => for _ in 0..4 { ui.label("--"); },
+        Some(summary) => {
+            ui.label(hours_text(summary.mean)).on_hover_text(format!("Standard deviation {}", hours_text(summary.std_dev)));
+            ui.label(hours_text(summary.median));
+            ui.label(hours_text(summary.p90));
+            ui.label(hours_text(summary.max));
+        }
+    }
+}
+
+fn report_ui(ui: &mut egui::Ui, model: &Model, report: &BatchReport) {
+    ui.label(format!("Completed runs: {} / {} ({} steps)", report.completed_runs, report.runs, report.steps));
+    if let Some(summary) = &report.completion_minutes {
+        ui.label(format!("Completion time: mean {}, median {}, 90th percentile {}", hours_text(summary.mean), hours_text(summary.median), hours_text(summary.p90)));
+    }
+    ui.add_space(5.0);
+    egui::Grid::new("Batch report")
+        .striped(true)
+        .spacing([10.0, 5.0])
+        .show(ui, |ui| {
+            for header in ["Transaction", "Instances", "Per run", "Accepted", "Declined", "Rejected", "Mean time", "Median", "90 %", "Max"] {
+                ui.strong(header);
+            }
+            ui.end_row();
+            for statistics in &report.transactions {
+                let Some(transaction) = model.find_transaction(&statistics.transaction_id) else { continue };
+                ui.label(format!("{}: {}", transaction.t_id, transaction.name));
+                ui.label(statistics.instances.to_string());
+                ui.label(statistics.instances_per_run.as_ref().map_or("--".to_string(), |summary| format!("{:.2}", summary.mean)));
+                ui.label(statistics.accepted.to_string());
+                ui.label(format!("{:.1} %", statistics.decline_rate() * 100.0));
+                ui.label(format!("{:.1} %", statistics.reject_rate() * 100.0));
+                summary_labels(ui, &statistics.completion_minutes);
+                ui.end_row();
+            }
+        });
+}
+
+pub fn view(ui: &mut egui::Ui, app_context: &mut AppContext) {
+    let model = &app_context.model;
+    let batch_context = &mut app_context.batch_context;
+    let transactions_map: HashMap<TransactionId, String> = model.transactions.iter()
+        .map(|tr| (tr.id.clone(), format!("{}: {}", tr.t_id, tr.name))).collect();
+    let config = &mut batch_context.config;
+    egui::Grid::new("Batch configuration")
+        .spacing([10.0, 5.0])
+        .show(ui, |ui| {
+            ui.label("Transaction");
+            egui::ComboBox::from_id_salt("Batch-Transaction")
+                .selected_text(batch_context.transaction_id_o.as_ref().and_then(|t_id| transactions_map.get(t_id)).cloned().unwrap_or_default())
+                .show_ui(ui, |ui| {
+                    ui.style_mut().wrap_mode = Some(TextWrapMode::Extend);
+                    for transaction in &model.transactions {
+                        ui.selectable_value(&mut batch_context.transaction_id_o, Some(transaction.id.clone()), transactions_map[&transaction.id].clone());
+                    }
+                });
+            ui.end_row();
+            ui.label("Product instance");
+            ui.text_edit_singleline(&mut config.product_instance);
+            ui.end_row();
+            ui.label("Initiator");
+            subject_combo(ui, "Batch-Initiator", model, &mut config.initiator_id);
+            ui.end_row();
+            ui.label("Executor");
+            subject_combo(ui, "Batch-Executor", model, &mut config.executor_id);
+            ui.end_row();
+            ui.label("Runs");
+            ui.add(egui::DragValue::new(&mut config.runs).range(1..=100_000));
+            ui.end_row();
+            ui.label("Seed");
+            ui.add(egui::DragValue::new(&mut config.seed));
+            ui.end_row();
+        });
+    ui.add_space(10.0);
+    ui.strong("Durations of acts");
+    ui.label(RichText::new("Drawn uniformly between the two values; acts without a duration take no time. Decisions follow the policies of the subjects.").color(Color32::DARK_GRAY));
+    durations_ui(ui, &model.transactions, &transactions_map, &mut config.durations);
+    ui.add_space(10.0);
+    if let Some(run) = &batch_context.run_o {
+        let runs = run.runs().max(1);
+        let mut cancelled = false;
+        ui.horizontal(|ui| {
+            ui.add(egui::ProgressBar::new(run.done_runs() as f32 / runs as f32).text(format!("{} / {} runs", run.done_runs(), runs)).desired_width(200.0));
+            cancelled = ui.button("Cancel").clicked();
+        });
+        if cancelled {
+            batch_context.run_o = None;
+        }
+    } else {
+        ui.add_enabled_ui(batch_context.transaction_id_o.is_some(), |ui| {
+            if ui.button("Run").clicked() {
+                if let Some(transaction_id) = &batch_context.transaction_id_o {
+                    match BatchRun::new(model, transaction_id, &batch_context.config) {
+                        Ok(run) => batch_context.run_o = Some(run),
+                        Err(e) => batch_context.report_o = Some(Err(e.to_string())),
+                    }
+                }
+            }
+        });
+    }
+    ui.separator();
+    match &batch_context.report_o {
+        None => {}
+        Some(Err(error)) => { ui.colored_label(Color32::RED, error); }
+        Some(Ok(report)) => report_ui(ui, model, report),
+    }
+}
@@ -71,7 +71,11 @@ pub fn view(ui: &mut egui::Ui, app_context: &mut AppContext) {
                             }
                             ui.label(c_world_item.performer.name.to_string());
                             ui.label(c_world_item.addressee.name.to_string());
-                            ui.label(c_world_item.fact.to_string());
+                            if c_world_item.tacit {
+                                ui.label(format!("{} (tacit)", c_world_item.fact));
+                            } else {
+                                ui.label(c_world_item.fact.to_string());
+                            }
                         }
                     }
                     ui.end_row();
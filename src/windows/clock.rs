@@ -0,0 +1,65 @@
+use chrono::{DateTime, Duration, Utc};
+use egui::{Color32, RichText};
+use crate::app::AppContext;
+use crate::clock::ClockMode;
+
+pub fn view(ui: &mut egui::Ui, app_context: &mut AppContext) {
+    let model = &app_context.model;
+    let execution = &mut app_context.execution;
+    let clock_context = &mut app_context.clock_context;
+    ui.horizontal(|ui| {
+        ui.label("Mode:");
+        ui.selectable_value(&mut execution.clock.mode, ClockMode::Simulated, ClockMode::Simulated.to_string());
+        ui.selectable_value(&mut execution.clock.mode, ClockMode::RealTime, ClockMode::RealTime.to_string());
+    });
+    ui.add_space(10.0);
+    ui.horizontal(|ui| {
+        ui.label("Now:");
+        ui.strong(execution.now().to_string());
+    });
+    if execution.clock.mode == ClockMode::RealTime {
+        ui.add_space(10.0);
+        ui.label(RichText::new("Facts are stamped with the real time.").color(Color32::DARK_GRAY));
+        return;
+    }
+    ui.add_space(10.0);
+    ui.horizontal(|ui| {
+        ui.label("Start:");
+        if clock_context.start_text.is_empty() {
+            clock_context.start_text = execution.clock.start.to_rfc3339();
+        }
+        let start_r = DateTime::parse_from_rfc3339(&clock_context.start_text);
+        let color = if start_r.is_err() { Some(Color32::RED) } else { None };
+        // The start can only be changed before anything has happened
+        ui.add_enabled_ui(execution.events().is_empty(), |ui| {
+            ui.add(egui::TextEdit::singleline(&mut clock_context.start_text).min_size([220.0, 20.0].into()).text_color_opt(color))
+                .on_disabled_hover_text("The execution has already started");
+        });
+        if let Ok(start) = start_r {
+            let start = start.with_timezone(&Utc);
+            if execution.events().is_empty() && start != execution.clock.start {
+                execution.clock.set_start(start);
+            }
+        }
+    });
+    ui.add_space(10.0);
+    ui.horizontal(|ui| {
+        ui.label("Advance by");
+        ui.add(egui::DragValue::new(&mut clock_context.advance_minutes).range(1..=525_600).suffix(" min"));
+        if ui.button("Advance").clicked() {
+            execution.advance_clock(model, Duration::minutes(clock_context.advance_minutes));
+        }
+    });
+    ui.horizontal(|ui| {
+        for (label, duration) in [("+1 hour", Duration::hours(1)), ("+1 day", Duration::days(1)), ("+1 week", Duration::weeks(1))] {
+            if ui.button(label).clicked() {
+                execution.advance_clock(model, duration);
+            }
+        }
+    });
+    ui.add_space(10.0);
+    ui.horizontal(|ui| {
+        ui.label("Advance automatically with every step by");
+        ui.add(egui::DragValue::new(&mut execution.clock.auto_advance_minutes).range(0..=525_600).suffix(" min"));
+    });
+}
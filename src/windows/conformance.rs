@@ -0,0 +1,59 @@
+use egui::{Color32, RichText};
+use crate::app::AppContext;
+use crate::conformance::{all_deviation_kinds, ConformanceReport, DeviationKind};
+
+fn summary_ui(ui: &mut egui::Ui, report: &ConformanceReport) {
+    let conforming = report.events - report.deviating_events() - report.unchecked;
+    ui.label(format!("Events: {}, conforming: {} ({:.1} %)", report.events, conforming, report.fitness() * 100.0));
+    if report.unchecked > 0 {
+        ui.label(format!("Not checked: {} (events of transaction instances not starting with a request)", report.unchecked));
+    }
+}
+
+pub fn view(ui: &mut egui::Ui, app_context: &mut AppContext) {
+    let conformance_context = &mut app_context.conformance_context;
+    let Some((file_name, report)) = &conformance_context.report_o else {
+        ui.label("Check an event log from the File menu to see how it conforms to the model.");
+        return;
+    };
+    ui.strong(file_name);
+    summary_ui(ui, report);
+    if report.deviations.is_empty() {
+        ui.colored_label(Color32::DARK_GREEN, "✔ No deviations found");
+        return;
+    }
+    ui.add_space(5.0);
+    let kind_filter_o = &mut conformance_context.kind_filter_o;
+    ui.horizontal_wrapped(|ui| {
+        ui.selectable_value(kind_filter_o, None, format!("All ({})", report.deviations.len()));
+        for kind in all_deviation_kinds() {
+            let count = report.count_of(kind);
+            if count > 0 {
+                ui.selectable_value(kind_filter_o, Some(kind), format!("{} ({})", kind, count));
+            }
+        }
+    });
+    ui.add_space(5.0);
+    egui::Grid::new("Conformance deviations")
+        .striped(true)
+        .spacing([10.0, 5.0])
+        .show(ui, |ui| {
+            for header in ["Event", "Time", "Case", "Activity", "Deviation", "Details"] {
+                ui.strong(header);
+            }
+            ui.end_row();
+            for deviation in report.deviations.iter().filter(|d| kind_filter_o.is_none() || *kind_filter_o == Some(d.kind)) {
+                ui.label(deviation.event_index.to_string());
+                ui.label(deviation.timestamp.to_string());
+                ui.label(&deviation.case_id);
+                ui.label(&deviation.activity);
+                let color = match deviation.kind {
+                    DeviationKind::NotInModel => Color32::from_rgb(230, 160, 0),
+                    _ => Color32::RED,
+                };
+                ui.label(RichText::new(deviation.kind.to_string()).color(color));
+                ui.label(&deviation.message);
+                ui.end_row();
+            }
+        });
+}
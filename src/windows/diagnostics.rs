@@ -0,0 +1,45 @@
+use egui::{Color32, RichText};
+use crate::app::AppContext;
+use crate::validation::{ModelEntity, Severity};
+
+fn entity_text(app_context: &AppContext, entity: &ModelEntity) -> String {
+    let model = &app_context.model;
+    match entity {
+        ModelEntity::ActorRole(ar_id) => format!("Actor role {}", model.find_actor_role(ar_id).map(|ar| ar.name.clone()).unwrap_or_default()),
+        ModelEntity::Transaction(t_id) => format!("Transaction {}", model.find_transaction(t_id).map(|t| t.t_id.clone()).unwrap_or_default()),
+        ModelEntity::Subject(s_id) => format!("Subject {}", model.find_subject(s_id).map(|s| s.name.clone()).unwrap_or_default()),
+        ModelEntity::Adt => "ADT".to_string(),
+    }
+}
+
+/// Returns the entity to jump to, if requested.
+pub fn view(ui: &mut egui::Ui, app_context: &mut AppContext) -> Option<ModelEntity> {
+    let diagnostics = app_context.diagnostics_cache.get(&app_context.model, app_context.model_generation).to_vec();
+    if diagnostics.is_empty() {
+        ui.colored_label(Color32::DARK_GREEN, "✔ No problems found");
+        return None;
+    }
+    let mut jump_o = None;
+    egui::Grid::new("Diagnostics")
+        .striped(true)
+        .spacing([10.0, 5.0])
+        .show(ui, |ui| {
+            ui.strong("Severity");
+            ui.strong("Entity");
+            ui.strong("Problem");
+            ui.end_row();
+
+            for diagnostic in &diagnostics {
+                match diagnostic.severity {
+                    Severity::Error => ui.label(RichText::new("⛔ Error").color(Color32::RED)),
+                    Severity::Warning => ui.label(RichText::new("⚠ Warning").color(Color32::from_rgb(230, 160, 0))),
+                };
+                if ui.link(entity_text(app_context, &diagnostic.entity)).on_hover_text("Show").clicked() {
+                    jump_o = Some(diagnostic.entity.clone());
+                }
+                ui.label(&diagnostic.message);
+                ui.end_row();
+            }
+        });
+    jump_o
+}
@@ -1,4 +1,7 @@
+use chrono::{DateTime, Utc};
+use egui::Color32;
 use crate::app::{AppContext, InitiateTransactionModalContext};
+use crate::validation::ModelEntity;
 
 mod actor_roles;
 mod transactions;
@@ -8,6 +11,14 @@ mod subjects_dashboard;
 mod transaction_initiate_modal;
 mod transactions_instances;
 mod c_p_world;
+mod clock;
+mod policies;
+mod batch;
+mod diagnostics;
+mod conformance;
+mod ocd_editor;
+mod psd_view;
+mod timeline;
 
 #[derive(serde::Deserialize, serde::Serialize, Default)]
 pub struct EguiWindows {
@@ -19,6 +30,23 @@ pub struct EguiWindows {
     pub transaction_initiate_modal: bool,
     pub transactions_instances: bool,
     pub c_p_world: bool,
+    pub clock: bool,
+    pub policies: bool,
+    pub batch: bool,
+    pub diagnostics: bool,
+    pub conformance: bool,
+    pub ocd_editor: bool,
+    pub psd_view: bool,
+    pub timeline: bool,
+}
+
+// Due date of an agenda item, highlighted when overdue
+pub(crate) fn due_label(ui: &mut egui::Ui, due_o: &Option<DateTime<Utc>>, now: &DateTime<Utc>) -> egui::Response {
+    match due_o {
+        None => ui.label("--"),
+        Some(due) if due <= now => ui.colored_label(Color32::RED, format!("⚠ {}", due)).on_hover_text("Overdue"),
+        Some(due) => ui.label(due.to_string()),
+    }
 }
 
 impl EguiWindows {
@@ -32,33 +60,70 @@ impl EguiWindows {
             transaction_initiate_modal,
             transactions_instances,
             c_p_world,
+            clock,
+            policies,
+            batch,
+            diagnostics,
+            conformance,
+            ocd_editor,
+            psd_view,
+            timeline,
         } = self;
+        let mut jump_o = None;
+        egui::Window::new("Diagnostics")
+            .open(diagnostics)
+            .vscroll(true)
+            .show(ctx, |ui| {
+                jump_o = diagnostics::view(ui, app_context);
+            });
+        if let Some(entity) = jump_o {
+            let (title, open) = match entity {
+                ModelEntity::ActorRole(_) => ("Actor Roles", &mut *actor_roles),
+                ModelEntity::Transaction(_) => ("Transactions", &mut *transactions),
+                ModelEntity::Subject(_) => ("Subjects", &mut *subjects),
+                ModelEntity::Adt => ("ADT", &mut *adt),
+            };
+            *open = true;
+            ctx.move_to_top(egui::LayerId::new(egui::Order::Middle, egui::Id::new(title)));
+            app_context.hi_entity_o = Some(entity);
+        }
+        egui::Window::new("OCD Editor")
+            .open(ocd_editor)
+            .default_size([700.0, 450.0])
+            .show(ctx, |ui| {
+                ocd_editor::view(ui, app_context)
+            });
         let model = &mut app_context.model;
+        let hi_entity_o = &app_context.hi_entity_o;
+        let mut model_changed = false;
 
         egui::Window::new("Actor Roles")
             .open(actor_roles)
             .vscroll(true)
             .show(ctx, |ui| {
-                actor_roles::actor_roles_ui(ui, &model.transactions, &mut model.actor_roles)
+                model_changed |= actor_roles::actor_roles_ui(ui, &model.transactions, &mut model.actor_roles, hi_entity_o)
             });
         egui::Window::new("Transactions")
             .open(transactions)
             .vscroll(true)
             .show(ctx, |ui| {
-                transactions::transactions_ui(ui, &model.actor_roles, &mut model.transactions)
+                model_changed |= transactions::transactions_ui(ui, &model.actor_roles, &mut model.transactions, hi_entity_o)
             });
         egui::Window::new("Subjects")
             .open(subjects)
             .vscroll(true)
             .show(ctx, |ui| {
-                subjects::subjects_ui(ui, &mut model.subjects)
+                model_changed |= subjects::subjects_ui(ui, &mut model.subjects, hi_entity_o)
             });
         egui::Window::new("ADT")
             .open(adt)
             .vscroll(true)
             .show(ctx, |ui| {
-                adt::adt_ui(ui, &model.actor_roles, &model.subjects, &mut model.adt)
+                model_changed |= adt::adt_ui(ui, &model.actor_roles, &model.subjects, &mut model.adt)
             });
+        if model_changed {
+            app_context.model_changed();
+        }
         let mut transaction_initiate_modal_open_request = false;
         let mut transaction_initiate_modal_close_request = false;
         let mut new_initiate_transaction_modal_context_o = None;
@@ -68,21 +133,21 @@ impl EguiWindows {
             .show(ctx, |ui| {
                 subjects_dashboard::subjects_tabs_ui(ui, app_context);
                 ui.add_space(10.0);
+                let open_modal = |parent_transaction_instance_id, transaction_id| {
+                    new_initiate_transaction_modal_context_o = Some(InitiateTransactionModalContext {
+                        parent_transaction_instance_id,
+                        initiated_transaction_id_o: Some(transaction_id),
+                        requested_product: String::new(),
+                        addressee_id_o: None,
+                        error_o: None,
+                    });
+                };
                 if let Some(fp_id) = app_context.subject_context.focused_subject_id_o.clone() {
-                    subjects_dashboard::subject_pane_ui(
-                        ui,
-                        app_context,
-                        &fp_id,
-                        transaction_initiate_modal.clone(),
-                        |parent_transaction_instance_id, transaction_id| {
-                            new_initiate_transaction_modal_context_o = Some(InitiateTransactionModalContext {
-                                parent_transaction_instance_id,
-                                initiated_transaction_id_o: Some(transaction_id),
-                                requested_product: String::new(),
-                                addressee_id_o: None,
-                            });
-                        },
-                    );
+                    if app_context.subject_context.inbox {
+                        subjects_dashboard::inbox_pane_ui(ui, app_context, *transaction_initiate_modal, open_modal);
+                    } else {
+                        subjects_dashboard::subject_pane_ui(ui, app_context, &fp_id, *transaction_initiate_modal, open_modal);
+                    }
                 }
             });
         if let Some(new_initiate_transaction_modal_context) = new_initiate_transaction_modal_context_o {
@@ -117,6 +182,42 @@ impl EguiWindows {
             .show(ctx, |ui| {
                 c_p_world::view(ui, app_context)
             });
+        egui::Window::new("Live Process Structure Diagram")
+            .open(psd_view)
+            .default_size([700.0, 400.0])
+            .show(ctx, |ui| {
+                psd_view::view(ui, app_context)
+            });
+        egui::Window::new("Timeline")
+            .open(timeline)
+            .default_size([800.0, 400.0])
+            .show(ctx, |ui| {
+                timeline::view(ui, app_context)
+            });
+        egui::Window::new("Simulation Clock")
+            .open(clock)
+            .vscroll(true)
+            .show(ctx, |ui| {
+                clock::view(ui, app_context)
+            });
+        egui::Window::new("Policies")
+            .open(policies)
+            .vscroll(true)
+            .show(ctx, |ui| {
+                policies::view(ui, app_context)
+            });
+        egui::Window::new("Batch Simulation")
+            .open(batch)
+            .vscroll(true)
+            .show(ctx, |ui| {
+                batch::view(ui, app_context)
+            });
+        egui::Window::new("Conformance")
+            .open(conformance)
+            .vscroll(true)
+            .show(ctx, |ui| {
+                conformance::view(ui, app_context)
+            });
     }
 }
 
@@ -0,0 +1,272 @@
+use egui::{vec2, Align2, Color32, FontId, Pos2, Rect, RichText, Sense, Shape, Stroke};
+use crate::app::{AppContext, OcdEditorContext};
+use crate::export::box_border_point;
+use crate::export::ocd::{is_environmental, Layout, ROLE_HEIGHT, ROLE_MIN_WIDTH, TRANSACTION_RADIUS};
+use crate::export::text_width;
+use crate::model::{ActorRole, CAct, CFact, Initiation, Model, Multiplicity, Position, Transaction};
+use crate::validation::ModelEntity;
+
+const FONT_SIZE: f32 = 13.0;
+const ENVIRONMENT_FILL: Color32 = Color32::from_gray(221);
+const PRODUCTION_FILL: Color32 = Color32::from_rgb(244, 199, 195);
+
+fn role_size(actor_role: &ActorRole) -> (f32, f32) {
+    ((text_width(&actor_role.name, FONT_SIZE) + 20.0).max(ROLE_MIN_WIDTH), ROLE_HEIGHT)
+}
+
+// The shapes with the rectangles they are grabbed by, the transactions last as they are drawn over the links
+fn shapes(model: &Model, layout: &Layout, origin: Pos2) -> Vec<(ModelEntity, Rect)> {
+    let actor_roles = model.actor_roles.iter().filter_map(|actor_role| {
+        let &(x, y) = layout.actor_roles.get(&actor_role.id)?;
+        let (width, height) = role_size(actor_role);
+        Some((ModelEntity::ActorRole(actor_role.id.clone()), Rect::from_center_size(origin + vec2(x, y), vec2(width, height))))
+    });
+    let transactions = model.transactions.iter().filter_map(|transaction| {
+        let &(x, y) = layout.transactions.get(&transaction.id)?;
+        Some((ModelEntity::Transaction(transaction.id.clone()), Rect::from_center_size(origin + vec2(x, y), vec2(2.0, 2.0) * TRANSACTION_RADIUS)))
+    });
+    actor_roles.chain(transactions).collect()
+}
+
+// Keeps the shape on the canvas
+fn move_shape(model: &mut Model, entity: &ModelEntity, rect: Rect, origin: Pos2) {
+    let center = rect.center() - origin;
+    let position = Position { x: center.x.max(rect.width() / 2.0), y: center.y.max(rect.height() / 2.0) };
+    match entity {
+        ModelEntity::ActorRole(actor_role_id) => { model.ocd_layout.actor_roles.insert(actor_role_id.clone(), position); }
+        ModelEntity::Transaction(transaction_id) => { model.ocd_layout.transactions.insert(transaction_id.clone(), position); }
+        ModelEntity::Subject(_) | ModelEntity::Adt => {}
+    }
+}
+
+// A link dragged from a shape to another one: an actor role to a transaction is its initiator, a transaction to an
+// actor role is its executor and a transaction to another transaction initiates it
+fn add_link(model: &mut Model, from: &ModelEntity, to: &ModelEntity) -> Result<(), String> {
+    match (from, to) {
+        (ModelEntity::ActorRole(actor_role_id), ModelEntity::Transaction(transaction_id)) => {
+            if let Some(transaction) = model.transactions.iter_mut().find(|t| t.id == *transaction_id) {
+                transaction.initiator_id = actor_role_id.clone();
+            }
+            Ok(())
+        }
+        (ModelEntity::Transaction(transaction_id), ModelEntity::ActorRole(actor_role_id)) => {
+            if let Some(other) = model.transactions.iter().find(|t| t.executor_id == *actor_role_id && t.id != *transaction_id) {
+                let name = model.find_actor_role(actor_role_id).map(|ar| ar.name.clone()).unwrap_or_default();
+                return Err(format!("{} already executes {}", name, other.t_id));
+            }
+            if let Some(transaction) = model.transactions.iter_mut().find(|t| t.id == *transaction_id) {
+                transaction.executor_id = actor_role_id.clone();
+            }
+            Ok(())
+        }
+        (ModelEntity::Transaction(transaction_id), ModelEntity::Transaction(initiated_transaction_id)) if transaction_id != initiated_transaction_id => {
+            let Some(transaction) = model.transactions.iter_mut().find(|t| t.id == *transaction_id) else { return Ok(()) };
+            if transaction.initiations.iter().any(|i| i.initiated_transaction_id == *initiated_transaction_id) {
+                return Err(format!("{} already initiates this transaction", transaction.t_id));
+            }
+            // The usual response link, to be refined in the Transactions window
+            transaction.initiations.push(Initiation {
+                initiating_c_fact: CFact::Promised,
+                initiated_transaction_id: initiated_transaction_id.clone(),
+                initiated_c_act: CAct::Request,
+                multiplicity: Multiplicity::default(),
+                multiplicity_tmp_str: Multiplicity::default().to_string(),
+            });
+            Ok(())
+        }
+        _ => Err("Links connect an actor role with a transaction, or a transaction with a transaction it initiates".to_string()),
+    }
+}
+
+fn draw(ui: &egui::Ui, painter: &egui::Painter, model: &Model, layout: &Layout, origin: Pos2, selected_o: &Option<ModelEntity>) {
+    let line_stroke = Stroke::new(1.0, ui.visuals().text_color());
+    let shape_stroke = |entity: ModelEntity| if *selected_o == Some(entity) { Stroke::new(2.5, ui.visuals().selection.bg_fill) } else { Stroke::new(1.0, Color32::BLACK) };
+    let font = FontId::proportional(FONT_SIZE);
+    let at = |(x, y): (f32, f32)| origin + vec2(x, y);
+
+    // Links first, the shapes cover their ends
+    let mut executor_squares = Vec::new();
+    for transaction in &model.transactions {
+        let Some(&t_center) = layout.transactions.get(&transaction.id) else { continue };
+        if let Some(&center) = layout.actor_roles.get(&transaction.initiator_id) {
+            painter.line_segment([at(center), at(t_center)], line_stroke);
+        }
+        if let Some((&center, actor_role)) = layout.actor_roles.get(&transaction.executor_id).zip(model.find_actor_role(&transaction.executor_id)) {
+            painter.line_segment([at(t_center), at(center)], line_stroke);
+            let (width, height) = role_size(actor_role);
+            executor_squares.push(at(box_border_point(center, (width / 2.0, height / 2.0), t_center)));
+        }
+        for initiation in &transaction.initiations {
+            let Some(&to) = layout.transactions.get(&initiation.initiated_transaction_id) else { continue };
+            let (from, to) = (at(t_center), at(to));
+            let direction = (to - from).normalized();
+            if (to - from).length() > 2.0 * TRANSACTION_RADIUS {
+                let (start, end) = (from + direction * TRANSACTION_RADIUS, to - direction * TRANSACTION_RADIUS);
+                painter.extend(Shape::dashed_line(&[start, end - direction * 8.0], line_stroke, 6.0, 4.0));
+                painter.arrow(end - direction * 8.0, direction * 8.0, line_stroke);
+                painter.text(start + (end - start) / 2.0 + vec2(4.0, 0.0), Align2::LEFT_BOTTOM, initiation.initiating_c_fact.to_act().abbreviation(), FontId::proportional(FONT_SIZE - 2.0), ui.visuals().weak_text_color());
+            }
+        }
+    }
+    for actor_role in &model.actor_roles {
+        let Some(&center) = layout.actor_roles.get(&actor_role.id) else { continue };
+        let (width, height) = role_size(actor_role);
+        let rect = Rect::from_center_size(at(center), vec2(width, height));
+        let fill = if is_environmental(model, &actor_role.id) { ENVIRONMENT_FILL } else { Color32::WHITE };
+        painter.rect(rect, 0.0, fill, shape_stroke(ModelEntity::ActorRole(actor_role.id.clone())));
+        painter.text(rect.center(), Align2::CENTER_CENTER, &actor_role.name, font.clone(), Color32::BLACK);
+    }
+    for square in executor_squares {
+        painter.rect_filled(Rect::from_center_size(square, vec2(8.0, 8.0)), 0.0, Color32::BLACK);
+    }
+    for transaction in &model.transactions {
+        let Some(&center) = layout.transactions.get(&transaction.id) else { continue };
+        let (c, r) = (at(center), TRANSACTION_RADIUS);
+        painter.circle(c, r, Color32::WHITE, shape_stroke(ModelEntity::Transaction(transaction.id.clone())));
+        painter.add(Shape::convex_polygon(vec![c - vec2(0.0, r), c + vec2(r, 0.0), c + vec2(0.0, r), c - vec2(r, 0.0)], PRODUCTION_FILL, Stroke::new(1.0, Color32::BLACK)));
+        painter.text(c, Align2::CENTER_CENTER, &transaction.t_id, FontId::proportional(FONT_SIZE - 1.0), Color32::BLACK);
+        painter.text(c + vec2(0.0, r + 2.0), Align2::CENTER_TOP, &transaction.name, FontId::proportional(FONT_SIZE - 1.0), ui.visuals().text_color());
+    }
+}
+
+// Returns whether the model was changed
+fn selection_ui(ui: &mut egui::Ui, model: &mut Model, selected_o: &mut Option<ModelEntity>) -> bool {
+    let mut delete = false;
+    let mut changed = false;
+    match selected_o.clone() {
+        Some(ModelEntity::ActorRole(actor_role_id)) => {
+            let used = model.transactions.iter().any(|tr| tr.initiator_id == actor_role_id || tr.executor_id == actor_role_id);
+            let Some(actor_role) = model.actor_roles.iter_mut().find(|ar| ar.id == actor_role_id) else { return false };
+            ui.horizontal(|ui| {
+                ui.label("Actor role");
+                changed |= ui.add(egui::TextEdit::singleline(&mut actor_role.name).min_size([200.0, 20.0].into())).changed();
+                ui.add_enabled_ui(!used, |ui| {
+                    delete = ui.button(RichText::new("❌").color(Color32::RED)).on_disabled_hover_text("Used in a transaction").clicked();
+                });
+            });
+            if delete {
+                model.actor_roles.retain(|ar| ar.id != actor_role_id);
+                model.ocd_layout.actor_roles.remove(&actor_role_id);
+            }
+        }
+        Some(ModelEntity::Transaction(transaction_id)) => {
+            let used = model.transactions.iter().any(|tr| tr.impediments.iter().any(|imp| imp.impeding_transaction_id == transaction_id));
+            let t_ids: Vec<(_, String)> = model.transactions.iter().map(|t| (t.id.clone(), t.t_id.clone())).collect();
+            let Some(transaction) = model.transactions.iter_mut().find(|t| t.id == transaction_id) else { return false };
+            ui.horizontal(|ui| {
+                ui.label("Transaction");
+                changed |= ui.add(egui::TextEdit::singleline(&mut transaction.t_id).min_size([50.0, 20.0].into())).changed();
+                changed |= ui.add(egui::TextEdit::singleline(&mut transaction.name).min_size([200.0, 20.0].into())).changed();
+                ui.add_enabled_ui(!used, |ui| {
+                    delete = ui.button(RichText::new("❌").color(Color32::RED)).on_disabled_hover_text("Used in an impediment").clicked();
+                });
+            });
+            let mut to_delete = Vec::new();
+            ui.horizontal_wrapped(|ui| {
+                for (index, initiation) in transaction.initiations.iter().enumerate() {
+                    let t_id = t_ids.iter().find(|(id, _)| *id == initiation.initiated_transaction_id).map(|(_, t_id)| t_id.as_str()).unwrap_or("tr not found");
+                    ui.label(format!("{} → {} {} ({})", initiation.initiating_c_fact, initiation.initiated_c_act, t_id, initiation.multiplicity));
+                    if ui.small_button(RichText::new("❌").color(Color32::RED)).clicked() {
+                        to_delete.push(index);
+                    }
+                }
+            });
+            changed |= !to_delete.is_empty();
+            for index in to_delete.into_iter().rev() {
+                transaction.initiations.remove(index);
+            }
+            if delete {
+                model.transactions.retain(|t| t.id != transaction_id);
+                model.ocd_layout.transactions.remove(&transaction_id);
+            }
+        }
+        Some(ModelEntity::Subject(_)) | Some(ModelEntity::Adt) | None => {}
+    }
+    if delete {
+        *selected_o = None;
+    }
+    changed || delete
+}
+
+pub fn view(ui: &mut egui::Ui, app_context: &mut AppContext) {
+    let OcdEditorContext { link_mode, selected_o, error_o } = &mut app_context.ocd_editor_context;
+    let model = &mut app_context.model;
+    let mut changed = false;
+    ui.horizontal(|ui| {
+        ui.selectable_value(link_mode, false, "✋ Move");
+        ui.selectable_value(link_mode, true, "🔗 Link");
+        ui.separator();
+        if ui.button(RichText::new("➕ Actor role").color(Color32::GREEN)).clicked() {
+            let actor_role = ActorRole::default();
+            *selected_o = Some(ModelEntity::ActorRole(actor_role.id.clone()));
+            model.actor_roles.push(actor_role);
+            changed = true;
+        }
+        if ui.button(RichText::new("➕ Transaction").color(Color32::GREEN)).clicked() {
+            let mut transaction = Transaction::new();
+            transaction.t_id = format!("T{:02}", model.transactions.len() + 1);
+            *selected_o = Some(ModelEntity::Transaction(transaction.id.clone()));
+            model.transactions.push(transaction);
+            changed = true;
+        }
+        ui.separator();
+        if ui.button("Auto layout").on_hover_text("Forget where the shapes were placed").clicked() {
+            model.ocd_layout = Default::default();
+            changed = true;
+        }
+    });
+    let hint = if *link_mode {
+        "Drag from an actor role to a transaction to make it the initiator, from a transaction to an actor role to make it the executor, from a transaction to another one to initiate it."
+    } else {
+        "Drag the shapes to place them, click one to edit it."
+    };
+    ui.label(RichText::new(hint).color(Color32::DARK_GRAY));
+    if let Some(error) = error_o {
+        ui.colored_label(Color32::RED, error.as_str());
+    }
+    changed |= selection_ui(ui, model, selected_o);
+    ui.separator();
+
+    egui::ScrollArea::both().show(ui, |ui| {
+        let layout = Layout::new(model);
+        let size = vec2(layout.width, layout.height).max(ui.available_size());
+        let (response, painter) = ui.allocate_painter(size, Sense::click());
+        let origin = response.rect.min;
+        if response.clicked() {
+            *selected_o = None;
+        }
+        let shapes = shapes(model, &layout, origin);
+        let mut dragged_link_o = None;
+        for (entity, rect) in &shapes {
+            let shape_response = ui.interact(*rect, ui.id().with(entity), Sense::click_and_drag());
+            if shape_response.clicked() || shape_response.drag_started() {
+                *selected_o = Some(entity.clone());
+                *error_o = None;
+            }
+            if !*link_mode && shape_response.dragged() {
+                move_shape(model, entity, rect.translate(shape_response.drag_delta()), origin);
+                changed = true;
+            }
+            if *link_mode && shape_response.dragged() {
+                dragged_link_o = ui.ctx().pointer_latest_pos().map(|pointer| (rect.center(), pointer));
+            }
+            if *link_mode && shape_response.drag_stopped() {
+                let target_o = ui.ctx().pointer_latest_pos()
+                    .and_then(|pointer| shapes.iter().rev().find(|(_, r)| r.contains(pointer)))
+                    .filter(|(target, _)| target != entity);
+                if let Some((target, _)) = target_o {
+                    *error_o = add_link(model, entity, target).err();
+                    changed = true;
+                }
+            }
+        }
+        let layout = Layout::new(model);
+        draw(ui, &painter, model, &layout, origin, selected_o);
+        if let Some((from, to)) = dragged_link_o {
+            painter.arrow(from, to - from, Stroke::new(1.5, ui.visuals().selection.bg_fill));
+        }
+    });
+    if changed {
+        app_context.model_changed();
+    }
+}
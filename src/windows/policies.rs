@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+use eframe::epaint::Color32;
+use egui::{RichText, TextWrapMode};
+use crate::app::AppContext;
+use crate::model::{all_c_facts, CPFact, Policy, PolicyRule, Subject, Transaction, TransactionId};
+
+fn rule_facts() -> Vec<CPFact> {
+    let mut facts: Vec<CPFact> = all_c_facts().into_iter()
+        .filter(|c_fact| !c_fact.is_revocation() && !c_fact.is_terminal())
+        .map(CPFact::CFact)
+        .collect();
+    facts.insert(2, CPFact::PFact);
+    facts
+}
+
+pub fn view(ui: &mut egui::Ui, app_context: &mut AppContext) {
+    let model = &mut app_context.model;
+    let mut changed = false;
+    if ui.checkbox(&mut app_context.run_policies, "Run automated subjects")
+        .on_hover_text("Automated subjects act as soon as something appears on their agenda")
+        .changed() {
+        app_context.policies_paused_at_o = None;
+    }
+    if app_context.run_policies && app_context.policies_paused_at_o.is_some() {
+        ui.label("Paused after undo or redo, until the next step");
+    }
+    ui.add_space(10.0);
+    let transactions_map: HashMap<TransactionId, String> = model.transactions.iter()
+        .map(|tr| (tr.id.clone(), format!("{}: {}", tr.t_id, tr.name))).collect();
+    let transaction_text = |t_id_o: &Option<TransactionId>| t_id_o.as_ref()
+        .map_or_else(|| "Any transaction".to_string(), |t_id| transactions_map.get(t_id).cloned().unwrap_or("tr not found".to_string()));
+    let facts = rule_facts();
+    for subject in &model.subjects {
+        // Edited on a copy, so that just viewing a subject does not add a policy to the model
+        let mut policy = model.policies.get(&subject.id).cloned().unwrap_or_default();
+        policy_ui(ui, subject, &model.transactions, &transaction_text, &facts, &mut policy);
+        if model.policies.get(&subject.id).map_or(policy != Policy::default(), |stored| *stored != policy) {
+            model.policies.insert(subject.id.clone(), policy);
+            changed = true;
+        }
+    }
+    if changed {
+        app_context.model_changed();
+    }
+}
+
+fn policy_ui(ui: &mut egui::Ui, subject: &Subject, transactions: &[Transaction], transaction_text: &impl Fn(&Option<TransactionId>) -> String, facts: &[CPFact], policy: &mut Policy) {
+    ui.separator();
+    ui.horizontal(|ui| {
+        ui.strong(&subject.name);
+        ui.checkbox(&mut policy.automated, "Automated");
+        if ui.button("Cooperative preset").on_hover_text("Replaces the rules").clicked() {
+            *policy = Policy::cooperative();
+        }
+    });
+    if !policy.automated {
+        return;
+    }
+    let mut to_delete = Vec::new();
+    egui::Grid::new(format!("Policy of {}", subject.id))
+        .striped(true)
+        .spacing([10.0, 5.0])
+        .show(ui, |ui| {
+            ui.label("");
+            ui.strong("Transaction");
+            ui.strong("Fact");
+            ui.strong("Act");
+            ui.strong("Probability");
+            ui.end_row();
+            for (r_index, rule) in policy.rules.iter_mut().enumerate() {
+                if ui.button(RichText::new("❌").color(Color32::RED)).clicked() {
+                    to_delete.push(r_index);
+                }
+                egui::ComboBox::from_id_salt(format!("{}_{}_{}", subject.id, "Rule-Transaction", r_index))
+                    .selected_text(transaction_text(&rule.transaction_id))
+                    .show_ui(ui, |ui| {
+                        ui.style_mut().wrap_mode = Some(TextWrapMode::Extend);
+                        ui.selectable_value(&mut rule.transaction_id, None, transaction_text(&None));
+                        for transaction in transactions {
+                            ui.selectable_value(&mut rule.transaction_id, Some(transaction.id.clone()), transaction_text(&Some(transaction.id.clone())));
+                        }
+                    });
+                egui::ComboBox::from_id_salt(format!("{}_{}_{}", subject.id, "Rule-Fact", r_index))
+                    .selected_text(rule.fact.to_string())
+                    .show_ui(ui, |ui| {
+                        for fact in facts {
+                            ui.selectable_value(&mut rule.fact, fact.clone(), fact.to_string());
+                        }
+                    });
+                egui::ComboBox::from_id_salt(format!("{}_{}_{}", subject.id, "Rule-Act", r_index))
+                    .selected_text(rule.act.to_string())
+                    .show_ui(ui, |ui| {
+                        for act in rule.fact.next_acts() {
+                            ui.selectable_value(&mut rule.act, act.clone(), act.to_string());
+                        }
+                    });
+                ui.add(egui::Slider::new(&mut rule.probability_percent, 0..=100).suffix(" %"));
+                ui.end_row();
+            }
+        });
+    for index in to_delete.into_iter().rev() {
+        policy.rules.remove(index);
+    }
+    if ui.button(RichText::new("➕").color(Color32::GREEN)).clicked() {
+        policy.rules.push(PolicyRule::default());
+    }
+}
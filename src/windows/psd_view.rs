@@ -0,0 +1,187 @@
+use std::collections::HashMap;
+use egui::{vec2, Align2, Color32, FontId, Pos2, Rect, RichText, Sense, Shape, Stroke, TextWrapMode};
+use crate::app::AppContext;
+use crate::execution::{Execution, TransactionInstance};
+use crate::export::psd::{LinkKind, PsdLayout, MARGIN, STEP_RADIUS};
+use crate::export::root_transaction_instance;
+use crate::model::{CPAct, CPFact, Model, TransactionId};
+
+const FONT_SIZE: f32 = 13.0;
+const REACHED_FILL: Color32 = Color32::from_rgb(170, 220, 170);
+const PENDING_FILL: Color32 = Color32::from_rgb(250, 215, 120);
+const BLOCKED_FILL: Color32 = Color32::from_rgb(240, 140, 130);
+const P_STEP_FILL: Color32 = Color32::from_rgb(244, 199, 195);
+
+// How a step stands in the transaction instances shown
+#[derive(Default)]
+struct StepState {
+    reached: usize,        // instances having reached its fact
+    pending: usize,        // instances where the act is awaited on an agenda
+    blocked: Vec<String>,  // why the awaited act is impeded by wait links
+}
+
+impl StepState {
+    fn fill(&self, act: &CPAct) -> Color32 {
+        if !self.blocked.is_empty() {
+            BLOCKED_FILL
+        } else if self.pending > 0 {
+            PENDING_FILL
+        } else if self.reached > 0 {
+            REACHED_FILL
+        } else if *act == CPAct::PAct {
+            P_STEP_FILL
+        } else {
+            Color32::WHITE
+        }
+    }
+
+    fn describe(&self) -> String {
+        let mut lines = vec![format!("Reached in {} instance(s)", self.reached)];
+        if self.pending > 0 {
+            lines.push(format!("Awaited in {} instance(s)", self.pending));
+        }
+        lines.extend(self.blocked.iter().map(|reason| format!("Blocked: {}", reason)));
+        lines.join("\n")
+    }
+}
+
+fn step_states<'a>(model: &'a Model, execution: &Execution, instances: &[&TransactionInstance]) -> HashMap<(&'a TransactionId, CPAct), StepState> {
+    let mut states: HashMap<(&TransactionId, CPAct), StepState> = HashMap::new();
+    for transaction_instance in instances {
+        let Some(transaction) = model.find_transaction(&transaction_instance.transaction_id) else { continue };
+        for fact in execution.get_effective_facts(&transaction_instance.id) {
+            let act = match fact {
+                CPFact::CFact(c_fact) => CPAct::CAct(c_fact.to_act()),
+                CPFact::PFact => CPAct::PAct,
+            };
+            states.entry((&transaction.id, act)).or_default().reached += 1;
+        }
+        for (subject_id, _) in execution.agendas.iter().filter(|(_, item)| item.transaction_instance_id == transaction_instance.id) {
+            for act in execution.available_acts(transaction_instance, subject_id).into_iter().filter(|act| !act.is_revocation()) {
+                let impediments_o = execution.get_act_impediments(model, transaction, &execution.impediments_context(transaction_instance), &act);
+                let state = states.entry((&transaction.id, act)).or_default();
+                state.pending += 1;
+                state.blocked.extend(impediments_o.unwrap_or_default());
+            }
+        }
+    }
+    states
+}
+
+fn instance_text(model: &Model, transaction_instance: &TransactionInstance) -> String {
+    let t_id = model.find_transaction(&transaction_instance.transaction_id).map(|t| t.t_id.clone()).unwrap_or_default();
+    format!("{}: {}", t_id, transaction_instance.product_instance)
+}
+
+fn legend_ui(ui: &mut egui::Ui) {
+    ui.horizontal(|ui| {
+        for (fill, text) in [(REACHED_FILL, "Reached"), (PENDING_FILL, "Awaited"), (BLOCKED_FILL, "Blocked by a wait link")] {
+            ui.label(RichText::new("⏺").color(fill));
+            ui.label(text);
+        }
+    });
+}
+
+fn draw(ui: &egui::Ui, painter: &egui::Painter, model: &Model, layout: &PsdLayout<'_>, origin: Pos2, states: &HashMap<(&TransactionId, CPAct), StepState>, counts: &HashMap<&TransactionId, usize>) -> Vec<(Rect, String)> {
+    let line_stroke = Stroke::new(1.0, ui.visuals().text_color());
+    let step_stroke = Stroke::new(1.0, Color32::BLACK);
+    let at = |(x, y): (f32, f32)| origin + vec2(x, y);
+    let arrow = |from: Pos2, to: Pos2, dashed: bool| {
+        let length = (to - from).length();
+        if length <= 2.0 * STEP_RADIUS {
+            return;
+        }
+        let direction = (to - from) / length;
+        let (start, end) = (from + direction * STEP_RADIUS, to - direction * (STEP_RADIUS + 8.0));
+        if dashed {
+            painter.extend(Shape::dashed_line(&[start, end], line_stroke, 6.0, 4.0));
+        } else {
+            painter.line_segment([start, end], line_stroke);
+        }
+        painter.arrow(end, direction * 8.0, line_stroke);
+    };
+
+    for link in &layout.links {
+        let (from, to) = (at(layout.step_center(link.from.0, &link.from.1)), at(layout.step_center(link.to.0, &link.to.1)));
+        match &link.kind {
+            LinkKind::Response(multiplicity) => {
+                arrow(from, to, false);
+                painter.text(from + (to - from) / 2.0 + vec2(5.0, 0.0), Align2::LEFT_CENTER, multiplicity, FontId::proportional(FONT_SIZE - 2.0), ui.visuals().weak_text_color());
+            }
+            LinkKind::Wait => arrow(from, to, true),
+        }
+    }
+    let mut hover_rects = Vec::new();
+    for transaction in &model.transactions {
+        let (_, y) = layout.lane_start(&transaction.id);
+        let count = counts.get(&transaction.id).copied().unwrap_or(0);
+        painter.text(at((MARGIN, y - 4.0)), Align2::LEFT_BOTTOM, format!("{} ({})", transaction.t_id, count), FontId::proportional(FONT_SIZE), ui.visuals().text_color());
+        painter.text(at((MARGIN, y + 2.0)), Align2::LEFT_TOP, &transaction.name, FontId::proportional(FONT_SIZE - 1.0), ui.visuals().text_color());
+        let t_steps = &layout.steps[&transaction.id];
+        for (position, act) in t_steps.iter().enumerate() {
+            let center = at(layout.step_center(&transaction.id, act));
+            if let Some(next) = t_steps.get(position + 1) {
+                arrow(center, at(layout.step_center(&transaction.id, next)), false);
+            }
+            let state_o = states.get(&(&transaction.id, act.clone()));
+            let fill = state_o.map_or(StepState::default().fill(act), |state| state.fill(act));
+            let r = STEP_RADIUS;
+            match act {
+                CPAct::PAct => { painter.add(Shape::convex_polygon(vec![center - vec2(0.0, r), center + vec2(r, 0.0), center + vec2(0.0, r), center - vec2(r, 0.0)], fill, step_stroke)); }
+                CPAct::CAct(_) => { painter.circle(center, r, fill, step_stroke); }
+            }
+            painter.text(center, Align2::CENTER_CENTER, act.abbreviation(), FontId::proportional(FONT_SIZE - 2.0), Color32::BLACK);
+            if let Some(state) = state_o {
+                hover_rects.push((Rect::from_center_size(center, vec2(2.0, 2.0) * r), format!("{} {}\n{}", transaction.t_id, act, state.describe())));
+            }
+        }
+    }
+    hover_rects
+}
+
+pub fn view(ui: &mut egui::Ui, app_context: &mut AppContext) {
+    let model = &app_context.model;
+    let execution = &app_context.execution;
+    let root_o = &mut app_context.psd_view_context.root_o;
+    if root_o.as_ref().is_some_and(|root_id| execution.find_transaction_instance(root_id).is_none()) {
+        *root_o = None;
+    }
+    let roots: Vec<&TransactionInstance> = execution.transactions_instances.iter().filter(|t_i| t_i.parent_transaction_instance_id.is_none()).collect();
+    ui.horizontal(|ui| {
+        ui.label("Show");
+        let selected_text = root_o.as_ref()
+            .and_then(|root_id| execution.find_transaction_instance(root_id))
+            .map_or("All transaction instances".to_string(), |root| format!("{} with its subtransactions", instance_text(model, root)));
+        egui::ComboBox::from_id_salt("PSD-Root")
+            .selected_text(selected_text)
+            .show_ui(ui, |ui| {
+                ui.style_mut().wrap_mode = Some(TextWrapMode::Extend);
+                ui.selectable_value(root_o, None, "All transaction instances");
+                for root in &roots {
+                    ui.selectable_value(root_o, Some(root.id.clone()), instance_text(model, root));
+                }
+            });
+    });
+    legend_ui(ui);
+    ui.separator();
+
+    let instances: Vec<&TransactionInstance> = execution.transactions_instances.iter()
+        .filter(|t_i| root_o.is_none() || root_o.as_ref() == Some(root_transaction_instance(execution, &t_i.id)))
+        .collect();
+    let mut counts: HashMap<&TransactionId, usize> = HashMap::new();
+    for transaction_instance in &instances {
+        *counts.entry(&transaction_instance.transaction_id).or_default() += 1;
+    }
+    let states = step_states(model, execution, &instances);
+    let layout = PsdLayout::new(model);
+    egui::ScrollArea::both().show(ui, |ui| {
+        let size = vec2(layout.width, layout.height).max(ui.available_size());
+        let (response, painter) = ui.allocate_painter(size, Sense::hover());
+        let hover_rects = draw(ui, &painter, model, &layout, response.rect.min, &states, &counts);
+        if let Some(pointer) = response.hover_pos() {
+            if let Some((_, text)) = hover_rects.iter().find(|(rect, _)| rect.contains(pointer)) {
+                response.on_hover_text_at_pointer(text);
+            }
+        }
+    });
+}
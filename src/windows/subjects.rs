@@ -1,9 +1,12 @@
 use eframe::epaint::Color32;
 use egui::{RichText, Vec2};
 use crate::model::Subject;
+use crate::validation::ModelEntity;
 
-pub fn subjects_ui(ui: &mut egui::Ui, subjects: &mut Vec<Subject>) {
+/// Returns whether the subjects were changed.
+pub fn subjects_ui(ui: &mut egui::Ui, subjects: &mut Vec<Subject>, hi_entity_o: &Option<ModelEntity>) -> bool {
     let mut to_delete = Vec::new();
+    let mut changed = false;
     egui::Grid::new("Subjects")
         .striped(true)
         .spacing(&[0.0, 5.0])
@@ -22,15 +25,22 @@ pub fn subjects_ui(ui: &mut egui::Ui, subjects: &mut Vec<Subject>) {
                             to_delete.push(index);
                         }
                 });
-                ui.add(egui::TextEdit::singleline(&mut subject.name).min_size(Vec2 { x: 200.0, y: 20.0 }));
+                let name_edit = ui.add(egui::TextEdit::singleline(&mut subject.name).min_size(Vec2 { x: 200.0, y: 20.0 }));
+                changed |= name_edit.changed();
+                if *hi_entity_o == Some(ModelEntity::Subject(subject.id.clone())) {
+                    name_edit.highlight();
+                }
                 ui.end_row();
             }
         });
+    changed |= !to_delete.is_empty();
     for index in to_delete.into_iter().rev() {
         subjects.remove(index);
     }
     ui.add_space(16.0);
     if ui.button(RichText::new("➕").color(Color32::GREEN)).clicked() {
         subjects.push(Subject::default());
+        changed = true;
     }
+    changed
 }
@@ -1,11 +1,15 @@
-use egui::TextWrapMode;
-use crate::app::AppContext;
-use crate::execution::{Execution, TransactionInstanceId};
-use crate::model::{CAct, CPAct, Model, SubjectId, Transaction, TransactionId};
+use std::collections::HashMap;
+use egui::{Color32, TextWrapMode};
+use strum::IntoEnumIterator;
+use crate::app::{AgendaFilter, AgendaSort, AppContext, SubjectContext};
+use crate::windows::due_label;
+use crate::execution::{AgendaItem, Execution, TransactionInstance, TransactionInstanceId};
+use crate::model::{CAct, CFact, CPAct, CPFact, Model, SubjectId, Transaction, TransactionId};
 
 #[inline]
 pub fn subjects_tabs_ui(ui: &mut egui::Ui, app_context: &mut AppContext) {
     let model = &app_context.model;
+    let execution = &app_context.execution;
     let subject_context = &mut app_context.subject_context;
     if model.subjects.is_empty() {
         subject_context.focused_subject_id_o = None;
@@ -18,8 +22,19 @@ pub fn subjects_tabs_ui(ui: &mut egui::Ui, app_context: &mut AppContext) {
             if let Some(fp_id) = subject_context.focused_subject_id_o.clone() {
                 let mut sel_fp_id = fp_id.clone();
                 ui.horizontal_wrapped(|ui| {
+                    let counts = waiting_counts(model, execution);
+                    let total: usize = counts.values().sum();
+                    if ui.selectable_label(subject_context.inbox, format!("📥 Inbox ({})", total)).clicked() {
+                        subject_context.inbox = true;
+                    }
+                    ui.separator();
                     for subject in &model.subjects {
-                        ui.selectable_value(&mut sel_fp_id, subject.id.clone(), &subject.name);
+                        let waiting = counts.get(&subject.id).copied().unwrap_or_default();
+                        let text = if waiting > 0 { format!("{} ({})", subject.name, waiting) } else { subject.name.clone() };
+                        if ui.selectable_label(!subject_context.inbox && sel_fp_id == subject.id, text).clicked() {
+                            sel_fp_id = subject.id.clone();
+                            subject_context.inbox = false;
+                        }
                     }
                 });
                 subject_context.focused_subject_id_o = Some(sel_fp_id);
@@ -66,23 +81,119 @@ fn startable_transactions_ui<F>(
     ui.separator();
 }
 
+// An item on a subject's agenda together with the acts the subject may perform on it
+struct AgendaRow {
+    subject_id: SubjectId,
+    item: AgendaItem,
+    transaction_instance: TransactionInstance,
+    next_acts: Vec<CPAct>,
+    actionable: bool, // at least one of the next acts, revocations aside, is not impeded
+}
+
+// The items waiting on the agenda of a subject, or of all subjects, in insertion order
+fn agenda_rows(model: &Model, execution: &Execution, subject_id_o: Option<&SubjectId>) -> Vec<AgendaRow> {
+    execution.agendas.iter()
+        .filter(|(subject_id, _)| subject_id_o.is_none() || subject_id_o == Some(subject_id))
+        .filter_map(|(subject_id, item)| {
+            let transaction_instance = execution.find_transaction_instance(&item.transaction_instance_id)?.clone();
+            let transaction = model.find_transaction(&transaction_instance.transaction_id)?;
+            let next_acts = execution.available_acts(&transaction_instance, subject_id);
+            if next_acts.is_empty() { return None; }
+            let actionable = next_acts.iter()
+                .filter(|act| !act.is_revocation())
+                .any(|act| execution.get_act_impediments(model, transaction, &Some(transaction_instance.id.clone()), act).is_none());
+            Some(AgendaRow { subject_id: subject_id.clone(), item: item.clone(), transaction_instance, next_acts, actionable })
+        })
+        .collect()
+}
+
+// Position of a fact in the course of a transaction, the production fact coming right after the promise
+fn fact_rank(fact: &CPFact) -> usize {
+    let position = |c_fact: &CFact| CFact::iter().position(|c_fact1| c_fact1 == *c_fact).unwrap_or_default();
+    match fact {
+        CPFact::CFact(c_fact) => 2 * position(c_fact),
+        CPFact::PFact => 2 * position(&CFact::Promised) + 1,
+    }
+}
+
+fn sort_agenda_rows(model: &Model, rows: &mut [AgendaRow], sort: AgendaSort) {
+    match sort {
+        AgendaSort::Timestamp => rows.sort_by_key(|row| row.item.timestamp),
+        AgendaSort::Transaction => rows.sort_by_key(|row| {
+            let t_id = model.find_transaction(&row.transaction_instance.transaction_id).map(|t| t.t_id.clone());
+            (t_id, row.item.timestamp)
+        }),
+        AgendaSort::Fact => rows.sort_by_key(|row| (fact_rank(&row.item.fact), row.item.timestamp)),
+    }
+}
+
+// Numbers of the items waiting on the agendas of the subjects (the rows of `agenda_rows`), shown on the tabs on
+// every frame, so counted in one pass
+fn waiting_counts<'a>(model: &Model, execution: &'a Execution) -> HashMap<&'a SubjectId, usize> {
+    let mut counts = HashMap::new();
+    for (subject_id, item) in execution.agendas.iter() {
+        let Some(transaction_instance) = execution.find_transaction_instance(&item.transaction_instance_id) else { continue };
+        if model.find_transaction(&transaction_instance.transaction_id).is_none() || execution.available_acts(transaction_instance, subject_id).is_empty() {
+            continue;
+        }
+        *counts.entry(subject_id).or_default() += 1;
+    }
+    counts
+}
+
+fn agenda_options_ui(ui: &mut egui::Ui, subject_context: &mut SubjectContext) {
+    ui.horizontal(|ui| {
+        ui.label("Sort by");
+        egui::ComboBox::from_id_salt("Agenda_sort")
+            .selected_text(subject_context.agenda_sort.to_string())
+            .show_ui(ui, |ui| {
+                for sort in AgendaSort::iter() {
+                    ui.selectable_value(&mut subject_context.agenda_sort, sort, sort.to_string());
+                }
+            });
+        ui.separator();
+        for filter in AgendaFilter::iter() {
+            ui.selectable_value(&mut subject_context.agenda_filter, filter, filter.to_string());
+        }
+    });
+}
+
+// The agenda of the given subject, or the combined inbox of all subjects without one
 #[inline]
 fn agenda_ui<F>(
     ui: &mut egui::Ui,
     app_context: &mut AppContext,
-    subject_id: &SubjectId,
+    subject_id_o: Option<&SubjectId>,
     modal_opened: bool,
-    mut open_modal: &mut F,
+    open_modal: &mut F,
 ) where F: FnMut(Option<TransactionInstanceId>, TransactionId) {
     let model = &app_context.model;
     let execution = &mut app_context.execution;
     let subject_context = &mut app_context.subject_context;
-    let agenda = execution.agenda_for(subject_id).clone();
+    agenda_options_ui(ui, subject_context);
+    ui.add_space(5.0);
+    let mut rows: Vec<AgendaRow> = agenda_rows(model, execution, subject_id_o).into_iter()
+        .filter(|row| match subject_context.agenda_filter {
+            AgendaFilter::All => true,
+            AgendaFilter::Actionable => row.actionable,
+            AgendaFilter::Blocked => !row.actionable,
+        })
+        .collect();
+    if rows.is_empty() {
+        ui.label("Nothing waiting");
+        return;
+    }
+    sort_agenda_rows(model, &mut rows, subject_context.agenda_sort);
+    let now = execution.now();
     egui::Grid::new("Subject's agenda")
         .striped(true)
         .spacing(&[10.0, 10.0])
         .show(ui, |ui| {
+            if subject_id_o.is_none() {
+                ui.strong("Subject");
+            }
             ui.strong("Timestamp");
+            ui.strong("Due");
             ui.strong("Transaction");
             ui.strong("Performer");
             ui.strong("Fact");
@@ -90,22 +201,26 @@ fn agenda_ui<F>(
             ui.strong("Act");
             ui.end_row();
 
-            for agenda_item in &agenda {
-                let transaction_instance = execution.get_transaction_instance(&agenda_item.transaction_instance_id).clone();
+            for row in rows {
+                let AgendaRow { subject_id, item: agenda_item, transaction_instance, next_acts, .. } = row;
                 let transaction = model.get_transaction(&transaction_instance.transaction_id);
                 let performer = model.get_subject(&agenda_item.performer_id);
-                let next_acts = agenda_item.fact.next_acts();
                 let mut selected_next_act = subject_context.get_selected_next_act(&subject_id, &transaction_instance.id)
+                    .filter(|act| next_acts.contains(act))
                     .unwrap_or(&next_acts[0].clone()).to_owned();
                 let mut committed = false;
                 let impediments_msgs_o = execution.get_act_impediments(model, &transaction, &Some(transaction_instance.id.clone()), &selected_next_act).map(|msgs| msgs.join("\n"));
 
+                if subject_id_o.is_none() {
+                    ui.label(model.get_subject(&subject_id).name.clone());
+                }
                 ui.label(agenda_item.timestamp.to_string());
+                due_label(ui, &agenda_item.due, &now);
                 ui.label(format!("{}: {}", transaction.t_id.to_string(), transaction.name.clone()));
                 ui.label(performer.name.clone());
                 ui.label(agenda_item.fact.to_string());
                 ui.label(transaction_instance.product_instance.clone());
-                egui::ComboBox::from_id_salt(format!("Act for Fact {}", transaction_instance.id))
+                egui::ComboBox::from_id_salt(format!("Act for Fact {} {}", subject_id, transaction_instance.id))
                     .selected_text(selected_next_act.to_string())
                     .show_ui(ui, |ui| {
                         ui.style_mut().wrap_mode = Some(TextWrapMode::Extend);
@@ -118,18 +233,92 @@ fn agenda_ui<F>(
                     if ui.button("Commit")
                         .on_disabled_hover_text(impediments_msgs_o.unwrap_or_default())
                         .clicked() {
-                            execution.process_new_fact(model, transaction_instance.id.clone(), subject_id.clone(), selected_next_act.to_fact());
-                            execution.remove_agenda_item(agenda_item);
+                            subject_context.error_o = execution.process_new_fact(model, transaction_instance.id.clone(), subject_id.clone(), selected_next_act.to_fact())
+                                .err().map(|e| e.to_string());
                             committed = true;
                         }
                 });
                 if committed {
-                    subject_context.clear_selected_next_act(subject_id, &transaction_instance.id);
+                    subject_context.clear_selected_next_act(&subject_id, &transaction_instance.id);
                 } else {
                     subject_context.selected_next_act.insert((subject_id.clone(), transaction_instance.id.clone()), selected_next_act);
                 }
-                let startable_subtransactions = execution.startable_subtransactions(model, &transaction_instance, subject_id);
-                initiate_transactions_ui(ui, model, execution, &startable_subtransactions, &Some(transaction_instance.id), modal_opened, &mut open_modal);
+                // The subtransaction is initiated by the subject whose agenda the item is on
+                let mut initiating = false;
+                let startable_subtransactions = execution.startable_subtransactions(model, &transaction_instance, &subject_id).unwrap_or_default();
+                initiate_transactions_ui(ui, model, execution, &startable_subtransactions, &Some(transaction_instance.id), modal_opened, &mut |parent_o, transaction_id| {
+                    initiating = true;
+                    open_modal(parent_o, transaction_id);
+                });
+                if initiating {
+                    subject_context.focused_subject_id_o = Some(subject_id);
+                }
+                ui.end_row();
+            }
+        });
+}
+
+#[inline]
+fn revocations_ui(
+    ui: &mut egui::Ui,
+    app_context: &mut AppContext,
+    subject_id: &SubjectId,
+) {
+    let model = &app_context.model;
+    let execution = &mut app_context.execution;
+    let subject_context = &mut app_context.subject_context;
+    let transaction_instances: Vec<TransactionInstance> = execution.revocable_transaction_instances(subject_id).into_iter().cloned().collect();
+    if transaction_instances.is_empty() {
+        ui.label("Nothing to revoke");
+        return;
+    }
+    egui::Grid::new("Subject's revocations")
+        .striped(true)
+        .spacing([10.0, 10.0])
+        .show(ui, |ui| {
+            ui.strong("Transaction");
+            ui.strong("Fact");
+            ui.strong("Product Instance");
+            ui.strong("Act");
+            ui.end_row();
+
+            for transaction_instance in &transaction_instances {
+                let transaction = model.get_transaction(&transaction_instance.transaction_id);
+                let revoke_acts: Vec<CPAct> = execution.available_acts(transaction_instance, subject_id).into_iter()
+                    .filter(|act| act.is_revocation())
+                    .collect();
+                let mut selected_act = subject_context.get_selected_next_act(subject_id, &transaction_instance.id)
+                    .filter(|act| revoke_acts.contains(act))
+                    .unwrap_or(&revoke_acts[0]).to_owned();
+                let mut committed = false;
+                let impediments_msgs_o = execution.get_act_impediments(model, transaction, &Some(transaction_instance.id.clone()), &selected_act).map(|msgs| msgs.join("\n"));
+
+                ui.label(format!("{}: {}", transaction.t_id, transaction.name));
+                ui.label(execution.get_state(&transaction_instance.id).map(|fact| fact.to_string()).unwrap_or_default());
+                ui.label(transaction_instance.product_instance.clone());
+                egui::ComboBox::from_id_salt(format!("Revocation for {}", transaction_instance.id))
+                    .selected_text(selected_act.to_string())
+                    .show_ui(ui, |ui| {
+                        ui.style_mut().wrap_mode = Some(TextWrapMode::Extend);
+                        ui.set_min_width(60.0);
+                        for act in revoke_acts {
+                            ui.selectable_value(&mut selected_act, act.clone(), act.to_string());
+                        }
+                    });
+                ui.add_enabled_ui(impediments_msgs_o.is_none(), |ui| {
+                    if ui.button("Commit")
+                        .on_disabled_hover_text(impediments_msgs_o.unwrap_or_default())
+                        .clicked() {
+                            subject_context.error_o = execution.process_new_fact(model, transaction_instance.id.clone(), subject_id.clone(), selected_act.to_fact())
+                                .err().map(|e| e.to_string());
+                            committed = true;
+                        }
+                });
+                if committed {
+                    subject_context.clear_selected_next_act(subject_id, &transaction_instance.id);
+                } else {
+                    subject_context.selected_next_act.insert((subject_id.clone(), transaction_instance.id.clone()), selected_act);
+                }
                 ui.end_row();
             }
         });
@@ -143,11 +332,37 @@ pub fn subject_pane_ui<F>(
     modal_opened: bool,
     mut open_modal: F,
 ) where F: FnMut(Option<TransactionInstanceId>, TransactionId) {
+    if let Some(error) = &app_context.subject_context.error_o {
+        ui.colored_label(Color32::RED, error);
+        ui.add_space(5.0);
+    }
     ui.strong("Initiate transaction");
     ui.add_space(5.0);
     startable_transactions_ui(ui, app_context, subject_id, &None, modal_opened, &mut open_modal);
     ui.strong("Agenda");
     ui.add_space(5.0);
-    agenda_ui(ui, app_context, subject_id, modal_opened, &mut open_modal);
+    agenda_ui(ui, app_context, Some(subject_id), modal_opened, &mut open_modal);
+    ui.add_space(10.0);
+    ui.separator();
+    ui.strong("Revocations");
+    ui.add_space(5.0);
+    revocations_ui(ui, app_context, subject_id);
 }
 
+
+/// The agendas of all subjects combined, so that a whole session can be run from one place.
+#[inline]
+pub fn inbox_pane_ui<F>(
+    ui: &mut egui::Ui,
+    app_context: &mut AppContext,
+    modal_opened: bool,
+    mut open_modal: F,
+) where F: FnMut(Option<TransactionInstanceId>, TransactionId) {
+    if let Some(error) = &app_context.subject_context.error_o {
+        ui.colored_label(Color32::RED, error);
+        ui.add_space(5.0);
+    }
+    ui.strong("Inbox");
+    ui.add_space(5.0);
+    agenda_ui(ui, app_context, None, modal_opened, &mut open_modal);
+}
@@ -0,0 +1,221 @@
+use chrono::{DateTime, Duration, Utc};
+use egui::{pos2, vec2, Align2, Color32, FontId, Rect, RichText, Sense, Shape, Stroke};
+use crate::app::AppContext;
+use crate::execution::{CPWorldItem, Execution, TransactionInstanceId};
+use crate::model::{CFact, CPFact, Model, SubjectId};
+
+const LABEL_WIDTH: f32 = 220.0;
+const LANE_HEIGHT: f32 = 28.0;
+const AXIS_HEIGHT: f32 = 24.0;
+const MARKER_RADIUS: f32 = 5.0;
+const FONT_SIZE: f32 = 12.0;
+const ORDER_FILL: Color32 = Color32::from_rgb(140, 180, 230);
+const EXECUTION_FILL: Color32 = Color32::from_rgb(244, 170, 160);
+const RESULT_FILL: Color32 = Color32::from_rgb(150, 210, 150);
+const OTHER_FILL: Color32 = Color32::from_gray(190);
+
+enum Lane<'a> {
+    TransactionInstance(&'a TransactionInstanceId, usize), // with its depth in the tree
+    Subject(&'a SubjectId),
+}
+
+// Transaction instances in the depth-first order of their trees, or the subjects acting in the execution
+fn lanes<'a>(model: &'a Model, execution: &'a Execution, by_subject: bool) -> Vec<Lane<'a>> {
+    if by_subject {
+        return model.subjects.iter()
+            .filter(|s| execution.c_p_world.iter().any(|item| item.get_performer().id == s.id))
+            .map(|s| Lane::Subject(&s.id))
+            .collect();
+    }
+    let mut lanes = Vec::new();
+    let is_root = |parent_o: &Option<TransactionInstanceId>| parent_o.as_ref().and_then(|parent_id| execution.find_transaction_instance(parent_id)).is_none();
+    let mut stack: Vec<(&TransactionInstanceId, usize)> = execution.transactions_instances.iter().rev()
+        .filter(|t_i| is_root(&t_i.parent_transaction_instance_id))
+        .map(|t_i| (&t_i.id, 0))
+        .collect();
+    while let Some((id, depth)) = stack.pop() {
+        lanes.push(Lane::TransactionInstance(id, depth));
+        stack.extend(execution.transactions_instances.iter().rev()
+            .filter(|t_i| t_i.parent_transaction_instance_id.as_ref() == Some(id))
+            .map(|t_i| (&t_i.id, depth + 1)));
+    }
+    lanes
+}
+
+fn lane_text(model: &Model, execution: &Execution, lane: &Lane<'_>) -> String {
+    match lane {
+        Lane::TransactionInstance(id, depth) => {
+            let Some(t_i) = execution.find_transaction_instance(id) else { return String::new() };
+            let t_id = model.find_transaction(&t_i.transaction_id).map(|t| t.t_id.clone()).unwrap_or_default();
+            format!("{}{} {}", "  ".repeat(*depth), t_id, t_i.product_instance)
+        }
+        Lane::Subject(subject_id) => model.find_subject(subject_id).map(|s| s.name.clone()).unwrap_or_default(),
+    }
+}
+
+// The phase of the transaction from the fact until the next one
+fn phase_fill(fact: &CPFact) -> Color32 {
+    match fact {
+        CPFact::CFact(CFact::Requested) => ORDER_FILL,
+        CPFact::CFact(CFact::Promised) | CPFact::PFact => EXECUTION_FILL,
+        CPFact::CFact(CFact::Declared) => RESULT_FILL,
+        _ => OTHER_FILL,
+    }
+}
+
+fn fact_abbreviation(fact: &CPFact) -> &'static str {
+    match fact {
+        CPFact::CFact(c_fact) => c_fact.to_act().abbreviation(),
+        CPFact::PFact => "ex",
+    }
+}
+
+// A bar from an item to the next one of its transaction instance, or to now if the instance is still open; in the
+// lanes of subjects it is on the lane of the subject acting next, who was the one awaited
+struct Bar {
+    lane: usize,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    fact: CPFact,
+    open: bool,
+}
+
+fn bars(execution: &Execution, lanes: &[Lane<'_>], by_subject: bool, now: DateTime<Utc>) -> Vec<Bar> {
+    let lane_of_instance = |id: &TransactionInstanceId| lanes.iter().position(|lane| matches!(lane, Lane::TransactionInstance(lane_id, _) if *lane_id == id));
+    let lane_of_subject = |id: &SubjectId| lanes.iter().position(|lane| matches!(lane, Lane::Subject(lane_id) if *lane_id == id));
+    let mut bars = Vec::new();
+    for t_i in &execution.transactions_instances {
+        let items = execution.get_facts_for_transaction_instance(&t_i.id);
+        for (index, item) in items.iter().enumerate() {
+            let fact = item.to_fact();
+            let (to, next_performer_o) = match items.get(index + 1) {
+                Some(next) => (*next.get_timestamp(), Some(&next.get_performer().id)),
+                None if !matches!(&fact, CPFact::CFact(c_fact) if c_fact.is_terminal()) => {
+                    let awaited_o = execution.agendas.iter().find(|(_, agenda_item)| agenda_item.transaction_instance_id == t_i.id).map(|(subject_id, _)| subject_id);
+                    (now, awaited_o)
+                }
+                None => continue,
+            };
+            let lane_o = if by_subject { next_performer_o.and_then(lane_of_subject) } else { lane_of_instance(&t_i.id) };
+            if let Some(lane) = lane_o {
+                bars.push(Bar { lane, from: *item.get_timestamp(), to, open: index + 1 == items.len(), fact });
+            }
+        }
+    }
+    bars
+}
+
+// Ticks of the time axis at least 80 points apart
+fn tick_interval(pixels_per_hour: f32) -> Duration {
+    [1, 5, 15, 30, 60, 180, 360, 720, 1440, 10080].into_iter()
+        .map(Duration::minutes)
+        .find(|interval| interval.num_minutes() as f32 / 60.0 * pixels_per_hour >= 80.0)
+        .unwrap_or(Duration::weeks(4))
+}
+
+fn legend_ui(ui: &mut egui::Ui) {
+    ui.horizontal(|ui| {
+        for (fill, text) in [(ORDER_FILL, "Order (request to promise)"), (EXECUTION_FILL, "Execution (promise to declare)"), (RESULT_FILL, "Result (declare to accept)"), (OTHER_FILL, "Other")] {
+            ui.label(RichText::new("⏹").color(fill));
+            ui.label(text);
+        }
+    });
+}
+
+pub fn view(ui: &mut egui::Ui, app_context: &mut AppContext) {
+    let model = &app_context.model;
+    let execution = &app_context.execution;
+    let context = &mut app_context.timeline_context;
+    let Some(start) = execution.c_p_world.iter().map(|item| *item.get_timestamp()).min() else {
+        ui.label("Nothing has happened yet");
+        return;
+    };
+    let now = execution.now().max(start);
+    let span_hours = ((now - start).num_seconds() as f32 / 3600.0).max(1.0 / 60.0);
+    ui.horizontal(|ui| {
+        ui.selectable_value(&mut context.by_subject, false, "Per transaction instance");
+        ui.selectable_value(&mut context.by_subject, true, "Per subject");
+        ui.separator();
+        ui.label("Zoom");
+        ui.add(egui::DragValue::new(&mut context.pixels_per_hour).range(0.1..=100_000.0).speed(1.0).suffix(" pt/h"));
+        if ui.button("Fit").clicked() {
+            context.pixels_per_hour = ((ui.available_width() - LABEL_WIDTH).max(200.0) / span_hours).clamp(0.1, 100_000.0);
+        }
+    });
+    legend_ui(ui);
+    ui.label(RichText::new("Ctrl + scroll to zoom, drag or scroll to pan.").color(Color32::DARK_GRAY));
+    ui.separator();
+
+    let lanes = lanes(model, execution, context.by_subject);
+    let bars = bars(execution, &lanes, context.by_subject, now);
+    let pixels_per_hour = context.pixels_per_hour;
+    let mut zoom = 1.0;
+    egui::ScrollArea::both().show(ui, |ui| {
+        let size = vec2(LABEL_WIDTH + span_hours * pixels_per_hour + 40.0, AXIS_HEIGHT + lanes.len() as f32 * LANE_HEIGHT + 10.0);
+        let (response, painter) = ui.allocate_painter(size.max(ui.available_size()), Sense::hover());
+        let origin = response.rect.min;
+        let x_of = |time: DateTime<Utc>| origin.x + LABEL_WIDTH + (time - start).num_seconds() as f32 / 3600.0 * pixels_per_hour;
+        let lane_y = |lane: usize| origin.y + AXIS_HEIGHT + lane as f32 * LANE_HEIGHT + LANE_HEIGHT / 2.0;
+        let text_color = ui.visuals().text_color();
+        let grid_stroke = Stroke::new(1.0, ui.visuals().widgets.noninteractive.bg_stroke.color);
+
+        // Time axis with a grid line on each tick
+        let interval = tick_interval(pixels_per_hour);
+        let first_visible_hours = ((ui.clip_rect().left() - origin.x - LABEL_WIDTH) / pixels_per_hour).max(0.0);
+        let skipped = (first_visible_hours * 60.0) as i64 / interval.num_minutes();
+        let mut tick = start + interval * skipped as i32;
+        while tick <= now && x_of(tick) <= ui.clip_rect().right() {
+            let x = x_of(tick);
+            painter.line_segment([pos2(x, origin.y + AXIS_HEIGHT - 4.0), pos2(x, response.rect.bottom())], grid_stroke);
+            painter.text(pos2(x + 2.0, origin.y + 2.0), Align2::LEFT_TOP, tick.format("%m-%d %H:%M").to_string(), FontId::proportional(FONT_SIZE - 1.0), ui.visuals().weak_text_color());
+            tick += interval;
+        }
+        painter.line_segment([pos2(x_of(now), origin.y + AXIS_HEIGHT - 4.0), pos2(x_of(now), response.rect.bottom())], Stroke::new(1.5, Color32::RED));
+
+        for bar in &bars {
+            let y = lane_y(bar.lane);
+            let rect = Rect::from_min_max(pos2(x_of(bar.from), y - LANE_HEIGHT / 4.0), pos2(x_of(bar.to).max(x_of(bar.from) + 1.0), y + LANE_HEIGHT / 4.0));
+            let fill = phase_fill(&bar.fact);
+            let fill = if bar.open { fill.gamma_multiply(0.5) } else { fill };
+            painter.rect_filled(rect, 2.0, fill);
+        }
+        let mut hover_o = None;
+        for (lane_index, lane) in lanes.iter().enumerate() {
+            let y = lane_y(lane_index);
+            let items = execution.c_p_world.iter().filter(|item| match lane {
+                Lane::TransactionInstance(id, _) => item.get_transaction_instance_id() == *id,
+                Lane::Subject(id) => item.get_performer().id == **id,
+            });
+            for item in items {
+                let center = pos2(x_of(*item.get_timestamp()), y);
+                let fact = item.to_fact();
+                let r = MARKER_RADIUS;
+                match item {
+                    CPWorldItem::CWorldItem(_) => { painter.circle(center, r, Color32::WHITE, Stroke::new(1.0, Color32::BLACK)); }
+                    CPWorldItem::PWorldItem(_) => { painter.add(Shape::convex_polygon(vec![center - vec2(0.0, r + 1.0), center + vec2(r + 1.0, 0.0), center + vec2(0.0, r + 1.0), center - vec2(r + 1.0, 0.0)], EXECUTION_FILL, Stroke::new(1.0, Color32::BLACK))); }
+                }
+                painter.text(center - vec2(0.0, r + 1.0), Align2::CENTER_BOTTOM, fact_abbreviation(&fact), FontId::proportional(FONT_SIZE - 2.0), text_color);
+                if response.hover_pos().is_some_and(|pointer| pointer.distance(center) <= r + 2.0) {
+                    let transaction_instance_o = execution.find_transaction_instance(item.get_transaction_instance_id());
+                    let t_id = transaction_instance_o.and_then(|t_i| model.find_transaction(&t_i.transaction_id)).map(|t| t.t_id.clone()).unwrap_or_default();
+                    hover_o = Some(format!("{} {} by {}\n{}", t_id, fact, item.get_performer().name, item.get_timestamp()));
+                }
+            }
+        }
+        // The labels stay at the left edge of the visible part
+        let left = ui.clip_rect().left().max(origin.x);
+        painter.rect_filled(Rect::from_min_max(pos2(left, origin.y + AXIS_HEIGHT), pos2(left + LABEL_WIDTH - 10.0, response.rect.bottom())), 0.0, ui.visuals().window_fill());
+        for (lane_index, lane) in lanes.iter().enumerate() {
+            painter.text(pos2(left + 4.0, lane_y(lane_index)), Align2::LEFT_CENTER, lane_text(model, execution, lane), FontId::proportional(FONT_SIZE), text_color);
+        }
+        if let Some(text) = hover_o {
+            response.clone().on_hover_text_at_pointer(text);
+        }
+        if response.hovered() {
+            zoom = ui.input(|i| i.zoom_delta());
+        }
+    });
+    if zoom != 1.0 {
+        context.pixels_per_hour = (context.pixels_per_hour * zoom).clamp(0.1, 100_000.0);
+    }
+}
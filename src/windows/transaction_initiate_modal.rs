@@ -1,4 +1,4 @@
-use egui::TextWrapMode;
+use egui::{Color32, TextWrapMode};
 use crate::app::AppContext;
 use crate::execution::TransactionInstance;
 
@@ -49,10 +49,15 @@ pub fn view<F: FnMut()>(ui: &mut egui::Ui, app_context: &mut AppContext, mut clo
             ui.add_enabled_ui(!modal_context.requested_product.is_empty() && modal_context.addressee_id_o.is_some(), |ui| {
                 if ui.button("Request product").clicked() {
                     let t_i = TransactionInstance::new(modal_context.parent_transaction_instance_id.clone(), transaction.id.clone(), modal_context.requested_product.clone(), performer.id.clone(), modal_context.addressee_id_o.clone().unwrap());
-                    execution.add_transaction_instance(model, t_i);
-                    close_modal();
+                    match execution.add_transaction_instance(model, t_i) {
+                        Ok(()) => close_modal(),
+                        Err(e) => modal_context.error_o = Some(e.to_string()),
+                    }
                 }
             });
+            if let Some(error) = &modal_context.error_o {
+                ui.colored_label(Color32::RED, error);
+            }
         }
     }
 }
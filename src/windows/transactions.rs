@@ -1,13 +1,16 @@
 use eframe::epaint::Color32;
 use egui::{RichText, TextWrapMode};
 use std::collections::{HashMap, HashSet};
-use crate::model::{all_acts, all_c_acts, all_c_facts, ActorRole, CAct, CFact, CPAct, Impediment, Initiation, Multiplicity, Transaction, TransactionId};
+use crate::validation::ModelEntity;
+use crate::model::{all_acts, all_c_acts, all_c_facts, all_tacit_candidate_acts, ActorRole, CAct, CFact, CPAct, Deadline, Impediment, Initiation, Multiplicity, Transaction, TransactionId};
 
-pub fn initiations_ui(ui: &mut egui::Ui, transactions: &Vec<Transaction>, transaction: &mut Transaction) {
+/// Returns whether the initiations were changed.
+pub fn initiations_ui(ui: &mut egui::Ui, transactions: &[Transaction], transaction: &mut Transaction) -> bool {
     let available_transactions: Vec<&Transaction> = transactions.iter().filter(|tr| **tr != *transaction).collect();
     let transactions_map: HashMap<TransactionId, String> = available_transactions.iter()
         .map(|tr| (tr.id.clone(), tr.t_id.clone())).collect();
     let mut to_delete = Vec::new();
+    let mut changed = false;
 
     ui.vertical(|ui| {
         for (in_index, initiation) in transaction.initiations.iter_mut().enumerate() {
@@ -19,7 +22,7 @@ pub fn initiations_ui(ui: &mut egui::Ui, transactions: &Vec<Transaction>, transa
                     .selected_text(initiation.initiating_c_fact.to_string())
                     .show_ui(ui, |ui| {
                         for initiating_fact in all_c_facts() {
-                            ui.selectable_value(&mut initiation.initiating_c_fact, initiating_fact.to_owned(), initiating_fact.to_string());
+                            changed |= ui.selectable_value(&mut initiation.initiating_c_fact, initiating_fact.to_owned(), initiating_fact.to_string()).changed();
                         }
                     });
                 ui.add_space(5.0);
@@ -27,7 +30,7 @@ pub fn initiations_ui(ui: &mut egui::Ui, transactions: &Vec<Transaction>, transa
                     .selected_text(transactions_map.get(&initiation.initiated_transaction_id).unwrap_or(&"tr not found".to_string()).to_owned())
                     .show_ui(ui, |ui| {
                         for transaction in available_transactions.iter() {
-                            ui.selectable_value(&mut initiation.initiated_transaction_id, transaction.id.clone(), transactions_map.get(&transaction.id).unwrap_or(&"tr not found".to_string()));
+                            changed |= ui.selectable_value(&mut initiation.initiated_transaction_id, transaction.id.clone(), transactions_map.get(&transaction.id).unwrap_or(&"tr not found".to_string())).changed();
                         }
                     });
                 ui.add_space(5.0);
@@ -35,7 +38,7 @@ pub fn initiations_ui(ui: &mut egui::Ui, transactions: &Vec<Transaction>, transa
                     .selected_text(initiation.initiated_c_act.to_string())
                     .show_ui(ui, |ui| {
                         for c_act in all_c_acts().iter() {
-                            ui.selectable_value(&mut initiation.initiated_c_act, c_act.clone(), c_act.to_string());
+                            changed |= ui.selectable_value(&mut initiation.initiated_c_act, c_act.clone(), c_act.to_string()).changed();
                         }
                     });
                 ui.add_space(5.0);
@@ -47,7 +50,7 @@ pub fn initiations_ui(ui: &mut egui::Ui, transactions: &Vec<Transaction>, transa
                     Err(_) => Some(Color32::RED),
                     Ok(_) => None,
                 };
-                ui.add(egui::TextEdit::singleline(&mut initiation.multiplicity_tmp_str).min_size([50.0, 20.0].into()).text_color_opt(color));
+                changed |= ui.add(egui::TextEdit::singleline(&mut initiation.multiplicity_tmp_str).min_size([50.0, 20.0].into()).text_color_opt(color)).changed();
                 if let Ok(multiplicity) = multiplicity_r {
                     initiation.multiplicity = multiplicity;
                 }
@@ -61,18 +64,23 @@ pub fn initiations_ui(ui: &mut egui::Ui, transactions: &Vec<Transaction>, transa
                 multiplicity: Multiplicity::default(),
                 multiplicity_tmp_str: Multiplicity::default().to_string(),
             });
+            changed = true;
         }
+        changed |= !to_delete.is_empty();
         for index in to_delete.into_iter().rev() {
             transaction.initiations.remove(index);
         }
     });
+    changed
 }
 
-pub fn impediments_ui(ui: &mut egui::Ui, transactions: &Vec<Transaction>, transaction: &mut Transaction) {
+/// Returns whether the wait links were changed.
+pub fn impediments_ui(ui: &mut egui::Ui, transactions: &[Transaction], transaction: &mut Transaction) -> bool {
     let available_transactions: Vec<&Transaction> = transactions.iter().filter(|tr| **tr != *transaction).collect();
     let transactions_map: HashMap<TransactionId, String> = available_transactions.iter()
         .map(|tr| (tr.id.clone(), tr.t_id.clone())).collect();
     let mut to_delete = Vec::new();
+    let mut changed = false;
 
     ui.vertical(|ui| {
         for (imp_index, impediment) in transaction.impediments.iter_mut().enumerate() {
@@ -84,7 +92,7 @@ pub fn impediments_ui(ui: &mut egui::Ui, transactions: &Vec<Transaction>, transa
                     .selected_text(impediment.impeded_act.to_string())
                     .show_ui(ui, |ui| {
                         for impeded_act in all_acts() {
-                            ui.selectable_value(&mut impediment.impeded_act, impeded_act.to_owned(), impeded_act.to_string());
+                            changed |= ui.selectable_value(&mut impediment.impeded_act, impeded_act.to_owned(), impeded_act.to_string()).changed();
                         }
                     });
                 ui.add_space(5.0);
@@ -92,14 +100,14 @@ pub fn impediments_ui(ui: &mut egui::Ui, transactions: &Vec<Transaction>, transa
                     .selected_text(transactions_map.get(&impediment.impeding_transaction_id).unwrap_or(&"tr not found".to_string()).to_owned())
                     .show_ui(ui, |ui| {
                         for transaction in available_transactions.iter() {
-                            ui.selectable_value(&mut impediment.impeding_transaction_id, transaction.id.clone(), transactions_map.get(&transaction.id).unwrap_or(&"tr not found".to_string()));
+                            changed |= ui.selectable_value(&mut impediment.impeding_transaction_id, transaction.id.clone(), transactions_map.get(&transaction.id).unwrap_or(&"tr not found".to_string())).changed();
                         }
                     });
                 egui::ComboBox::from_id_salt(format!("{}_{}_{}", transaction.id, imp_index, "Impeding-CFact"))
                     .selected_text(impediment.impeding_c_fact.to_string())
                     .show_ui(ui, |ui| {
                         for c_fact in all_c_facts().iter() {
-                            ui.selectable_value(&mut impediment.impeding_c_fact, c_fact.clone(), c_fact.to_string());
+                            changed |= ui.selectable_value(&mut impediment.impeding_c_fact, c_fact.clone(), c_fact.to_string()).changed();
                         }
                     });
             });
@@ -110,15 +118,90 @@ pub fn impediments_ui(ui: &mut egui::Ui, transactions: &Vec<Transaction>, transa
                     impeding_transaction_id: available_transactions[0].id.clone(),
                     impeding_c_fact: CFact::default(),
             });
+            changed = true;
         }
+        changed |= !to_delete.is_empty();
         for index in to_delete.into_iter().rev() {
             transaction.impediments.remove(index);
         }
     });
+    changed
 }
 
-pub fn transactions_ui(ui: &mut egui::Ui, actor_roles: &Vec<ActorRole>, transactions: &mut Vec<Transaction>) {
+/// Returns whether the deadlines were changed.
+pub fn deadlines_ui(ui: &mut egui::Ui, transaction: &mut Transaction) -> bool {
     let mut to_delete = Vec::new();
+    let mut changed = false;
+    let option_text = |act_o: &Option<CPAct>| act_o.as_ref().map_or_else(|| "Any act".to_string(), |act| act.to_string());
+    ui.vertical(|ui| {
+        for (dl_index, deadline) in transaction.deadlines.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                if ui.button(RichText::new("❌").color(Color32::RED)).clicked() {
+                    to_delete.push(dl_index);
+                }
+                egui::ComboBox::from_id_salt(format!("{}_{}_{}", transaction.id, "Deadline-Act", dl_index))
+                    .selected_text(option_text(&deadline.act))
+                    .show_ui(ui, |ui| {
+                        changed |= ui.selectable_value(&mut deadline.act, None, option_text(&None)).changed();
+                        for act in all_acts().into_iter().filter(|act| !act.is_revocation()) {
+                            changed |= ui.selectable_value(&mut deadline.act, Some(act.clone()), act.to_string()).changed();
+                        }
+                    });
+                ui.label("within");
+                let within_minutes = &mut deadline.within_minutes;
+                changed |= ui.add(egui::DragValue::from_get_set(|hours_o: Option<f64>| {
+                        if let Some(hours) = hours_o {
+                            *within_minutes = (hours * 60.0).round().max(1.0) as i64;
+                        }
+                        *within_minutes as f64 / 60.0
+                    })
+                    .speed(0.25)
+                    .suffix(" h")).changed();
+                egui::ComboBox::from_id_salt(format!("{}_{}_{}", transaction.id, dl_index, "Deadline-On-Expiry"))
+                    .selected_text(deadline.on_expiry.as_ref().map_or_else(|| "No action".to_string(), |c_act| c_act.to_string()))
+                    .show_ui(ui, |ui| {
+                        changed |= ui.selectable_value(&mut deadline.on_expiry, None, "No action").changed();
+                        for c_act in all_c_acts().into_iter().filter(|c_act| *c_act != CAct::Request) {
+                            changed |= ui.selectable_value(&mut deadline.on_expiry, Some(c_act.clone()), c_act.to_string()).changed();
+                        }
+                    });
+            });
+        }
+        if ui.button(RichText::new("➕").color(Color32::GREEN)).clicked() {
+            transaction.deadlines.push(Deadline::default());
+            changed = true;
+        }
+        changed |= !to_delete.is_empty();
+        for index in to_delete.into_iter().rev() {
+            transaction.deadlines.remove(index);
+        }
+    });
+    changed
+}
+
+/// Returns whether the tacit acts were changed.
+pub fn tacit_acts_ui(ui: &mut egui::Ui, transaction: &mut Transaction) -> bool {
+    let mut changed = false;
+    ui.vertical(|ui| {
+        for c_act in all_tacit_candidate_acts() {
+            let mut is_tacit = transaction.tacit_acts.contains(&c_act);
+            if ui.checkbox(&mut is_tacit, c_act.to_string()).changed() {
+                changed = true;
+                if is_tacit {
+                    transaction.tacit_acts.push(c_act);
+                } else {
+                    transaction.tacit_acts.retain(|c_act1| *c_act1 != c_act);
+                }
+            }
+        }
+    });
+    changed
+}
+
+/// Returns whether the transactions were changed.
+pub fn transactions_ui(ui: &mut egui::Ui, actor_roles: &Vec<ActorRole>, transactions: &mut Vec<Transaction>, hi_entity_o: &Option<ModelEntity>) -> bool {
+    let mut to_delete = Vec::new();
+    let mut changed = false;
     egui::Grid::new("Actor Roles")
         .striped(true)
         .spacing(&[10.0, 40.0])
@@ -131,6 +214,8 @@ pub fn transactions_ui(ui: &mut egui::Ui, actor_roles: &Vec<ActorRole>, transact
             ui.strong("Executor");
             ui.strong("Initiations\nInitiating C-Fact | Initiated Transaction | Iniciated C-Act | Multiplicity");
             ui.strong("Wait Links\nImpeded Fact | Impeding Transaction | Impeding C-Fact");
+            ui.strong("Tacit Acts");
+            ui.strong("Deadlines\nAct | Within | On Expiry");
             ui.end_row();
 
             let used_executors: HashSet<_> = transactions.iter().map(|tr| tr.executor_id.clone()).collect();
@@ -138,7 +223,7 @@ pub fn transactions_ui(ui: &mut egui::Ui, actor_roles: &Vec<ActorRole>, transact
                 .filter(|aar| !used_executors.contains(&aar.id))
                 .collect();
             let transactions_cloned = transactions.to_owned();
-            for (t_index, mut transaction) in transactions.iter_mut().enumerate() {
+            for (t_index, transaction) in transactions.iter_mut().enumerate() {
                 let initiator: Option<ActorRole> = actor_roles.iter().find(|ar| ar.id == transaction.initiator_id).cloned();
                 let executor: Option<ActorRole> = actor_roles.iter().find(|ar| ar.id == transaction.executor_id).cloned();
                 let can_delete = !transactions_cloned.iter().any(|tr| tr.impediments.iter().any(|imp| imp.impeding_transaction_id == transaction.id));
@@ -149,9 +234,13 @@ pub fn transactions_ui(ui: &mut egui::Ui, actor_roles: &Vec<ActorRole>, transact
                         to_delete.push(t_index);
                     }
                 });
-                ui.add(egui::TextEdit::singleline(&mut transaction.t_id).min_size([50.0, 20.0].into()));
-                ui.add(egui::TextEdit::singleline(&mut transaction.name).min_size([200.0, 20.0].into()));
-                ui.add(egui::TextEdit::singleline(&mut transaction.product).min_size([200.0, 20.0].into()));
+                let t_id_edit = ui.add(egui::TextEdit::singleline(&mut transaction.t_id).min_size([50.0, 20.0].into()));
+                changed |= t_id_edit.changed();
+                if *hi_entity_o == Some(ModelEntity::Transaction(transaction.id.clone())) {
+                    t_id_edit.highlight();
+                }
+                changed |= ui.add(egui::TextEdit::singleline(&mut transaction.name).min_size([200.0, 20.0].into())).changed();
+                changed |= ui.add(egui::TextEdit::singleline(&mut transaction.product).min_size([200.0, 20.0].into())).changed();
                 // Initiator actor role combo
                 egui::ComboBox::from_id_salt(format!("{}_{}_{}", "Initiator", transaction.id, t_index))
                     .selected_text(initiator.unwrap_or_default().name)
@@ -159,7 +248,7 @@ pub fn transactions_ui(ui: &mut egui::Ui, actor_roles: &Vec<ActorRole>, transact
                         ui.style_mut().wrap_mode = Some(TextWrapMode::Extend);
                         ui.set_min_width(60.0);
                         for actor_role in actor_roles {
-                            ui.selectable_value(&mut transaction.initiator_id, actor_role.id.clone(), actor_role.name.clone());
+                            changed |= ui.selectable_value(&mut transaction.initiator_id, actor_role.id.clone(), actor_role.name.clone()).changed();
                         }
                     });
                 // Executor actor role combo
@@ -169,19 +258,24 @@ pub fn transactions_ui(ui: &mut egui::Ui, actor_roles: &Vec<ActorRole>, transact
                         ui.style_mut().wrap_mode = Some(TextWrapMode::Extend);
                         ui.set_min_width(60.0);
                         for actor_role in available_executors.clone() {
-                            ui.selectable_value(&mut transaction.executor_id, actor_role.id.clone(), actor_role.name.clone());
+                            changed |= ui.selectable_value(&mut transaction.executor_id, actor_role.id.clone(), actor_role.name.clone()).changed();
                         }
                     });
-                initiations_ui(ui, &transactions_cloned, &mut transaction);
-                impediments_ui(ui, &transactions_cloned, &mut transaction);
+                changed |= initiations_ui(ui, &transactions_cloned, transaction);
+                changed |= impediments_ui(ui, &transactions_cloned, transaction);
+                changed |= tacit_acts_ui(ui, transaction);
+                changed |= deadlines_ui(ui, transaction);
                 ui.end_row();
             }
         });
+    changed |= !to_delete.is_empty();
     for index in to_delete.into_iter().rev() {
         transactions.remove(index);
     }
     ui.add_space(16.0);
     if ui.button(RichText::new("➕").color(Color32::GREEN)).clicked() {
         transactions.push(Transaction::new());
+        changed = true;
     }
+    changed
 }
\ No newline at end of file
@@ -1,56 +1,202 @@
-use egui::{Color32, RichText};
+use std::collections::{HashMap, HashSet};
+use chrono::{DateTime, Utc};
+use egui::collapsing_header::CollapsingState;
+use egui::{Color32, RichText, TextWrapMode};
 use crate::app::AppContext;
-use crate::model::{CFact, CPFact};
+use crate::execution::{Execution, TransactionInstance, TransactionInstanceId};
+use crate::windows::due_label;
+use crate::model::{CFact, CPFact, MaxMultiplicity, Model, SubjectId, TransactionId};
+
+// The children of each transaction instance, those whose parent is missing as roots
+fn children_of(execution: &Execution) -> HashMap<Option<&TransactionInstanceId>, Vec<&TransactionInstance>> {
+    let mut children: HashMap<Option<&TransactionInstanceId>, Vec<&TransactionInstance>> = HashMap::new();
+    for t_i in &execution.transactions_instances {
+        let parent_o = t_i.parent_transaction_instance_id.as_ref().filter(|parent_id| execution.find_transaction_instance(parent_id).is_some());
+        children.entry(parent_o).or_default().push(t_i);
+    }
+    children
+}
+
+// The instances matching the filters, with their ancestors so that they are shown in their tree; all without filters
+fn visible_instances<'a>(execution: &'a Execution, transaction_filter_o: &Option<TransactionId>, subject_filter_o: &Option<SubjectId>) -> Option<HashSet<&'a TransactionInstanceId>> {
+    if transaction_filter_o.is_none() && subject_filter_o.is_none() {
+        return None;
+    }
+    let matches = |t_i: &TransactionInstance| {
+        let is_involved = match subject_filter_o {
+            Some(subject_id) => t_i.initiator_id == *subject_id || t_i.executor_id == *subject_id
+                || execution.agendas.iter().any(|(s_id, item)| s_id == subject_id && item.transaction_instance_id == t_i.id),
+            None => true,
+        };
+        (transaction_filter_o.is_none() || transaction_filter_o.as_ref() == Some(&t_i.transaction_id)) && is_involved
+    };
+    let mut visible = HashSet::new();
+    for t_i in execution.transactions_instances.iter().filter(|t_i| matches(t_i)) {
+        let mut id_o = Some(&t_i.id);
+        while let Some(id) = id_o {
+            if !visible.insert(id) {
+                break;
+            }
+            id_o = execution.find_transaction_instance(id).and_then(|t_i| t_i.parent_transaction_instance_id.as_ref());
+        }
+    }
+    Some(visible)
+}
+
+fn state_label(ui: &mut egui::Ui, state_o: Option<CPFact>, text: String) -> egui::Response {
+    match state_o {
+        Some(CPFact::CFact(CFact::Accepted)) => ui.colored_label(Color32::GREEN, text),
+        Some(CPFact::CFact(ref c_fact)) if c_fact.is_terminal() => ui.colored_label(Color32::GRAY, text),
+        _ => ui.label(text),
+    }
+}
+
+struct TreeUi<'a> {
+    model: &'a Model,
+    execution: &'a Execution,
+    children: HashMap<Option<&'a TransactionInstanceId>, Vec<&'a TransactionInstance>>,
+    visible_o: Option<HashSet<&'a TransactionInstanceId>>,
+    hi_transaction_instance_id_o: &'a Option<TransactionInstanceId>,
+    now: DateTime<Utc>,
+    open_all_o: Option<bool>, // expand or collapse all requested
+    to_delete: Vec<TransactionInstanceId>,
+}
+
+impl<'a> TreeUi<'a> {
+    fn visible_children(&self, parent_o: Option<&'a TransactionInstanceId>) -> Vec<&'a TransactionInstance> {
+        self.children.get(&parent_o).into_iter().flatten()
+            .filter(|t_i| match &self.visible_o {
+                Some(visible) => visible.contains(&t_i.id),
+                None => true,
+            })
+            .copied()
+            .collect()
+    }
+
+    // Subtransaction instances deleted together with the instance, hidden by the filters or not
+    fn descendants_count(&self, transaction_instance_id: &TransactionInstanceId) -> usize {
+        self.children.get(&Some(transaction_instance_id)).into_iter().flatten()
+            .map(|child| 1 + self.descendants_count(&child.id))
+            .sum()
+    }
+
+    fn header_ui(&mut self, ui: &mut egui::Ui, t_i: &TransactionInstance) {
+        let (model, execution) = (self.model, self.execution);
+        let transaction = model.get_transaction(&t_i.transaction_id);
+        let state_o = execution.get_state(&t_i.id);
+        let text = format!("{}: {} — {}", transaction.t_id, transaction.name, t_i.product_instance);
+        let label = state_label(ui, state_o.clone(), text)
+            .on_hover_text(format!("Transaction instance {}\nInitiator: {}\nExecutor: {}", t_i.id, model.get_subject(&t_i.initiator_id).name, model.get_subject(&t_i.executor_id).name));
+        if *self.hi_transaction_instance_id_o == Some(t_i.id.clone()) {
+            label.highlight();
+        }
+        ui.label(RichText::new(state_o.map(|state| state.to_string()).unwrap_or("--".to_string())).strong());
+        let pending: Vec<_> = execution.agendas.iter().filter(|(_, item)| item.transaction_instance_id == t_i.id).collect();
+        if !pending.is_empty() {
+            let performers: Vec<&str> = pending.iter().map(|(subject_id, _)| model.get_subject(subject_id).name.as_str()).collect();
+            ui.label(format!("awaiting {}", performers.join(", ")));
+            let due_o = pending.iter().filter_map(|(_, item)| item.due).min();
+            if due_o.is_some() {
+                due_label(ui, &due_o, &self.now);
+            }
+        }
+        // Subtransactions started against the multiplicity of their initiation, the cancelled ones aside
+        let children = self.children.get(&Some(&t_i.id));
+        for initiation in &transaction.initiations {
+            let Some(initiated) = model.find_transaction(&initiation.initiated_transaction_id) else { continue };
+            let count = children.into_iter().flatten().filter(|child| child.transaction_id == initiated.id && !execution.is_cancelled(&child.id)).count();
+            let text = format!("{}: {} ({})", initiated.t_id, count, initiation.multiplicity);
+            match initiation.multiplicity.max {
+                MaxMultiplicity::Numeric(max) if count > max as usize => { ui.colored_label(Color32::RED, text).on_hover_text("More instances than the multiplicity allows"); }
+                _ if count < initiation.multiplicity.min as usize => { ui.colored_label(Color32::from_rgb(230, 160, 0), text).on_hover_text("Fewer instances than the multiplicity requires"); }
+                _ => { ui.label(text); }
+            }
+        }
+        let descendants = self.descendants_count(&t_i.id);
+        let delete_text = if descendants > 0 { format!("Delete, with its {} subtransaction instance(s)", descendants) } else { "Delete".to_string() };
+        if ui.small_button(RichText::new("❌").color(Color32::RED)).on_hover_text(delete_text).clicked() {
+            self.to_delete.push(t_i.id.clone());
+        }
+    }
+
+    fn instance_ui(&mut self, ui: &mut egui::Ui, t_i: &'a TransactionInstance) {
+        let children = self.visible_children(Some(&t_i.id));
+        if children.is_empty() {
+            ui.horizontal(|ui| {
+                ui.add_space(ui.spacing().indent);
+                self.header_ui(ui, t_i);
+            });
+            return;
+        }
+        let mut state = CollapsingState::load_with_default_open(ui.ctx(), ui.make_persistent_id(&t_i.id), true);
+        if let Some(open) = self.open_all_o {
+            state.set_open(open);
+        }
+        state
+            .show_header(ui, |ui| self.header_ui(ui, t_i))
+            .body(|ui| {
+                for child in children {
+                    self.instance_ui(ui, child);
+                }
+            });
+    }
+}
 
 pub fn view(ui: &mut egui::Ui, app_context: &mut AppContext) {
     let model = &app_context.model;
-    let execution = &mut app_context.execution;
-    let mut to_delete = Vec::new();
-    egui::Grid::new("Transactions Instances")
-        .striped(true)
-        .spacing(&[10.0, 10.0])
-        .show(ui, |ui| {
-            ui.strong("Transaction Instance ID");
-            ui.strong("Transaction");
-            ui.strong("Parent TI ID");
-            ui.strong("Product Instance");
-            ui.strong("Initiator Subject");
-            ui.strong("Executor Subject");
-            ui.strong("Last Fact");
-            ui.end_row();
-            for t_i in execution.transactions_instances.iter() {
-                let transaction = model.get_transaction(&t_i.transaction_id);
-                let initiator_subject = model.get_subject(&t_i.initiator_id);
-                let executor_subject = model.get_subject(&t_i.executor_id);
-                let last_fact = execution.get_facts_for_transaction_instance(&t_i.id).last().unwrap().to_fact();
-                let render_label = |ui: &mut egui::Ui, text: String| -> egui::Response {
-                    match last_fact {
-                        CPFact::CFact(CFact::Accepted) => ui.colored_label(Color32::GREEN, text),
-                        _ => ui.label(text),
-                    }
-                };
-
-                let id_label = render_label(ui, t_i.id.clone().to_string());
-                if app_context.hi_transaction_instance_id_o == Some(t_i.id.clone()) {
-                    id_label.highlight();
+    let execution = &app_context.execution;
+    let context = &mut app_context.transactions_instances_context;
+    let mut open_all_o = None;
+    ui.horizontal(|ui| {
+        ui.label("Transaction");
+        egui::ComboBox::from_id_salt("Transactions_Instances_Transaction_filter")
+            .selected_text(context.transaction_filter_o.as_ref().and_then(|t_id| model.find_transaction(t_id)).map(|t| format!("{}: {}", t.t_id, t.name)).unwrap_or_default())
+            .show_ui(ui, |ui| {
+                ui.style_mut().wrap_mode = Some(TextWrapMode::Extend);
+                ui.selectable_value(&mut context.transaction_filter_o, None, "");
+                for transaction in &model.transactions {
+                    ui.selectable_value(&mut context.transaction_filter_o, Some(transaction.id.clone()), format!("{}: {}", transaction.t_id, transaction.name));
                 }
-                render_label(ui, format!("{}: {}", transaction.t_id.clone(), transaction.name.clone()));
-                render_label(ui, match &t_i.parent_transaction_instance_id {
-                    None => "--".to_string(),
-                    Some(id) => id.to_string(),
-                });
-                render_label(ui, t_i.product_instance.clone());
-                render_label(ui, initiator_subject.name.clone());
-                render_label(ui, executor_subject.name.clone());
-                render_label(ui, last_fact.to_string());
-                if ui.button(RichText::new("❌").color(Color32::RED)).clicked() {
-                    to_delete.push(t_i.id.clone());
+            });
+        ui.label("Subject");
+        egui::ComboBox::from_id_salt("Transactions_Instances_Subject_filter")
+            .selected_text(context.subject_filter_o.as_ref().and_then(|s_id| model.find_subject(s_id)).map(|s| s.name.clone()).unwrap_or_default())
+            .show_ui(ui, |ui| {
+                ui.style_mut().wrap_mode = Some(TextWrapMode::Extend);
+                ui.selectable_value(&mut context.subject_filter_o, None, "");
+                for subject in &model.subjects {
+                    ui.selectable_value(&mut context.subject_filter_o, Some(subject.id.clone()), subject.name.clone());
                 }
-                ui.end_row();
-            }
-        });
+            });
+        ui.separator();
+        if ui.button("Expand all").clicked() {
+            open_all_o = Some(true);
+        }
+        if ui.button("Collapse all").clicked() {
+            open_all_o = Some(false);
+        }
+    });
+    ui.separator();
+
+    let mut tree_ui = TreeUi {
+        model,
+        execution,
+        children: children_of(execution),
+        visible_o: visible_instances(execution, &context.transaction_filter_o, &context.subject_filter_o),
+        hi_transaction_instance_id_o: &app_context.hi_transaction_instance_id_o,
+        now: execution.now(),
+        open_all_o,
+        to_delete: Vec::new(),
+    };
+    let roots = tree_ui.visible_children(None);
+    if roots.is_empty() {
+        ui.label("No transaction instances");
+    }
+    for root in roots {
+        tree_ui.instance_ui(ui, root);
+    }
+    let to_delete = tree_ui.to_delete;
     for transaction_instance_id in to_delete.into_iter().rev() {
-        execution.delete_transaction_instance(&transaction_instance_id);
+        app_context.execution.delete_transaction_instance(&transaction_instance_id);
     }
 }
-
@@ -0,0 +1,69 @@
+//! Automated subjects decide with the probabilities of their policy rules, drawing once per agenda item.
+
+mod common;
+
+use demosim::engine::Engine;
+use demosim::model::{CAct, CFact, CPFact};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use common::sale_model;
+
+const TRIALS: usize = 2000;
+
+// How often the requests end up in each state after Bob's policy has run (twice, as the GUI does on every frame)
+fn outcomes(rules: &[(CAct, u8)], seed: u64) -> (usize, usize, usize) {
+    let sale = sale_model();
+    let bob = sale.bob.clone();
+    let sale = sale.with_policy(&bob, rules);
+    let mut rng = StdRng::seed_from_u64(seed);
+    let (mut promised, mut declined, mut requested) = (0, 0, 0);
+    for _ in 0..TRIALS {
+        let mut engine = Engine::new(sale.model.clone());
+        let t_i_id = engine.start_transaction(None, &sale.sale, "Book", &sale.alice, &bob).unwrap();
+        engine.run_policies(&mut rng);
+        engine.run_policies(&mut rng);
+        match engine.state(&t_i_id).unwrap() {
+            Some(CPFact::CFact(CFact::Promised)) => promised += 1,
+            Some(CPFact::CFact(CFact::Declined)) => declined += 1,
+            Some(CPFact::CFact(CFact::Requested)) => requested += 1,
+            state => panic!("unexpected state {:?}", state),
+        }
+    }
+    (promised, declined, requested)
+}
+
+fn assert_share(count: usize, percent: usize) {
+    let expected = TRIALS * percent / 100;
+    let tolerance = TRIALS * 3 / 100;
+    assert!(count.abs_diff(expected) <= tolerance, "{} out of {}, expected about {}", count, TRIALS, expected);
+}
+
+#[test]
+fn rules_take_their_shares_in_order() {
+    let (promised, declined, requested) = outcomes(&[(CAct::Promise, 80), (CAct::Decline, 20)], 1);
+    assert_share(promised, 80);
+    assert_share(declined, 20);
+    assert_eq!(requested, 0);
+}
+
+#[test]
+fn later_rules_take_the_rest() {
+    let (promised, declined, requested) = outcomes(&[(CAct::Decline, 20), (CAct::Promise, 100)], 2);
+    assert_share(promised, 80);
+    assert_share(declined, 20);
+    assert_eq!(requested, 0);
+}
+
+#[test]
+fn decision_is_not_drawn_again() {
+    // The remaining share leaves the request to be handled by hand, however many times the policies run
+    let (promised, declined, requested) = outcomes(&[(CAct::Decline, 20)], 3);
+    assert_eq!(promised, 0);
+    assert_share(declined, 20);
+    assert_share(requested, 80);
+}
+
+#[test]
+fn same_seed_same_decisions() {
+    assert_eq!(outcomes(&[(CAct::Promise, 50), (CAct::Decline, 50)], 4), outcomes(&[(CAct::Promise, 50), (CAct::Decline, 50)], 4));
+}
@@ -0,0 +1,45 @@
+//! Batch simulations are reproducible, whether performed at once or a few runs at a time, and acts take exactly their
+//! sampled durations.
+
+mod common;
+
+use demosim::batch::{run_batch, ActDuration, BatchConfig, BatchRun};
+use demosim::model::{CAct, CPAct};
+use common::sale_model;
+
+fn config(durations: Vec<ActDuration>) -> BatchConfig {
+    BatchConfig { runs: 50, seed: 7, durations, ..Default::default() }
+}
+
+fn duration(act: CAct, min_minutes: i64, max_minutes: i64) -> ActDuration {
+    ActDuration { transaction_id: None, act: CPAct::CAct(act), min_minutes, max_minutes }
+}
+
+#[test]
+fn chunks_give_the_same_report() {
+    let sale = sale_model();
+    let bob = sale.bob.clone();
+    let sale = sale.with_policy(&bob, &[(CAct::Promise, 70), (CAct::Decline, 30)]);
+    let config = config(vec![duration(CAct::Promise, 10, 100), duration(CAct::Declare, 5, 50)]);
+    let whole = run_batch(&sale.model, &sale.sale, &config).unwrap();
+    let mut run = BatchRun::new(&sale.model, &sale.sale, &config).unwrap();
+    while !run.is_finished() {
+        run.advance(7).unwrap();
+    }
+    assert_eq!(run.report(), whole);
+    assert_eq!(whole.runs, 50);
+    let statistics = whole.statistics_of(&sale.sale).unwrap();
+    assert!(statistics.declined > 0 && statistics.declined < 50);
+}
+
+#[test]
+fn acts_take_their_durations() {
+    // The declaration waits for the delivery, which Bob and Carol carry out cooperatively meanwhile
+    let sale = sale_model().with_delivery_before_declare();
+    let config = config(vec![duration(CAct::Request, 5, 5), duration(CAct::Promise, 60, 60), duration(CAct::Declare, 30, 30)]);
+    let report = run_batch(&sale.model, &sale.sale, &config).unwrap();
+    assert_eq!(report.completed_runs, 50);
+    // Request of the delivery 5, its promise 60 and declaration 30; promise and declaration of the sale 60 + 30
+    let completion = report.completion_minutes.unwrap();
+    assert_eq!((completion.min, completion.max), (185.0, 185.0));
+}
@@ -0,0 +1,100 @@
+//! The simulated time starts where it is set, moves only forward with the steps of an execution and goes back to the
+//! start when the steps are replayed; the real time is the wall-clock time.
+
+mod common;
+
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use demosim::clock::{ClockMode, SimulationClock};
+use demosim::engine::Engine;
+use demosim::execution::Execution;
+use demosim::model::{CAct, CPAct};
+use common::sale_model;
+
+fn nine_o_clock() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, 3, 1, 9, 0, 0).unwrap()
+}
+
+fn simulated_clock(auto_advance_minutes: i64) -> SimulationClock {
+    let mut clock = SimulationClock::default();
+    clock.mode = ClockMode::Simulated;
+    clock.auto_advance_minutes = auto_advance_minutes;
+    clock.set_start(nine_o_clock());
+    clock
+}
+
+#[test]
+fn observed_time_only_moves_forward() {
+    let mut clock = simulated_clock(0);
+    clock.observe(&(nine_o_clock() + Duration::hours(2)));
+    assert_eq!(clock.now(), nine_o_clock() + Duration::hours(2));
+    clock.observe(&(nine_o_clock() + Duration::hours(1)));
+    assert_eq!(clock.now(), nine_o_clock() + Duration::hours(2));
+    clock.observe(&(nine_o_clock() - Duration::days(1)));
+    assert_eq!(clock.now(), nine_o_clock() + Duration::hours(2));
+}
+
+#[test]
+fn reset_rewinds_to_the_start() {
+    let mut clock = simulated_clock(0);
+    clock.observe(&(nine_o_clock() + Duration::hours(3)));
+    clock.reset();
+    assert_eq!(clock.now(), nine_o_clock());
+    assert_eq!(clock.start, nine_o_clock());
+}
+
+#[test]
+fn set_start_moves_the_time_too() {
+    let mut clock = simulated_clock(0);
+    clock.observe(&(nine_o_clock() + Duration::hours(3)));
+    let earlier = nine_o_clock() - Duration::days(7);
+    clock.set_start(earlier);
+    assert_eq!(clock.start, earlier);
+    assert_eq!(clock.now(), earlier);
+}
+
+#[test]
+fn simulated_steps_advance_automatically() {
+    assert_eq!(simulated_clock(0).next_step_time(), nine_o_clock());
+    assert_eq!(simulated_clock(15).next_step_time(), nine_o_clock() + Duration::minutes(15));
+    // A negative advance would stamp the step before the previous one
+    assert_eq!(simulated_clock(-15).next_step_time(), nine_o_clock());
+    // Asking for the time of the next step does not take it
+    let clock = simulated_clock(15);
+    clock.next_step_time();
+    assert_eq!(clock.now(), nine_o_clock());
+}
+
+#[test]
+fn real_time_follows_the_wall_clock() {
+    let mut clock = simulated_clock(15);
+    clock.mode = ClockMode::RealTime;
+    let before = Utc::now();
+    let (now, next_step_time) = (clock.now(), clock.next_step_time());
+    let after = Utc::now();
+    assert!(before <= now && now <= after, "{} not within {} and {}", now, before, after);
+    assert!(before <= next_step_time && next_step_time <= after, "{} not within {} and {}", next_step_time, before, after);
+    // Steps observed in the future do not move the wall clock
+    clock.observe(&(after + Duration::days(1)));
+    assert!(clock.now() <= Utc::now());
+}
+
+#[test]
+fn steps_of_an_execution_are_stamped_with_the_simulated_time() {
+    let sale = sale_model();
+    let mut execution = Execution::default();
+    execution.clock = simulated_clock(30);
+    let mut engine = Engine::with_execution(sale.model.clone(), execution);
+    let sale_instance = engine.start_transaction(None, &sale.sale, "Fish", &sale.alice, &sale.bob).unwrap();
+    engine.perform(&sale_instance, &sale.bob, CPAct::CAct(CAct::Promise)).unwrap();
+    let timestamps: Vec<DateTime<Utc>> = engine.execution().events().iter().map(|event| *event.get_timestamp()).collect();
+    let minutes: Vec<i64> = timestamps.iter().map(|timestamp| (*timestamp - nine_o_clock()).num_minutes()).collect();
+    assert_eq!(minutes.first(), Some(&30));
+    assert!(minutes.windows(2).all(|pair| pair[0] <= pair[1]), "{:?}", minutes);
+    assert_eq!(engine.execution().now(), *timestamps.last().unwrap());
+
+    engine.advance_clock(Duration::hours(1));
+    assert_eq!(engine.execution().now(), *timestamps.last().unwrap() + Duration::hours(1));
+    // Undoing replays the remaining steps from the start
+    assert!(engine.undo());
+    assert_eq!(engine.execution().now(), *timestamps.last().unwrap());
+}
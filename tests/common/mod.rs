@@ -0,0 +1,98 @@
+//! A small model shared by the tests: Alice (a customer) buys from Bob (a seller), who has the goods delivered by
+//! Carol (a courier) once he has promised the sale. Each test file uses only a part of it.
+#![allow(dead_code)]
+
+use demosim::model::{
+    ActorRole, AdtOption, CAct, CFact, CPAct, Impediment, Initiation, Model, Multiplicity, Policy, PolicyRule,
+    CPFact, Subject, SubjectId, Transaction, TransactionId,
+};
+
+pub struct Sale {
+    pub model: Model,
+    pub alice: SubjectId,
+    pub bob: SubjectId,
+    pub carol: SubjectId,
+    pub sale: TransactionId,     // T01, Alice -> Bob
+    pub delivery: TransactionId, // T02, Bob -> Carol, initiated within the sale once promised
+}
+
+impl Sale {
+    pub fn transaction_mut(&mut self, transaction_id: &TransactionId) -> &mut Transaction {
+        self.model.transactions.iter_mut().find(|t| t.id == *transaction_id).unwrap()
+    }
+
+    /// The declaration of the sale waits for the delivery to be accepted.
+    pub fn with_delivery_before_declare(mut self) -> Self {
+        let delivery = self.delivery.clone();
+        self.transaction_mut(&self.sale.clone()).impediments.push(Impediment {
+            impeded_act: CPAct::CAct(CAct::Declare),
+            impeding_transaction_id: delivery,
+            impeding_c_fact: CFact::Accepted,
+        });
+        self
+    }
+
+    pub fn with_multiplicity(mut self, multiplicity: &str) -> Self {
+        let sale = self.sale.clone();
+        self.transaction_mut(&sale).initiations[0].multiplicity = multiplicity.parse().unwrap();
+        self
+    }
+
+    /// Makes the subject automated with the given rules for a request of any transaction.
+    pub fn with_policy(mut self, subject_id: &SubjectId, rules: &[(CAct, u8)]) -> Self {
+        let rules = rules.iter()
+            .map(|(act, probability_percent)| PolicyRule {
+                transaction_id: None,
+                fact: CPFact::CFact(CFact::Requested),
+                act: CPAct::CAct(act.clone()),
+                probability_percent: *probability_percent,
+            })
+            .collect();
+        self.model.policies.insert(subject_id.clone(), Policy { automated: true, rules });
+        self
+    }
+}
+
+pub fn sale_model() -> Sale {
+    let customer = ActorRole { name: "Customer".to_string(), ..Default::default() };
+    let seller = ActorRole { name: "Seller".to_string(), ..Default::default() };
+    let courier = ActorRole { name: "Courier".to_string(), ..Default::default() };
+    let alice = Subject { name: "Alice".to_string(), ..Default::default() };
+    let bob = Subject { name: "Bob".to_string(), ..Default::default() };
+    let carol = Subject { name: "Carol".to_string(), ..Default::default() };
+    let mut delivery = Transaction::new();
+    delivery.t_id = "T02".to_string();
+    delivery.name = "Delivery".to_string();
+    delivery.product = "Delivery is done".to_string();
+    delivery.initiator_id = seller.id.clone();
+    delivery.executor_id = courier.id.clone();
+    let mut sale = Transaction::new();
+    sale.t_id = "T01".to_string();
+    sale.name = "Sale".to_string();
+    sale.product = "Sale is completed".to_string();
+    sale.initiator_id = customer.id.clone();
+    sale.executor_id = seller.id.clone();
+    sale.initiations.push(Initiation {
+        initiating_c_fact: CFact::Promised,
+        initiated_transaction_id: delivery.id.clone(),
+        initiated_c_act: CAct::Request,
+        multiplicity: Multiplicity::default(),
+        multiplicity_tmp_str: String::new(),
+    });
+
+    let mut model = Model { name: "Sale".to_string(), ..Default::default() };
+    model.adt.mappings.insert((customer.id.clone(), alice.id.clone()), AdtOption::Authorisation);
+    model.adt.mappings.insert((seller.id.clone(), bob.id.clone()), AdtOption::Authorisation);
+    model.adt.mappings.insert((courier.id.clone(), carol.id.clone()), AdtOption::Authorisation);
+    model.actor_roles = vec![customer, seller, courier];
+    model.subjects = vec![alice.clone(), bob.clone(), carol.clone()];
+    model.transactions = vec![sale.clone(), delivery.clone()];
+    Sale {
+        model,
+        alice: alice.id,
+        bob: bob.id,
+        carol: carol.id,
+        sale: sale.id,
+        delivery: delivery.id,
+    }
+}
@@ -0,0 +1,184 @@
+//! Logs are read from CSV and XES and replayed against the model, each kind of deviation being reported.
+
+mod common;
+
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use demosim::conformance::{check_conformance, read_csv_log, read_xes_log, DeviationKind, LogEvent};
+use demosim::model::{CAct, CPAct, Deadline};
+use common::sale_model;
+
+fn start() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, 3, 1, 9, 0, 0).unwrap()
+}
+
+fn event(activity: &str, minute: i64, performer: &str) -> LogEvent {
+    LogEvent {
+        case_id: "Book".to_string(),
+        activity: activity.to_string(),
+        timestamp: start() + Duration::minutes(minute),
+        performer: performer.to_string(),
+        addressee: None,
+        transaction: None,
+        fact: None,
+        transaction_instance_id: None,
+        parent_transaction_instance_id: None,
+        product_instance: None,
+    }
+}
+
+// The sale with its delivery, as the model has it
+fn sale_log() -> Vec<LogEvent> {
+    vec![
+        event("T01 Requested", 0, "Alice"),
+        event("T01 Promised", 10, "Bob"),
+        event("T02 Requested", 20, "Bob"),
+        event("T02 Promised", 30, "Carol"),
+        event("T02 Executed", 35, "Carol"),
+        event("T02 Declared", 40, "Carol"),
+        event("T02 Accepted", 50, "Bob"),
+        event("T01 Executed", 55, "Bob"),
+        event("T01 Declared", 60, "Bob"),
+        event("T01 Accepted", 70, "Alice"),
+    ]
+}
+
+#[test]
+fn csv_log_is_read_by_column_names() {
+    let text = "case:concept:name;concept:name;time:timestamp;org:resource;Fact\n\
+                \"Book; paperback\";T01 Requested;2024-03-01T09:00:00Z;Alice;\n\
+                \"Book; paperback\";T01;2024-03-01 09:10:00;Bob;Promised\n";
+    let log = read_csv_log(text).unwrap();
+    assert_eq!(log.len(), 2);
+    assert_eq!(log[0].case_id, "Book; paperback");
+    assert_eq!(log[0].fact, None);
+    assert_eq!(log[1].timestamp, start() + Duration::minutes(10));
+    assert_eq!(log[1].performer, "Bob");
+    assert_eq!(log[1].fact.as_deref(), Some("Promised"));
+}
+
+#[test]
+fn csv_log_errors_tell_where() {
+    let missing = read_csv_log("case,activity,performer\nBook,T01 Requested,Alice\n").unwrap_err();
+    assert_eq!(missing.position, Some((1, 1)));
+    let invalid = read_csv_log("case,activity,timestamp,performer\nBook,T01 Requested,2024-03-01T09:00:00Z,Alice\nBook,T01 Promised,tomorrow,Bob\n").unwrap_err();
+    assert!(invalid.message.contains("tomorrow"));
+    assert_eq!(invalid.position, Some((3, 1)));
+}
+
+#[test]
+fn xes_log_takes_the_case_from_the_trace() {
+    let text = r#"<?xml version="1.0" encoding="UTF-8"?>
+<log xes.version="1.0">
+  <string key="concept:name" value="Sales"/>
+  <trace>
+    <string key="concept:name" value="Book &amp; pen"/>
+    <event>
+      <string key="concept:name" value="T01 Requested"/>
+      <date key="time:timestamp" value="2024-03-01T09:00:00.000+00:00"/>
+      <string key="org:resource" value="Alice"/>
+      <string key="addressee" value="Bob"/>
+    </event>
+    <event>
+      <string key="concept:name" value="T01 Promised"/>
+      <date key="time:timestamp" value="2024-03-01T10:05:00.000+01:00"/>
+      <string key="org:resource" value="Bob"/>
+      <string key="lifecycle:transition" value="start"/>
+    </event>
+    <event>
+      <string key="concept:name" value="T01 Promised"/>
+      <date key="time:timestamp" value="2024-03-01T10:10:00.000+01:00"/>
+      <string key="org:resource" value="Bob"/>
+      <string key="lifecycle:transition" value="complete"/>
+    </event>
+  </trace>
+</log>
+"#;
+    let log = read_xes_log(text).unwrap();
+    assert_eq!(log.len(), 2);
+    assert!(log.iter().all(|event| event.case_id == "Book & pen"));
+    assert_eq!(log[0].addressee.as_deref(), Some("Bob"));
+    assert_eq!(log[1].timestamp, start() + Duration::minutes(10));
+
+    let error = read_xes_log("<log>\n<trace>\n<event>\n<string key=\"concept:name\" value=\"T01 Requested\"/>\n</event>\n</trace>\n</log>").unwrap_err();
+    assert!(error.message.contains("time:timestamp"));
+    assert_eq!(error.position, Some((3, 1)));
+}
+
+#[test]
+fn log_of_the_model_conforms() {
+    let sale = sale_model();
+    let report = check_conformance(&sale.model, &sale_log());
+    assert_eq!(report.deviations, vec![]);
+    assert_eq!(report.fitness(), 1.0);
+}
+
+#[test]
+fn unknown_transaction_is_not_in_model() {
+    let sale = sale_model();
+    let mut log = sale_log();
+    log.push(event("T09 Requested", 80, "Alice"));
+    let report = check_conformance(&sale.model, &log);
+    assert_eq!(report.count_of(DeviationKind::NotInModel), 1);
+    assert_eq!(report.deviations[0].event_index, 11);
+}
+
+#[test]
+fn act_against_the_pattern_is_out_of_order() {
+    let sale = sale_model();
+    let log = vec![event("T01 Requested", 0, "Alice"), event("T01 Declared", 10, "Bob"), event("T01 Accepted", 20, "Alice")];
+    let report = check_conformance(&sale.model, &log);
+    // The declaration is replayed anyway, so the acceptance following it conforms
+    assert_eq!(report.deviations.len(), 1);
+    assert_eq!(report.count_of(DeviationKind::OutOfOrder), 1);
+    assert_eq!(report.deviations[0].event_index, 2);
+}
+
+#[test]
+fn act_by_the_wrong_subject_is_unauthorised() {
+    let sale = sale_model();
+    let log = vec![event("T01 Requested", 0, "Alice"), event("T01 Promised", 10, "Alice"), event("T01 Executed", 20, "Bob"), event("T01 Declared", 30, "Bob")];
+    let report = check_conformance(&sale.model, &log);
+    assert_eq!(report.deviations.len(), 1);
+    assert_eq!(report.count_of(DeviationKind::Unauthorised), 1);
+}
+
+#[test]
+fn act_before_the_awaited_fact_violates_the_wait_link() {
+    let sale = sale_model().with_delivery_before_declare();
+    let log = vec![event("T01 Requested", 0, "Alice"), event("T01 Promised", 10, "Bob"), event("T01 Executed", 20, "Bob"), event("T01 Declared", 30, "Bob")];
+    let report = check_conformance(&sale.model, &log);
+    assert_eq!(report.deviations.len(), 1);
+    assert_eq!(report.count_of(DeviationKind::WaitLinkViolated), 1);
+    assert_eq!(report.deviations[0].event_index, 4);
+}
+
+#[test]
+fn act_after_the_awaited_fact_conforms_to_the_wait_link() {
+    let sale = sale_model().with_delivery_before_declare();
+    let report = check_conformance(&sale.model, &sale_log());
+    assert_eq!(report.deviations, vec![]);
+}
+
+#[test]
+fn subtransaction_beyond_the_multiplicity_exceeds_it() {
+    let sale = sale_model().with_multiplicity("1..1");
+    let mut log = sale_log();
+    log.truncate(2);
+    for (minute, instance) in [(20, "Delivery 1"), (30, "Delivery 2")] {
+        log.push(LogEvent { transaction_instance_id: Some(instance.to_string()), ..event("T02 Requested", minute, "Bob") });
+    }
+    let report = check_conformance(&sale.model, &log);
+    assert_eq!(report.deviations.len(), 1);
+    assert_eq!(report.count_of(DeviationKind::MultiplicityExceeded), 1);
+    assert_eq!(report.deviations[0].event_index, 4);
+}
+
+#[test]
+fn deadlines_do_not_expire_in_the_replay() {
+    // The late promise is in the log; the engine must not decline the sale on its own in the meantime
+    let mut sale = sale_model();
+    let sale_id = sale.sale.clone();
+    sale.transaction_mut(&sale_id).deadlines = vec![Deadline { act: Some(CPAct::CAct(CAct::Promise)), within_minutes: 5, on_expiry: Some(CAct::Decline) }];
+    let report = check_conformance(&sale.model, &sale_log());
+    assert_eq!(report.deviations, vec![]);
+}
@@ -0,0 +1,120 @@
+//! Agenda items are due by the deadlines of their transaction and, once expired, may trigger an act on their own.
+
+mod common;
+
+use chrono::Duration;
+use demosim::engine::Engine;
+use demosim::execution::{CPWorldItem, Execution};
+use demosim::model::{CAct, CFact, CPAct, CPFact, Deadline, Model};
+use common::{sale_model, Sale};
+
+// The sale has to be promised within an hour, else it is declined; the customer has to respond to a decline within an
+// hour too, else she requests again
+fn sale_with_deadlines(within_minutes: i64) -> Sale {
+    let mut sale = sale_model();
+    let sale_id = sale.sale.clone();
+    sale.transaction_mut(&sale_id).deadlines = vec![
+        Deadline { act: Some(CPAct::CAct(CAct::Promise)), within_minutes, on_expiry: Some(CAct::Decline) },
+        Deadline { act: Some(CPAct::CAct(CAct::Request)), within_minutes, on_expiry: Some(CAct::Request) },
+    ];
+    sale
+}
+
+fn assert_consistent(model: &Model, execution: &Execution) {
+    let mut rebuilt = execution.clone();
+    rebuilt.restore(model);
+    assert_eq!(rebuilt.transactions_instances.len(), execution.transactions_instances.len());
+    assert_eq!(rebuilt.c_p_world, execution.c_p_world);
+    assert_eq!(rebuilt.agendas, execution.agendas);
+}
+
+#[test]
+fn agenda_items_are_due_by_the_deadline() {
+    let sale = sale_with_deadlines(60);
+    let mut engine = Engine::new(sale.model.clone());
+    let t_i_id = engine.start_transaction(None, &sale.sale, "Book", &sale.alice, &sale.bob).unwrap();
+    let agenda = engine.agenda(&sale.bob).unwrap();
+    assert_eq!(agenda[0].due, Some(agenda[0].timestamp + Duration::minutes(60)));
+    assert!(engine.execution().overdue_agenda_items().is_empty());
+
+    engine.perform(&t_i_id, &sale.bob, CPAct::CAct(CAct::Promise)).unwrap();
+    // No deadline for the execution
+    assert_eq!(engine.agenda(&sale.bob).unwrap()[0].due, None);
+}
+
+#[test]
+fn items_without_an_expiry_act_just_become_overdue() {
+    let mut sale = sale_model();
+    let sale_id = sale.sale.clone();
+    sale.transaction_mut(&sale_id).deadlines = vec![Deadline { act: None, within_minutes: 30, on_expiry: None }];
+    let mut engine = Engine::new(sale.model.clone());
+    let t_i_id = engine.start_transaction(None, &sale.sale, "Book", &sale.alice, &sale.bob).unwrap();
+    engine.advance_clock(Duration::minutes(31));
+    assert_eq!(engine.execution().overdue_agenda_items().len(), 1);
+    assert_eq!(engine.state(&t_i_id).unwrap(), Some(CPFact::CFact(CFact::Requested)));
+}
+
+#[test]
+fn expired_deadline_performs_its_act_when_it_expired() {
+    let sale = sale_with_deadlines(60);
+    let mut engine = Engine::new(sale.model.clone());
+    let t_i_id = engine.start_transaction(None, &sale.sale, "Book", &sale.alice, &sale.bob).unwrap();
+    let due = engine.agenda(&sale.bob).unwrap()[0].due.unwrap();
+    engine.advance_clock(Duration::minutes(90));
+    assert_eq!(engine.state(&t_i_id).unwrap(), Some(CPFact::CFact(CFact::Declined)));
+    let Some(CPWorldItem::CWorldItem(decline)) = engine.execution().c_p_world.last() else { panic!("no decline") };
+    assert!(decline.on_deadline_expiry);
+    assert_eq!(decline.timestamp, due);
+    assert_consistent(engine.model(), engine.execution());
+}
+
+#[test]
+fn expiries_follow_each_other_until_the_time_is_reached() {
+    let sale = sale_with_deadlines(60);
+    let mut engine = Engine::new(sale.model.clone());
+    let t_i_id = engine.start_transaction(None, &sale.sale, "Book", &sale.alice, &sale.bob).unwrap();
+    engine.advance_clock(Duration::minutes(10 * 60 + 30));
+    // Declined, requested again, ... every hour
+    let expired = engine.execution().c_p_world.iter().filter(|item| matches!(item, CPWorldItem::CWorldItem(c) if c.on_deadline_expiry)).count();
+    assert_eq!(expired, 10);
+    assert_eq!(engine.state(&t_i_id).unwrap(), Some(CPFact::CFact(CFact::Requested)));
+    assert_consistent(engine.model(), engine.execution());
+}
+
+#[test]
+fn invalid_step_takes_its_expiries_back() {
+    let sale = sale_with_deadlines(60);
+    let mut execution = Execution::default();
+    execution.clock.auto_advance_minutes = 120;
+    let mut engine = Engine::with_execution(sale.model.clone(), execution);
+    let t_i_id = engine.start_transaction(None, &sale.sale, "Book", &sale.alice, &sale.bob).unwrap();
+    let now = engine.now();
+    // Alice cannot promise; the request expiring meanwhile must not be declined because of her attempt
+    assert!(engine.perform(&t_i_id, &sale.alice, CPAct::CAct(CAct::Promise)).is_err());
+    assert_eq!(engine.state(&t_i_id).unwrap(), Some(CPFact::CFact(CFact::Requested)));
+    assert_eq!(engine.now(), now);
+    assert_consistent(engine.model(), engine.execution());
+    // The next valid step past the due time brings the expiry
+    engine.advance_clock(Duration::minutes(61));
+    assert_eq!(engine.state(&t_i_id).unwrap(), Some(CPFact::CFact(CFact::Declined)));
+    assert_consistent(engine.model(), engine.execution());
+}
+
+#[test]
+fn deadlines_must_be_at_least_a_minute() {
+    let sale = sale_with_deadlines(0);
+    assert!(sale.model.validate().iter().any(|diagnostic| diagnostic.message.contains("at least one minute")));
+    let mut repaired = sale.model.clone();
+    assert_eq!(repaired.repair().len(), 1);
+    assert!(repaired.transactions.iter().all(|t| t.deadlines.is_empty()));
+    assert!(repaired.validate().iter().all(|diagnostic| !diagnostic.message.contains("at least one minute")));
+}
+
+#[test]
+fn zero_deadlines_do_not_hang_the_engine() {
+    // Not repaired, the expiries would decline and request again forever at the same instant
+    let sale = sale_with_deadlines(0);
+    let mut engine = Engine::new(sale.model.clone());
+    engine.start_transaction(None, &sale.sale, "Book", &sale.alice, &sale.bob).unwrap();
+    engine.advance_clock(Duration::minutes(1));
+}
@@ -0,0 +1,61 @@
+//! The engine reports an execution that does not match its model (e.g. loaded with another version of the model or
+//! edited by hand) as errors instead of failing.
+
+mod common;
+
+use demosim::engine::Engine;
+use demosim::execution::{ExecutionError, TransactionInstanceId};
+use demosim::model::{CAct, CFact, CPAct, CPFact, Impediment, Model};
+use common::{sale_model, Sale};
+
+// The sale promised and the delivery requested within it
+fn started(sale: &Sale) -> (Engine, TransactionInstanceId, TransactionInstanceId) {
+    let mut engine = Engine::new(sale.model.clone());
+    let sale_instance = engine.start_transaction(None, &sale.sale, "Book", &sale.alice, &sale.bob).unwrap();
+    engine.perform(&sale_instance, &sale.bob, CPAct::CAct(CAct::Promise)).unwrap();
+    let delivery_instance = engine.start_transaction(Some(&sale_instance), &sale.delivery, "Book / Delivery", &sale.bob, &sale.carol).unwrap();
+    (engine, sale_instance, delivery_instance)
+}
+
+#[test]
+fn unknown_addressee_is_an_error() {
+    let sale = sale_model();
+    let (engine, _, delivery_instance) = started(&sale);
+    let (mut model, execution) = engine.into_parts();
+    model.subjects.retain(|s| s.id != sale.carol);
+    let mut engine = Engine::with_execution(model, execution);
+    // Bob is still there, but the revocation would be addressed to Carol
+    let res = engine.perform(&delivery_instance, &sale.bob, CPAct::CAct(CAct::RevokeRequest));
+    assert_eq!(res, Err(ExecutionError::UnknownSubject(sale.carol.clone())));
+    assert_eq!(engine.state(&delivery_instance).unwrap(), Some(CPFact::CFact(CFact::Requested)));
+}
+
+#[test]
+fn lost_parent_instance_is_an_error() {
+    let mut sale = sale_model();
+    let (sale_id, delivery) = (sale.sale.clone(), sale.delivery.clone());
+    // Carol promises only once the sale is promised, which is looked up from the initiating instance
+    sale.transaction_mut(&delivery).impediments.push(Impediment {
+        impeded_act: CPAct::CAct(CAct::Promise),
+        impeding_transaction_id: sale_id,
+        impeding_c_fact: CFact::Promised,
+    });
+    let (engine, sale_instance, delivery_instance) = started(&sale);
+    let (model, mut execution) = engine.into_parts();
+    execution.transactions_instances.retain(|t_i| t_i.id != sale_instance);
+    let mut engine = Engine::with_execution(model, execution);
+    let res = engine.perform(&delivery_instance, &sale.carol, CPAct::CAct(CAct::Promise));
+    assert!(matches!(res, Err(ExecutionError::Impeded { .. })), "{:?}", res);
+    assert_eq!(engine.startable_transactions(&sale.bob, Some(&sale_instance)), Err(ExecutionError::UnknownTransactionInstance(sale_instance.clone())));
+}
+
+#[test]
+fn unknown_transaction_of_the_parent_is_an_error() {
+    let sale = sale_model();
+    let (engine, sale_instance, _) = started(&sale);
+    let (model, execution) = engine.into_parts();
+    let parent = execution.find_transaction_instance(&sale_instance).unwrap();
+    let empty = Model::default();
+    assert_eq!(execution.startable_subtransactions(&empty, parent, &sale.bob), Err(ExecutionError::UnknownTransaction(sale.sale.clone())));
+    assert_eq!(execution.startable_subtransactions(&model, parent, &sale.bob), Ok(vec![]));
+}
@@ -0,0 +1,136 @@
+//! Names are written so each format reads them back as they are: quoted and escaped where the format needs it, and
+//! the XML formats are well-formed.
+
+mod common;
+
+use demosim::conformance::{read_csv_log, read_xes_log};
+use demosim::engine::Engine;
+use demosim::execution::{Execution, TransactionInstanceId};
+use demosim::export::event_log::{to_csv, to_xes};
+use demosim::export::ocd::{ocd_to_dot, ocd_to_svg};
+use demosim::export::psd::{psd_to_dot, psd_to_mermaid, psd_to_svg};
+use demosim::export::sequence::{sequence_to_mermaid, sequence_to_plantuml};
+use demosim::model::{CAct, CPAct, Model};
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use common::{sale_model, Sale};
+
+// Quotes, markup, separators and a line break, each of which some format has to escape
+const NAME: &str = "Fish \"&\" <chips>, salt; vinegar\nto go";
+
+// The sale with the model, the customer and the sale named awkwardly
+fn awkward_sale() -> Sale {
+    let mut sale = sale_model();
+    let sale_id = sale.sale.clone();
+    sale.model.name = NAME.to_string();
+    sale.model.actor_roles[0].name = NAME.to_string();
+    sale.transaction_mut(&sale_id).name = NAME.to_string();
+    sale
+}
+
+// The sale of a product named awkwardly, requested by Alice, named so too, and promised by Bob
+fn awkward_execution() -> (Model, Execution, TransactionInstanceId) {
+    let mut sale = awkward_sale();
+    sale.model.subjects[0].name = NAME.to_string();
+    let mut engine = Engine::new(sale.model.clone());
+    let sale_instance = engine.start_transaction(None, &sale.sale, NAME, &sale.alice, &sale.bob).unwrap();
+    engine.perform(&sale_instance, &sale.bob, CPAct::CAct(CAct::Promise)).unwrap();
+    let (model, execution) = engine.into_parts();
+    (model, execution, sale_instance)
+}
+
+fn has_line(text: &str, line: &str) -> bool {
+    text.lines().any(|l| l == line)
+}
+
+// The texts and attribute values of a document, unescaped; panics unless it is well-formed
+fn xml_strings(xml: &str) -> Vec<String> {
+    let mut reader = Reader::from_str(xml);
+    let mut strings = Vec::new();
+    loop {
+        match reader.read_event().unwrap() {
+            Event::Start(element) | Event::Empty(element) => {
+                for attribute in element.attributes() {
+                    strings.push(attribute.unwrap().unescape_value().unwrap().to_string());
+                }
+            }
+            Event::Text(text) => strings.push(text.unescape().unwrap().to_string()),
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    strings
+}
+
+#[test]
+fn ocd_dot_escapes_names() {
+    let dot = ocd_to_dot(&awkward_sale().model);
+    assert!(has_line(&dot, r#"digraph "Fish \"&\" <chips>, salt; vinegar\nto go OCD" {"#), "{}", dot);
+    assert!(has_line(&dot, r##"  A0 [shape=box, style=filled, fillcolor="#dddddd", label="Fish \"&\" <chips>, salt; vinegar\nto go"];"##), "{}", dot);
+    assert!(has_line(&dot, r##"  T0 [shape=circle, style=filled, fillcolor="#f4c7c3", label="◇\nT01", xlabel="Fish \"&\" <chips>, salt; vinegar\nto go"];"##), "{}", dot);
+}
+
+#[test]
+fn ocd_svg_is_well_formed() {
+    let svg = ocd_to_svg(&awkward_sale().model);
+    let strings = xml_strings(&svg);
+    // The actor role and the transaction
+    assert_eq!(strings.iter().filter(|s| *s == NAME).count(), 2, "{}", svg);
+}
+
+#[test]
+fn psd_dot_and_mermaid_escape_names() {
+    let model = awkward_sale().model;
+    let dot = psd_to_dot(&model);
+    assert!(has_line(&dot, r#"digraph "Fish \"&\" <chips>, salt; vinegar\nto go PSD" {"#), "{}", dot);
+    assert!(has_line(&dot, r#"    label="T01 Fish \"&\" <chips>, salt; vinegar\nto go";"#), "{}", dot);
+    let mermaid = psd_to_mermaid(&model);
+    assert!(has_line(&mermaid, r#"  subgraph T0 ["T01 Fish #quot;&#quot; #lt;chips#gt;, salt; vinegar to go"]"#), "{}", mermaid);
+}
+
+#[test]
+fn psd_svg_is_well_formed() {
+    let svg = psd_to_svg(&awkward_sale().model);
+    assert!(xml_strings(&svg).iter().any(|s| s == NAME), "{}", svg);
+}
+
+#[test]
+fn xes_is_well_formed_and_read_back() {
+    let (model, execution, _) = awkward_execution();
+    let xes = to_xes(&model, &execution);
+    assert!(has_line(&xes, r#"      <string key="org:resource" value="Fish &quot;&amp;&quot; &lt;chips&gt;, salt; vinegar&#10;to go"/>"#), "{}", xes);
+    assert!(xml_strings(&xes).iter().any(|s| s == NAME));
+    let events = read_xes_log(&xes).unwrap();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].performer, NAME);
+    assert_eq!(events[0].product_instance.as_deref(), Some(NAME));
+    assert_eq!(events[1].addressee.as_deref(), Some(NAME));
+}
+
+#[test]
+fn csv_quotes_values_and_is_read_back() {
+    let (model, execution, _) = awkward_execution();
+    let csv = to_csv(&model, &execution);
+    let quoted = "\"Fish \"\"&\"\" <chips>, salt; vinegar\nto go\"";
+    assert_eq!(csv.matches(quoted).count(), 4, "{}", csv);
+    let events = read_csv_log(&csv).unwrap();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].performer, NAME);
+    assert_eq!(events[0].product_instance.as_deref(), Some(NAME));
+    assert_eq!(events[1].addressee.as_deref(), Some(NAME));
+}
+
+#[test]
+fn sequence_diagrams_escape_names() {
+    let (model, execution, sale_instance) = awkward_execution();
+    let mermaid = sequence_to_mermaid(&model, &execution, &sale_instance);
+    let mermaid_name = "Fish \"&\" #lt;chips#gt;, salt#59; vinegar to go";
+    assert!(has_line(&mermaid, &format!("  title T01 {}, {}", mermaid_name, mermaid_name)), "{}", mermaid);
+    assert!(has_line(&mermaid, &format!("  participant S0 as {}", mermaid_name)), "{}", mermaid);
+    assert!(has_line(&mermaid, &format!("  S0->>S1: T01 Requested: {}", mermaid_name)), "{}", mermaid);
+    let plantuml = sequence_to_plantuml(&model, &execution, &sale_instance);
+    let plantuml_name = "Fish \"&\" <chips>, salt; vinegar to go";
+    assert!(has_line(&plantuml, &format!("title T01 {}, {}", plantuml_name, plantuml_name)), "{}", plantuml);
+    assert!(has_line(&plantuml, "participant \"Fish '&' <chips>, salt; vinegar to go\" as S0"), "{}", plantuml);
+    assert!(has_line(&plantuml, &format!("S0 -> S1 : T01 Requested: {}", plantuml_name)), "{}", plantuml);
+}
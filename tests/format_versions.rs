@@ -0,0 +1,92 @@
+//! Every historic version of the `.dms` format has a fixture of the same model in `tests/fixtures`. Each must load,
+//! upgrade to the same current model and survive saving and loading again unchanged.
+
+use std::path::PathBuf;
+use demosim::engine::Engine;
+use demosim::persistence::{model_from_ron, model_to_ron, session_from_ron, FORMAT_VERSION};
+
+const LIBRARY_FIXTURES: [(u32, &str); 3] = [
+    (1, "library_v1.dms"),
+    (2, "library_v2.dms"),
+    (3, "library_v3.dms"),
+];
+
+fn read_fixture(file_name: &str) -> String {
+    let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "tests", "fixtures", file_name].iter().collect();
+    std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("cannot read {}: {}", path.display(), e))
+}
+
+// Saved models compared as RON values, as the order of map entries (e.g. of the policies) is not stable
+fn normalised(text: &str) -> ron::Value {
+    ron::from_str(text).expect("saved model is valid RON")
+}
+
+#[test]
+fn every_version_has_a_fixture() {
+    let versions: Vec<u32> = LIBRARY_FIXTURES.iter().map(|(version, _)| *version).collect();
+    assert_eq!(versions, (1..=FORMAT_VERSION).collect::<Vec<u32>>());
+}
+
+#[test]
+fn fixtures_load_with_their_version() {
+    for (version, file_name) in LIBRARY_FIXTURES {
+        let loaded = model_from_ron(&read_fixture(file_name)).unwrap_or_else(|e| panic!("{}: {}", file_name, e));
+        assert_eq!(loaded.format_version, version, "{}", file_name);
+        assert!(loaded.repairs.is_empty(), "{}: {:?}", file_name, loaded.repairs);
+        let model = loaded.model;
+        assert_eq!(model.name, "Library");
+        assert_eq!(model.actor_roles.len(), 3);
+        assert_eq!(model.subjects.len(), 2);
+        assert_eq!(model.transactions.len(), 2);
+        assert_eq!(model.transactions[0].initiations.len(), 1);
+        assert_eq!(model.transactions[0].impediments.len(), 1);
+        assert_eq!(model.adt.mappings.len(), 3);
+        assert!(model.validate().is_empty(), "{}: {:?}", file_name, model.validate());
+    }
+}
+
+#[test]
+fn fixtures_upgrade_to_the_same_model() {
+    let current: Vec<ron::Value> = LIBRARY_FIXTURES.iter()
+        .map(|(_, file_name)| normalised(&model_to_ron(&model_from_ron(&read_fixture(file_name)).unwrap().model)))
+        .collect();
+    for (saved, (_, file_name)) in current.iter().zip(LIBRARY_FIXTURES) {
+        assert_eq!(*saved, current[current.len() - 1], "{}", file_name);
+    }
+}
+
+#[test]
+fn fixtures_round_trip() {
+    for (_, file_name) in LIBRARY_FIXTURES {
+        let saved = model_to_ron(&model_from_ron(&read_fixture(file_name)).unwrap().model);
+        let reloaded = model_from_ron(&saved).unwrap();
+        assert_eq!(reloaded.format_version, FORMAT_VERSION, "{}", file_name);
+        assert_eq!(normalised(&model_to_ron(&reloaded.model)), normalised(&saved), "{}", file_name);
+    }
+}
+
+#[test]
+fn newer_versions_are_rejected() {
+    let text = read_fixture("library_v3.dms").replacen(&format!("format_version: {}", FORMAT_VERSION), &format!("format_version: {}", FORMAT_VERSION + 1), 1);
+    let error = model_from_ron(&text).err().expect("a newer version cannot be read");
+    assert!(error.message.contains("upgrade DEMOsim"), "{}", error);
+}
+
+#[test]
+fn old_forms_are_rejected_in_the_current_version() {
+    // Version 2 with its map of the ADT, claiming to be the current version
+    let text = read_fixture("library_v2.dms").replacen("format_version: 2", &format!("format_version: {}", FORMAT_VERSION), 1);
+    assert!(model_from_ron(&text).is_err(), "the ADT of version 2 is not read as of version {}", FORMAT_VERSION);
+}
+
+#[test]
+fn sessions_of_version_2_are_upgraded() {
+    let model = model_from_ron(&read_fixture("library_v3.dms")).unwrap().model;
+    let (_, execution) = Engine::new(model.clone()).into_parts();
+    // The version 2 model file with the execution added to it
+    let model_file = read_fixture("library_v2.dms");
+    let text = format!("{},\n    execution: {},\n)\n", model_file.trim_end().trim_end_matches(')').trim_end().trim_end_matches(','), ron::to_string(&execution).unwrap());
+    let loaded = session_from_ron(&text).unwrap_or_else(|e| panic!("{}\n{}", e, text));
+    assert!(loaded.repairs.is_empty(), "{:?}", loaded.repairs);
+    assert_eq!(normalised(&model_to_ron(&loaded.session.model)), normalised(&model_to_ron(&model)));
+}
@@ -0,0 +1,111 @@
+//! Spreadsheets are read as CSV, quoted values and all, and rows that cannot be imported are reported by their line.
+
+use demosim::import::{import_links, import_transaction_product_table, RowError, Sheet};
+use demosim::model::{CAct, CFact, CPAct, Model};
+
+const TPT: &str = "\
+transaction id,transaction name,product,initiator,executor
+T01,Sale,Sale is completed,Customer,Seller
+T02,Delivery,Delivery is done,Seller,Courier
+";
+
+fn model_with_tpt() -> Model {
+    let mut model = Model::default();
+    let summary = import_transaction_product_table(&mut model, TPT);
+    assert_eq!(summary.errors, vec![]);
+    model
+}
+
+fn error(line: usize, message: &str) -> RowError {
+    RowError { line, message: message.to_string() }
+}
+
+#[test]
+fn quoted_values_keep_separators_quotes_and_line_breaks() {
+    let text = "\u{feff}Transaction ID,Name,Product,Initiator,Executor\r\n\
+                T01,\"Sale, retail\",\"The \"\"sale\"\" is\r\ncompleted\",Customer,Seller\r\n\
+                \r\n\
+                T02,Delivery,Delivery is done,Seller,Courier\r\n";
+    let mut model = Model::default();
+    let summary = import_transaction_product_table(&mut model, text);
+    assert_eq!((summary.transactions, summary.actor_roles), (2, 3));
+    assert_eq!(model.transactions[0].name, "Sale, retail");
+    assert_eq!(model.transactions[0].product, "The \"sale\" is\ncompleted");
+    assert_eq!(model.transactions[1].t_id, "T02");
+}
+
+#[test]
+fn semicolons_separate_when_the_header_has_more_of_them() {
+    let text = "transaction id;transaction name;product;initiator;executor\nT01;Sale, retail;Sale is completed;Customer;Seller\n";
+    let mut model = Model::default();
+    import_transaction_product_table(&mut model, text);
+    assert_eq!(model.transactions[0].name, "Sale, retail");
+    assert_eq!(Sheet::detect(text), Some(Sheet::TransactionProductTable));
+    assert_eq!(Sheet::detect("link;from transaction;from fact;to transaction;to act\n"), Some(Sheet::Links));
+    assert_eq!(Sheet::detect("case,activity\n"), None);
+}
+
+#[test]
+fn rows_are_reported_by_the_line_they_start_on() {
+    let text = "transaction id,transaction name,product,initiator,executor\n\
+                T01,Sale,\"Sale is\ncompleted\",Customer,Seller\n\
+                T01,Sale again,Sale,Customer,Seller\n\
+                ,Nameless,Nothing,Customer,Seller\n\
+                T03,Payment,Payment is made,,Seller\n";
+    let mut model = Model::default();
+    let summary = import_transaction_product_table(&mut model, text);
+    assert_eq!(summary.transactions, 1);
+    assert_eq!(summary.errors, vec![
+        error(4, "Transaction T01 already exists"),
+        error(5, "Missing transaction id"),
+        error(6, "Transaction T03 is missing its initiator or executor"),
+    ]);
+    let missing = import_transaction_product_table(&mut model, "transaction id,product\nT04,Something\n");
+    assert_eq!(missing.errors.len(), 1);
+    assert_eq!(missing.errors[0].line, 1);
+}
+
+#[test]
+fn links_are_added_to_the_transactions() {
+    let mut model = model_with_tpt();
+    let text = "link,from transaction,from fact,to transaction,to act,multiplicity\n\
+                initiation,T01,pm,T02,rq,0..*\n\
+                wait,T02,Accepted,T01,Declare,\n";
+    let summary = import_links(&mut model, text);
+    assert_eq!((summary.initiations, summary.impediments, summary.errors.len()), (1, 1, 0));
+    let initiation = &model.transactions[0].initiations[0];
+    assert_eq!((initiation.initiating_c_fact.clone(), initiation.initiated_c_act.clone()), (CFact::Promised, CAct::Request));
+    assert_eq!(initiation.multiplicity.to_string(), "0..*");
+    let impediment = &model.transactions[0].impediments[0];
+    assert_eq!((impediment.impeded_act.clone(), impediment.impeding_c_fact.clone()), (CPAct::CAct(CAct::Declare), CFact::Accepted));
+}
+
+#[test]
+fn invalid_and_repeated_links_are_rejected() {
+    let mut model = model_with_tpt();
+    let text = "link,from transaction,from fact,to transaction,to act,multiplicity\n\
+                initiation,T01,Promised,T02,Request,\n\
+                initiation,T01,Declared,T02,Request,\n\
+                initiation,T01,Promised,T01,Request,\n\
+                initiation,T01,Promised,T02,Execute,\n\
+                initiation,T01,Promised,T09,Request,\n\
+                initiation,T01,Promised,T02,Request,1-2\n\
+                wait,T02,Accepted,T01,Declare,\n\
+                wait,T02,ac,T01,da,\n\
+                wait,T02,Sent,T01,Declare,\n\
+                link,T02,Accepted,T01,Declare,\n";
+    let summary = import_links(&mut model, text);
+    assert_eq!((summary.initiations, summary.impediments), (1, 1));
+    assert_eq!(summary.errors, vec![
+        error(3, "Transaction T01 already initiates T02"),
+        error(4, "Transaction T01 cannot initiate itself"),
+        error(5, "A transaction cannot be initiated by its execution"),
+        error(6, "Unknown transaction \"T09\""),
+        error(7, "Invalid multiplicity \"1-2\", expected e.g. 1..1 or 0..*"),
+        error(9, "Declare in transaction T01 already waits for T02 Accepted"),
+        error(10, "Unknown C-fact \"Sent\""),
+        error(11, "Unknown link \"link\", expected initiation or wait"),
+    ]);
+    assert_eq!(model.transactions[0].initiations.len(), 1);
+    assert_eq!(model.transactions[0].impediments.len(), 1);
+}
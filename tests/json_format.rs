@@ -0,0 +1,78 @@
+//! JSON is an alternative representation of the same documents as RON: converting between them must not change
+//! the model or the execution, and the published JSON Schemas must describe what is written.
+
+use std::path::PathBuf;
+use demosim::engine::Engine;
+use demosim::model::{CAct, CPAct};
+use demosim::persistence::{
+    model_from_json, model_from_ron, model_json_schema, model_to_json, model_to_ron, session_from_json,
+    session_from_ron, session_json_schema, session_to_json, session_to_ron, Session,
+};
+
+fn read_file(dir: &str, file_name: &str) -> String {
+    let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), dir, file_name].iter().collect();
+    std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("cannot read {}: {}", path.display(), e))
+}
+
+fn ron_value(text: &str) -> ron::Value {
+    ron::from_str(text).expect("valid RON")
+}
+
+fn json_value(text: &str) -> serde_json::Value {
+    serde_json::from_str(text).expect("valid JSON")
+}
+
+fn played_session() -> Session {
+    let model = model_from_ron(&read_file("tests/fixtures", "library_v3.dms")).unwrap().model;
+    let membership = model.transactions.iter().find(|t| t.t_id == "T01").unwrap().id.clone();
+    let mut engine = Engine::new(model);
+    let alice = engine.subject_by_name("Alice").unwrap().id.clone();
+    let bob = engine.subject_by_name("Bob").unwrap().id.clone();
+    let t_i_id = engine.start_transaction(None, &membership, "Alice's membership", &alice, &bob).unwrap();
+    engine.perform(&t_i_id, &bob, CPAct::CAct(CAct::Promise)).unwrap();
+    let (model, execution) = engine.into_parts();
+    Session { model, execution }
+}
+
+#[test]
+fn model_survives_ron_to_json_and_back() {
+    for file_name in ["library_v1.dms", "library_v2.dms", "library_v3.dms"] {
+        let from_ron = model_from_ron(&read_file("tests/fixtures", file_name)).unwrap().model;
+        let json = model_to_json(&from_ron);
+        let from_json = model_from_json(&json).unwrap();
+        assert!(from_json.repairs.is_empty(), "{}: {:?}", file_name, from_json.repairs);
+        assert_eq!(ron_value(&model_to_ron(&from_json.model)), ron_value(&model_to_ron(&from_ron)), "{}", file_name);
+        assert_eq!(json_value(&model_to_json(&from_json.model)), json_value(&json), "{}", file_name);
+    }
+}
+
+#[test]
+fn session_survives_ron_to_json_and_back() {
+    let session = played_session();
+    let from_json = session_from_json(&session_to_json(&session)).unwrap();
+    assert!(from_json.repairs.is_empty(), "{:?}", from_json.repairs);
+    let from_ron = session_from_ron(&session_to_ron(&session)).unwrap();
+    assert_eq!(ron_value(&session_to_ron(&from_json.session)), ron_value(&session_to_ron(&from_ron.session)));
+    assert_eq!(ron_value(&session_to_ron(&from_json.session)), ron_value(&session_to_ron(&session)));
+    assert_eq!(from_json.session.execution.events().len(), 2);
+    assert_eq!(from_json.session.execution.c_p_world.len(), session.execution.c_p_world.len());
+}
+
+#[test]
+fn json_errors_have_a_position() {
+    let error = model_from_json("{\n  \"format_version\": 3,\n  \"model\": [\n}").err().expect("malformed JSON");
+    assert_eq!(error.position.map(|(line, _)| line), Some(4));
+}
+
+// Run with DEMOSIM_UPDATE_SCHEMA=1 to regenerate the published schemas after changing the model or the execution
+#[test]
+fn published_schemas_are_up_to_date() {
+    for (file_name, schema) in [("model.schema.json", model_json_schema()), ("session.schema.json", session_json_schema())] {
+        let generated = serde_json::to_string_pretty(&schema).unwrap() + "\n";
+        if std::env::var_os("DEMOSIM_UPDATE_SCHEMA").is_some() {
+            let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "schema", file_name].iter().collect();
+            std::fs::write(&path, &generated).unwrap();
+        }
+        assert_eq!(json_value(&read_file("schema", file_name)), json_value(&generated), "schema/{} is outdated", file_name);
+    }
+}
@@ -0,0 +1,205 @@
+//! The transaction pattern as the engine enforces it: revocations rolling instances back, the complete pattern with
+//! tacit acts, and the steps it rejects.
+
+mod common;
+
+use demosim::engine::Engine;
+use demosim::execution::{ExecutionError, TransactionInstance, TransactionInstanceId};
+use demosim::model::{CAct, CFact, CPAct, CPFact, Model, Subject, SubjectId, TransactionRole};
+use common::{sale_model, Sale};
+
+fn c(act: CAct) -> CPAct {
+    CPAct::CAct(act)
+}
+
+fn f(fact: CFact) -> CPFact {
+    CPFact::CFact(fact)
+}
+
+fn requested(sale: &Sale) -> (Engine, TransactionInstanceId) {
+    let mut engine = Engine::new(sale.model.clone());
+    let t_i_id = engine.start_transaction(None, &sale.sale, "Book", &sale.alice, &sale.bob).unwrap();
+    (engine, t_i_id)
+}
+
+type Acts<'a> = Vec<(&'a SubjectId, CPAct)>;
+
+// Performs the acts in turn, which all have to succeed
+fn perform(engine: &mut Engine, t_i_id: &TransactionInstanceId, acts: &[(&SubjectId, CPAct)]) {
+    for (subject_id, act) in acts {
+        engine.perform(t_i_id, subject_id, act.clone()).unwrap_or_else(|e| panic!("{} failed: {}", act, e));
+    }
+}
+
+fn agenda_acts(engine: &Engine, subject_id: &SubjectId, t_i_id: &TransactionInstanceId) -> Vec<CPAct> {
+    if engine.agenda(subject_id).unwrap().iter().any(|item| item.transaction_instance_id == *t_i_id) {
+        engine.available_acts(t_i_id, subject_id).unwrap().into_iter().filter(|act| !act.is_revocation()).collect()
+    } else {
+        Vec::new()
+    }
+}
+
+#[test]
+fn revocations_roll_back_to_their_targets() {
+    let sale = sale_model();
+    let (alice, bob) = (&sale.alice, &sale.bob);
+    let declared = [(bob, c(CAct::Promise)), (bob, CPAct::PAct), (bob, c(CAct::Declare))];
+    let up_to_declared = vec![f(CFact::Requested), f(CFact::Promised), CPFact::PFact, f(CFact::Declared)];
+    let cases: Vec<(Acts<'_>, CFact, Vec<CPFact>)> = vec![
+        (vec![(bob, c(CAct::Promise)), (bob, c(CAct::RevokePromise))], CFact::PromiseRevoked, vec![f(CFact::Requested)]),
+        (vec![(bob, c(CAct::Decline)), (bob, c(CAct::RevokeDecline))], CFact::DeclineRevoked, vec![f(CFact::Requested)]),
+        ([&declared[..], &[(bob, c(CAct::RevokeDeclare))]].concat(), CFact::DeclareRevoked, vec![f(CFact::Requested), f(CFact::Promised)]),
+        ([&declared[..], &[(alice, c(CAct::Accept)), (alice, c(CAct::RevokeAccept))]].concat(), CFact::AcceptRevoked, up_to_declared.clone()),
+        ([&declared[..], &[(alice, c(CAct::Reject)), (alice, c(CAct::RevokeReject))]].concat(), CFact::RejectRevoked, up_to_declared),
+    ];
+    for (acts, revoked, effective) in cases {
+        let (mut engine, t_i_id) = requested(&sale);
+        perform(&mut engine, &t_i_id, &acts);
+        assert_eq!(engine.state(&t_i_id).unwrap(), Some(f(revoked.clone())));
+        assert_eq!(engine.execution().get_effective_facts(&t_i_id), effective, "after {}", revoked);
+        // The transaction goes on from the target, on the agenda of whoever acts there
+        let target = f(revoked.rollback_target().unwrap());
+        let expected: Vec<CPAct> = target.next_acts().into_iter().filter(|act| !act.is_revocation()).collect();
+        let performer_id = if target.agenda_role() == Some(TransactionRole::Initiator) { alice } else { bob };
+        assert_eq!(agenda_acts(&engine, performer_id, &t_i_id), expected, "after {}", revoked);
+    }
+}
+
+#[test]
+fn revoked_declaration_withdraws_the_product() {
+    let sale = sale_model();
+    let bob = &sale.bob;
+    let (mut engine, t_i_id) = requested(&sale);
+    perform(&mut engine, &t_i_id, &[(bob, c(CAct::Promise)), (bob, CPAct::PAct), (bob, c(CAct::Declare)), (bob, c(CAct::RevokeDeclare))]);
+    assert!(!engine.execution().is_fact_reached(&t_i_id, &CPFact::PFact));
+    assert!(!engine.execution().is_fact_reached(&t_i_id, &f(CFact::Declared)));
+    // The product has to be produced again before it can be declared
+    assert_eq!(agenda_acts(&engine, bob, &t_i_id), vec![CPAct::PAct]);
+    assert!(engine.perform(&t_i_id, bob, c(CAct::Declare)).is_err());
+    perform(&mut engine, &t_i_id, &[(bob, CPAct::PAct), (bob, c(CAct::Declare)), (&sale.alice, c(CAct::Accept))]);
+    let effective = engine.execution().get_effective_facts(&t_i_id);
+    assert_eq!(effective, vec![f(CFact::Requested), f(CFact::Promised), CPFact::PFact, f(CFact::Declared), f(CFact::Accepted)]);
+}
+
+#[test]
+fn revoked_request_cancels_the_instance() {
+    let sale = sale_model();
+    let (mut engine, t_i_id) = requested(&sale);
+    perform(&mut engine, &t_i_id, &[(&sale.bob, c(CAct::Promise)), (&sale.alice, c(CAct::RevokeRequest))]);
+    assert!(engine.execution().is_cancelled(&t_i_id));
+    assert!(engine.execution().get_effective_facts(&t_i_id).is_empty());
+    assert!(engine.agenda(&sale.alice).unwrap().is_empty() && engine.agenda(&sale.bob).unwrap().is_empty());
+    assert!(engine.available_acts(&t_i_id, &sale.alice).unwrap().is_empty());
+}
+
+#[test]
+fn cancelled_subtransactions_do_not_count_against_the_multiplicity() {
+    let sale = sale_model().with_multiplicity("1..1");
+    let (mut engine, t_i_id) = requested(&sale);
+    perform(&mut engine, &t_i_id, &[(&sale.bob, c(CAct::Promise))]);
+    let delivery = engine.start_transaction(Some(&t_i_id), &sale.delivery, "Delivery", &sale.bob, &sale.carol).unwrap();
+    assert!(engine.startable_transactions(&sale.bob, Some(&t_i_id)).unwrap().is_empty());
+    perform(&mut engine, &delivery, &[(&sale.bob, c(CAct::RevokeRequest))]);
+    let startable: Vec<_> = engine.startable_transactions(&sale.bob, Some(&t_i_id)).unwrap().into_iter().map(|t| t.id.clone()).collect();
+    assert_eq!(startable, vec![sale.delivery.clone()]);
+    engine.start_transaction(Some(&t_i_id), &sale.delivery, "Delivery again", &sale.bob, &sale.carol).unwrap();
+}
+
+#[test]
+fn decline_and_reject_lead_to_quit_and_stop_or_another_try() {
+    let sale = sale_model();
+    let (alice, bob) = (&sale.alice, &sale.bob);
+    let declared = [(bob, c(CAct::Promise)), (bob, CPAct::PAct), (bob, c(CAct::Declare))];
+    let cases: Vec<(Acts<'_>, CPFact)> = vec![
+        (vec![(bob, c(CAct::Decline)), (alice, c(CAct::Quit))], f(CFact::Quitted)),
+        (vec![(bob, c(CAct::Decline)), (alice, c(CAct::Request)), (bob, c(CAct::Promise))], f(CFact::Promised)),
+        ([&declared[..], &[(alice, c(CAct::Reject)), (bob, c(CAct::Stop))]].concat(), f(CFact::Stopped)),
+        ([&declared[..], &[(alice, c(CAct::Reject)), (bob, c(CAct::Declare)), (alice, c(CAct::Accept))]].concat(), f(CFact::Accepted)),
+    ];
+    for (acts, state) in cases {
+        let (mut engine, t_i_id) = requested(&sale);
+        perform(&mut engine, &t_i_id, &acts);
+        assert_eq!(engine.state(&t_i_id).unwrap(), Some(state.clone()));
+        if state != f(CFact::Promised) {
+            // Ended: nothing left to do but to revoke
+            assert!(engine.agenda(alice).unwrap().is_empty() && engine.agenda(bob).unwrap().is_empty(), "after {}", state);
+        }
+    }
+}
+
+#[test]
+fn tacit_acts_follow_each_other_until_someone_has_to_act() {
+    let mut sale = sale_model();
+    let sale_id = sale.sale.clone();
+    sale.transaction_mut(&sale_id).tacit_acts = vec![CAct::Promise, CAct::Accept];
+    let (mut engine, t_i_id) = requested(&sale);
+    assert_eq!(engine.state(&t_i_id).unwrap(), Some(f(CFact::Promised)));
+    assert_eq!(agenda_acts(&engine, &sale.bob, &t_i_id), vec![CPAct::PAct]);
+    perform(&mut engine, &t_i_id, &[(&sale.bob, CPAct::PAct), (&sale.bob, c(CAct::Declare))]);
+    assert_eq!(engine.state(&t_i_id).unwrap(), Some(f(CFact::Accepted)));
+    let tacit: Vec<bool> = engine.execution().c_p_world.iter().map(|item| item.is_tacit()).collect();
+    assert_eq!(tacit, vec![false, true, false, false, true]);
+}
+
+#[test]
+fn revoked_tacit_act_is_not_repeated() {
+    let mut sale = sale_model();
+    let sale_id = sale.sale.clone();
+    sale.transaction_mut(&sale_id).tacit_acts = vec![CAct::Promise];
+    let (mut engine, t_i_id) = requested(&sale);
+    perform(&mut engine, &t_i_id, &[(&sale.bob, c(CAct::RevokePromise))]);
+    assert_eq!(engine.state(&t_i_id).unwrap(), Some(f(CFact::PromiseRevoked)));
+    assert_eq!(agenda_acts(&engine, &sale.bob, &t_i_id), vec![c(CAct::Promise), c(CAct::Decline)]);
+}
+
+#[test]
+fn impeded_tacit_act_waits_on_the_agenda() {
+    let mut sale = sale_model().with_delivery_before_declare();
+    let sale_id = sale.sale.clone();
+    sale.transaction_mut(&sale_id).tacit_acts = vec![CAct::Declare];
+    let (mut engine, t_i_id) = requested(&sale);
+    perform(&mut engine, &t_i_id, &[(&sale.bob, c(CAct::Promise)), (&sale.bob, CPAct::PAct)]);
+    assert_eq!(engine.state(&t_i_id).unwrap(), Some(CPFact::PFact));
+    assert_eq!(agenda_acts(&engine, &sale.bob, &t_i_id), vec![c(CAct::Declare)]);
+}
+
+#[test]
+fn check_transition_tells_what_is_wrong() {
+    let sale = sale_model().with_delivery_before_declare();
+    let (mut engine, t_i_id) = requested(&sale);
+    perform(&mut engine, &t_i_id, &[(&sale.bob, c(CAct::Promise)), (&sale.bob, CPAct::PAct)]);
+    let (model, execution) = (engine.model(), engine.execution());
+    let check = |model: &Model, t_i_id: &TransactionInstanceId, subject_id: &SubjectId, act: CPAct| execution.check_transition(model, t_i_id, subject_id, &act.to_fact());
+
+    let other = TransactionInstance::new(None, sale.sale.clone(), "Other".to_string(), sale.alice.clone(), sale.bob.clone());
+    assert_eq!(check(model, &other.id, &sale.bob, c(CAct::Declare)), Err(ExecutionError::UnknownTransactionInstance(other.id.clone())));
+    assert_eq!(check(&Model::default(), &t_i_id, &sale.bob, c(CAct::Declare)), Err(ExecutionError::UnknownTransaction(sale.sale.clone())));
+    let stranger = Subject::default();
+    assert_eq!(check(model, &t_i_id, &stranger.id, c(CAct::Declare)), Err(ExecutionError::UnknownSubject(stranger.id.clone())));
+    assert_eq!(
+        check(model, &t_i_id, &sale.alice, c(CAct::Accept)),
+        Err(ExecutionError::IllegalTransition { transaction_instance_id: t_i_id.clone(), state: Some(CPFact::PFact), act: c(CAct::Accept) }),
+    );
+    assert_eq!(
+        check(model, &t_i_id, &sale.alice, c(CAct::Declare)),
+        Err(ExecutionError::WrongPerformer { transaction_instance_id: t_i_id.clone(), performer_id: sale.alice.clone(), act: c(CAct::Declare), required_role: TransactionRole::Executor }),
+    );
+    let mut unauthorised = model.clone();
+    unauthorised.adt.mappings.retain(|(_, subject_id), _| *subject_id != sale.bob);
+    let seller = model.find_transaction(&sale.sale).unwrap().executor_id.clone();
+    assert_eq!(check(&unauthorised, &t_i_id, &sale.bob, c(CAct::Declare)), Err(ExecutionError::NotAuthorised { subject_id: sale.bob.clone(), actor_role_id: seller }));
+    assert!(matches!(check(model, &t_i_id, &sale.bob, c(CAct::Declare)), Err(ExecutionError::Impeded { act, reasons }) if act == c(CAct::Declare) && reasons.len() == 1));
+    assert_eq!(check(model, &t_i_id, &sale.bob, c(CAct::RevokePromise)), Ok(()));
+}
+
+#[test]
+fn rejected_step_changes_nothing() {
+    let sale = sale_model();
+    let (mut engine, t_i_id) = requested(&sale);
+    let (c_p_world, agendas, events) = (engine.execution().c_p_world.clone(), engine.execution().agendas.clone(), engine.execution().events().len());
+    assert!(engine.perform(&t_i_id, &sale.alice, c(CAct::Promise)).is_err());
+    assert!(engine.perform(&t_i_id, &sale.bob, c(CAct::Declare)).is_err());
+    assert_eq!(engine.execution().c_p_world, c_p_world);
+    assert_eq!(engine.execution().agendas, agendas);
+    assert_eq!(engine.execution().events().len(), events);
+}
@@ -0,0 +1,144 @@
+//! Steps are taken back and performed again through the event log, ending up where rebuilding the execution from the
+//! log does.
+
+mod common;
+
+use chrono::{DateTime, Duration, Utc};
+use demosim::engine::Engine;
+use demosim::execution::{AgendaItem, CPWorldItem, Execution, TransactionInstanceId};
+use demosim::model::{CAct, CFact, CPAct, CPFact, Deadline, SubjectId};
+use common::{sale_model, Sale};
+
+#[derive(Debug, PartialEq)]
+struct State {
+    instances: Vec<TransactionInstanceId>,
+    c_p_world: Vec<CPWorldItem>,
+    agendas: Vec<(SubjectId, AgendaItem)>,
+    now: DateTime<Utc>,
+}
+
+fn state(execution: &Execution) -> State {
+    State {
+        instances: execution.transactions_instances.iter().map(|t_i| t_i.id.clone()).collect(),
+        c_p_world: execution.c_p_world.clone(),
+        agendas: execution.agendas.clone(),
+        now: execution.now(),
+    }
+}
+
+fn rebuilt(engine: &Engine) -> State {
+    let mut execution = engine.execution().clone();
+    execution.restore(engine.model());
+    state(&execution)
+}
+
+// Performs the steps of a sale with a delivery, a deadline expiring on the way, and returns the states after each
+fn perform_sale(sale: &Sale, engine: &mut Engine) -> Vec<State> {
+    let mut states = vec![state(engine.execution())];
+    let sale_instance = engine.start_transaction(None, &sale.sale, "Book", &sale.alice, &sale.bob).unwrap();
+    states.push(state(engine.execution()));
+    engine.perform(&sale_instance, &sale.bob, CPAct::CAct(CAct::Promise)).unwrap();
+    states.push(state(engine.execution()));
+    let delivery_instance = engine.start_transaction(Some(&sale_instance), &sale.delivery, "Book / Delivery", &sale.bob, &sale.carol).unwrap();
+    states.push(state(engine.execution()));
+    // The delivery is declined on expiry of its deadline
+    engine.advance_clock(Duration::minutes(90));
+    states.push(state(engine.execution()));
+    engine.perform(&delivery_instance, &sale.bob, CPAct::CAct(CAct::Request)).unwrap();
+    states.push(state(engine.execution()));
+    let acts = [
+        (&sale.carol, CPAct::CAct(CAct::Promise)),
+        (&sale.carol, CPAct::PAct),
+        (&sale.carol, CPAct::CAct(CAct::Declare)),
+        (&sale.bob, CPAct::CAct(CAct::Accept)),
+    ];
+    for (subject_id, act) in acts {
+        engine.perform(&delivery_instance, subject_id, act).unwrap();
+        states.push(state(engine.execution()));
+    }
+    states
+}
+
+fn sale_with_deadline() -> Sale {
+    let mut sale = sale_model();
+    let delivery = sale.delivery.clone();
+    sale.transaction_mut(&delivery).deadlines = vec![Deadline { act: Some(CPAct::CAct(CAct::Promise)), within_minutes: 60, on_expiry: Some(CAct::Decline) }];
+    sale
+}
+
+#[test]
+fn undo_and_redo_go_through_the_same_states() {
+    let sale = sale_with_deadline();
+    let mut engine = Engine::new(sale.model.clone());
+    let states = perform_sale(&sale, &mut engine);
+    assert_eq!(rebuilt(&engine), *states.last().unwrap());
+    for expected in states.iter().rev().skip(1) {
+        assert!(engine.undo());
+        assert_eq!(state(engine.execution()), *expected);
+        assert_eq!(rebuilt(&engine), *expected);
+    }
+    assert!(!engine.undo());
+    for expected in states.iter().skip(1) {
+        assert!(engine.redo());
+        assert_eq!(state(engine.execution()), *expected);
+        assert_eq!(rebuilt(&engine), *expected);
+    }
+    assert!(!engine.redo());
+}
+
+#[test]
+fn new_step_drops_the_undone_ones() {
+    let sale = sale_with_deadline();
+    let mut engine = Engine::new(sale.model.clone());
+    let states = perform_sale(&sale, &mut engine);
+    for _ in 0..3 {
+        engine.undo();
+    }
+    engine.advance_clock(Duration::minutes(5));
+    assert!(!engine.redo());
+    assert_ne!(state(engine.execution()), states[states.len() - 4]);
+    assert_eq!(rebuilt(&engine), state(engine.execution()));
+}
+
+#[test]
+fn deletion_takes_the_subtransactions_along_and_is_undone_at_once() {
+    let sale = sale_model();
+    let mut engine = Engine::new(sale.model.clone());
+    let sale_instance = engine.start_transaction(None, &sale.sale, "Book", &sale.alice, &sale.bob).unwrap();
+    engine.perform(&sale_instance, &sale.bob, CPAct::CAct(CAct::Promise)).unwrap();
+    engine.start_transaction(Some(&sale_instance), &sale.delivery, "Book / Delivery", &sale.bob, &sale.carol).unwrap();
+    let before = state(engine.execution());
+
+    let (model, mut execution) = engine.into_parts();
+    execution.delete_transaction_instance(&sale_instance);
+    assert!(execution.transactions_instances.is_empty());
+    assert!(execution.c_p_world.is_empty());
+    assert!(execution.agendas.is_empty());
+    let mut engine = Engine::with_execution(model, execution);
+    assert_eq!(rebuilt(&engine), state(engine.execution()));
+
+    assert!(engine.undo());
+    assert_eq!(state(engine.execution()), before);
+}
+
+#[test]
+fn event_log_of_an_older_execution_is_built_when_restored() {
+    let sale = sale_model();
+    let mut engine = Engine::new(sale.model.clone());
+    let sale_instance = engine.start_transaction(None, &sale.sale, "Book", &sale.alice, &sale.bob).unwrap();
+    engine.perform(&sale_instance, &sale.bob, CPAct::CAct(CAct::Promise)).unwrap();
+
+    // Stored before the events were introduced: just the derived state
+    let mut value = serde_json::to_value(engine.execution()).unwrap();
+    value.as_object_mut().unwrap().remove("events");
+    let mut execution: Execution = serde_json::from_value(value).unwrap();
+    assert!(!execution.can_undo());
+    assert!(!execution.undo(&sale.model));
+    assert_eq!(execution.c_p_world.len(), 2);
+
+    assert_eq!(execution.restore(&sale.model), 0);
+    assert_eq!(execution.events().len(), 2);
+    assert!(execution.undo(&sale.model));
+    assert_eq!(execution.c_p_world.len(), 1);
+    assert_eq!(execution.c_p_world[0].to_fact(), CPFact::CFact(CFact::Requested));
+}
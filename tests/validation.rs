@@ -0,0 +1,157 @@
+//! Models with problems are diagnosed, errors first, and broken references can be repaired.
+
+mod common;
+
+use demosim::model::{ActorRole, AdtOption, CAct, CFact, CPAct, Impediment, Initiation, Model, Multiplicity, Policy, PolicyRule, CPFact, Subject, Transaction};
+use demosim::persistence::{model_from_ron, model_to_ron};
+use demosim::validation::{ModelEntity, Severity};
+use common::sale_model;
+
+fn messages(model: &Model) -> Vec<(Severity, String)> {
+    model.validate().into_iter().map(|diagnostic| (diagnostic.severity, diagnostic.message)).collect()
+}
+
+fn has(model: &Model, severity: Severity, message: &str) -> bool {
+    messages(model).contains(&(severity, message.to_string()))
+}
+
+#[test]
+fn sale_model_has_no_problems() {
+    assert_eq!(messages(&sale_model().model), vec![]);
+}
+
+#[test]
+fn errors_come_first() {
+    let mut sale = sale_model();
+    let (sale_id, delivery_id) = (sale.sale.clone(), sale.delivery.clone());
+    sale.model.subjects.push(Subject::default());
+    sale.transaction_mut(&sale_id).initiator_id = ActorRole::default().id;
+    let diagnostics = sale.model.validate();
+    assert_eq!(diagnostics.len(), 4);
+    assert_eq!(diagnostics[0].severity, Severity::Error);
+    assert_eq!(diagnostics[0].entity, ModelEntity::Transaction(sale_id));
+    assert!(diagnostics[1..].iter().all(|diagnostic| diagnostic.severity == Severity::Warning));
+    // Besides the error: the customer takes part in nothing any more, the new subject has neither a name nor a role
+    assert!(diagnostics.iter().all(|diagnostic| diagnostic.entity != ModelEntity::Transaction(delivery_id.clone())));
+}
+
+#[test]
+fn actor_roles_and_subjects_are_checked() {
+    let mut sale = sale_model();
+    let delivery_id = sale.delivery.clone();
+    let seller = sale.model.actor_roles[1].id.clone();
+    sale.transaction_mut(&delivery_id).executor_id = seller;
+    sale.model.actor_roles.push(ActorRole { name: " ".to_string(), ..Default::default() });
+    sale.model.subjects.push(Subject { name: "Dave".to_string(), ..Default::default() });
+    let model = &sale.model;
+    assert!(has(model, Severity::Warning, "Actor role has no name"));
+    assert!(has(model, Severity::Warning, "No subject is authorised for actor role   in the ADT"));
+    assert!(has(model, Severity::Warning, "Actor role Seller executes more than one transaction: T01, T02"));
+    assert!(has(model, Severity::Warning, "Actor role Courier takes part in no transaction"));
+    assert!(has(model, Severity::Warning, "Subject Dave fulfils no actor role"));
+}
+
+#[test]
+fn transactions_are_checked() {
+    let mut sale = sale_model();
+    let (sale_id, delivery_id) = (sale.sale.clone(), sale.delivery.clone());
+    let missing = Transaction::new();
+    let delivery = sale.transaction_mut(&delivery_id);
+    delivery.t_id = "T01".to_string();
+    delivery.executor_id = ActorRole::default().id;
+    delivery.impediments.push(Impediment { impeded_act: CPAct::CAct(CAct::Promise), impeding_transaction_id: missing.id.clone(), impeding_c_fact: CFact::Accepted });
+    // The delivery initiates the sale, which initiates the delivery
+    delivery.initiations.push(Initiation {
+        initiating_c_fact: CFact::Promised,
+        initiated_transaction_id: sale_id.clone(),
+        initiated_c_act: CAct::Request,
+        multiplicity: "2..1".parse().unwrap(),
+        multiplicity_tmp_str: String::new(),
+    });
+    let sale_transaction = sale.transaction_mut(&sale_id);
+    sale_transaction.initiations.push(Initiation { initiated_transaction_id: missing.id.clone(), ..sale_transaction.initiations[0].clone() });
+    let model = &sale.model;
+    assert!(has(model, Severity::Warning, "Transaction id T01 is used more than once"));
+    assert!(has(model, Severity::Error, "The executor of transaction T01 is not an existing actor role"));
+    assert!(has(model, Severity::Error, "Promise in transaction T01 waits for a transaction that does not exist"));
+    assert!(has(model, Severity::Error, "Transaction T01 initiates a transaction that does not exist"));
+    assert!(has(model, Severity::Error, "Multiplicity of the initiation of T01 from T01 has minimum greater than maximum"));
+    assert!(has(model, Severity::Error, "Transaction T01 initiates itself (directly or through its subtransactions)"));
+    assert!(messages(model).iter().all(|(_, message)| !message.contains("initiated from more than one")));
+}
+
+#[test]
+fn transaction_initiated_from_two_parents_is_reported() {
+    let mut sale = sale_model();
+    let mut payment = Transaction::new();
+    payment.t_id = "T03".to_string();
+    payment.initiator_id = sale.model.actor_roles[0].id.clone();
+    payment.executor_id = sale.model.actor_roles[1].id.clone();
+    payment.initiations.push(Initiation { multiplicity: Multiplicity::default(), ..sale.model.transactions[0].initiations[0].clone() });
+    sale.model.transactions.push(payment);
+    assert!(has(&sale.model, Severity::Warning, "Transaction T02 is initiated from more than one transaction (T01, T03); only the initiation from T01 is used"));
+}
+
+// A model as left by a buggy editor: references to an actor role, a transaction and a subject that are gone
+fn broken_model() -> Model {
+    let mut sale = sale_model();
+    let (sale_id, delivery_id, carol) = (sale.sale.clone(), sale.delivery.clone(), sale.carol.clone());
+    let courier = sale.model.actor_roles.remove(2);
+    sale.model.transactions.retain(|t| t.id != delivery_id);
+    sale.model.subjects.retain(|s| s.id != carol);
+    sale.transaction_mut(&sale_id).impediments.push(Impediment { impeded_act: CPAct::CAct(CAct::Declare), impeding_transaction_id: delivery_id.clone(), impeding_c_fact: CFact::Accepted });
+    let rules = vec![PolicyRule { transaction_id: Some(delivery_id.clone()), fact: CPFact::CFact(CFact::Requested), act: CPAct::CAct(CAct::Promise), probability_percent: 100 }];
+    sale.model.policies.insert(sale.bob.clone(), Policy { automated: true, rules: rules.clone() });
+    sale.model.policies.insert(carol.clone(), Policy { automated: true, rules });
+    sale.model.adt.mappings.insert((courier.id.clone(), sale.bob.clone()), AdtOption::Authorisation);
+    sale.model.ocd_layout.transactions.insert(delivery_id, Default::default());
+    sale.model.ocd_layout.actor_roles.insert(courier.id, Default::default());
+    // The sale's executor is lost too
+    sale.model.actor_roles.remove(1);
+    sale.model
+}
+
+#[test]
+fn broken_references_are_diagnosed() {
+    let model = broken_model();
+    assert!(has(&model, Severity::Error, "The executor of transaction T01 is not an existing actor role"));
+    assert!(has(&model, Severity::Error, "Transaction T01 initiates a transaction that does not exist"));
+    assert!(has(&model, Severity::Error, "Declare in transaction T01 waits for a transaction that does not exist"));
+    assert!(has(&model, Severity::Warning, "The policy of subject Bob refers to a transaction that does not exist"));
+    assert!(has(&model, Severity::Warning, "The ADT refers to a deleted actor role or subject"));
+}
+
+#[test]
+fn repair_removes_broken_references() {
+    let mut model = broken_model();
+    let repairs = model.repair();
+    assert_eq!(repairs, vec![
+        "Removed initiations of missing transactions from transaction T01".to_string(),
+        "Removed wait links to missing transactions from transaction T01".to_string(),
+        "Removed 3 ADT entries of missing actor roles or subjects".to_string(),
+        "Removed policies of missing subjects".to_string(),
+        "Removed policy rules of subject Bob referring to missing transactions".to_string(),
+    ]);
+    // The lost executor is not made up, it is left to be chosen again
+    let errors: Vec<String> = model.validate().into_iter().filter(|d| d.severity == Severity::Error).map(|d| d.message).collect();
+    assert_eq!(errors, vec!["The executor of transaction T01 is not an existing actor role".to_string()]);
+    assert_eq!(model.actor_roles.len(), 1);
+    assert!(model.ocd_layout.transactions.is_empty());
+    assert!(model.ocd_layout.actor_roles.is_empty());
+    // Nothing left to repair
+    assert_eq!(model.repair(), Vec::<String>::new());
+}
+
+#[test]
+fn new_transaction_survives_saving_and_loading() {
+    let mut sale = sale_model();
+    sale.model.transactions.push(Transaction::new());
+    let actor_roles = sale.model.actor_roles.clone();
+    let loaded = model_from_ron(&model_to_ron(&sale.model)).unwrap();
+    assert_eq!(loaded.repairs, Vec::<String>::new());
+    assert_eq!(loaded.model.actor_roles, actor_roles);
+    let reloaded = model_from_ron(&model_to_ron(&loaded.model)).unwrap();
+    assert_eq!(reloaded.model.actor_roles, actor_roles);
+    // Its roles are still to be chosen
+    assert!(has(&reloaded.model, Severity::Error, "The initiator of transaction  is not an existing actor role"));
+}